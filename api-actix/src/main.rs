@@ -1,64 +1,137 @@
+use actix_web::http::StatusCode;
 use actix_web::http::header::CONTENT_TYPE;
-use actix_web::{App, HttpResponse, HttpServer, Responder, post, web};
+use actix_web::{App, HttpRequest, HttpResponse, HttpServer, Responder, post, web};
+use futures_util::StreamExt;
 use serde::Serialize;
-use shared_lib::{parse, pretty_print_token};
+use shared_lib::{
+    Json, JsonPatch, JsonPushParser, PatchError, pretty_print_token, to_json_string, to_string,
+};
+
+/// Largest accepted request body, the same as the axum and rocket servers.
+const BODY_LIMIT: usize = 2 * 1024 * 1024;
 
 #[derive(Serialize)]
 struct ErrorResponse {
     code: u16,
     message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    line: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    column: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    offset: Option<usize>,
 }
 
-#[post("/api/v1/parse")]
-async fn parse_endpoint(req_body: web::Bytes, req: actix_web::HttpRequest) -> impl Responder {
-    let content_type = req
-        .headers()
-        .get(CONTENT_TYPE)
-        .and_then(|ct| ct.to_str().ok());
+impl ErrorResponse {
+    fn new(code: u16, message: impl Into<String>) -> Self {
+        ErrorResponse {
+            code,
+            message: message.into(),
+            line: None,
+            column: None,
+            offset: None,
+        }
+    }
 
-    if content_type != Some("text/plain") {
-        let err = ErrorResponse {
-            code: 415,
-            message: "Unsupported Media Type".to_string(),
-        };
-        return HttpResponse::UnsupportedMediaType()
+    fn reply(self) -> HttpResponse {
+        let status = StatusCode::from_u16(self.code).unwrap_or(StatusCode::BAD_REQUEST);
+        HttpResponse::build(status)
             .insert_header((CONTENT_TYPE, "application/json"))
-            .json(err);
+            .body(to_string(&self).expect("error responses are plain data"))
     }
+}
 
-    let text = match String::from_utf8(req_body.to_vec()) {
-        Ok(s) => s,
-        Err(e) => {
-            let err = ErrorResponse {
-                code: 400,
-                message: format!("Invalid UTF-8: {}", e),
-            };
-            return HttpResponse::BadRequest()
-                .insert_header((CONTENT_TYPE, "application/json"))
-                .json(err);
+/// Parse the request body as it streams in, so that malformed bodies fail
+/// early.
+async fn read_json(mut payload: web::Payload) -> Result<Json, HttpResponse> {
+    let mut parser = JsonPushParser::new();
+    let mut length = 0;
+    let result = loop {
+        let chunk = match payload.next().await {
+            Some(Ok(chunk)) => chunk,
+            Some(Err(e)) => {
+                return Err(ErrorResponse::new(400, format!("Failed to read body: {}", e)).reply());
+            }
+            None => break parser.finish(),
+        };
+        length += chunk.len();
+        if length > BODY_LIMIT {
+            return Err(ErrorResponse::new(413, "Payload Too Large").reply());
+        }
+        parser.feed(&chunk);
+        match parser.next_value() {
+            Ok(Some(json)) => break Ok(json),
+            Ok(None) => {}
+            Err(e) => break Err(e),
         }
     };
 
-    match parse(&text) {
+    result.map_err(|e| {
+        ErrorResponse {
+            code: 400,
+            message: e.to_string(),
+            line: Some(e.line),
+            column: Some(e.column),
+            offset: Some(e.offset),
+        }
+        .reply()
+    })
+}
+
+fn content_type(req: &HttpRequest) -> Option<&str> {
+    req.headers()
+        .get(CONTENT_TYPE)
+        .and_then(|ct| ct.to_str().ok())
+}
+
+#[post("/api/v1/parse")]
+async fn parse_endpoint(payload: web::Payload, req: HttpRequest) -> impl Responder {
+    if content_type(&req) != Some("text/plain") {
+        return ErrorResponse::new(415, "Unsupported Media Type").reply();
+    }
+
+    match read_json(payload).await {
         Ok(result) => HttpResponse::Ok()
             .insert_header((CONTENT_TYPE, "text/plain"))
             .body(pretty_print_token(&result.token, 0)),
-        Err(e) => {
-            let err = ErrorResponse {
-                code: 400,
-                message: e.to_string(),
-            };
-            HttpResponse::BadRequest()
-                .insert_header((CONTENT_TYPE, "application/json"))
-                .json(err)
+        Err(response) => response,
+    }
+}
+
+/// Apply an RFC 6902 patch. The body is `{"document": ..., "patch": [...]}`
+/// and the response is the patched document.
+#[post("/api/v1/patch")]
+async fn patch_endpoint(payload: web::Payload, req: HttpRequest) -> impl Responder {
+    if content_type(&req) != Some("application/json") {
+        return ErrorResponse::new(415, "Unsupported Media Type").reply();
+    }
+
+    let request = match read_json(payload).await {
+        Ok(request) => request,
+        Err(response) => return response,
+    };
+    let (Some(document), Some(patch)) = (request.pointer("/document"), request.pointer("/patch"))
+    else {
+        return ErrorResponse::new(400, "Expected \"document\" and \"patch\" members").reply();
+    };
+    let mut document = document.clone();
+    match JsonPatch::from_token(patch).and_then(|patch| patch.apply(&mut document)) {
+        Ok(()) => HttpResponse::Ok()
+            .insert_header((CONTENT_TYPE, "application/json"))
+            .body(to_json_string(&document)),
+        // RFC 5789: a malformed patch is unprocessable, a patch that does not
+        // fit the document is a conflict.
+        Err(e @ (PatchError::NoValue { .. } | PatchError::TestFailed { .. })) => {
+            ErrorResponse::new(409, e.to_string()).reply()
         }
+        Err(e) => ErrorResponse::new(422, e.to_string()).reply(),
     }
 }
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
     println!("Server running on http://localhost:8000");
-    HttpServer::new(|| App::new().service(parse_endpoint))
+    HttpServer::new(|| App::new().service(parse_endpoint).service(patch_endpoint))
         .bind("0.0.0.0:8000")?
         .run()
         .await
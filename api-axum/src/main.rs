@@ -1,50 +1,161 @@
-use axum::{Router, http::StatusCode, response::IntoResponse, routing::post};
+use axum::{Router, body::Body, http::StatusCode, response::IntoResponse, routing::post};
 use axum_extra::{TypedHeader, headers::ContentType};
-use serde_json::json;
-use shared_lib::{parse, pretty_print_token};
+use futures_util::StreamExt;
+use serde::Serialize;
+use shared_lib::{
+    Json, JsonPatch, JsonPushParser, PatchError, pretty_print_token, to_json_string, to_string,
+};
+
+/// Largest accepted request body, the same as axum's default for buffered
+/// bodies.
+const BODY_LIMIT: usize = 2 * 1024 * 1024;
+
+type Reply = (StatusCode, [(&'static str, &'static str); 1], String);
+
+#[derive(Serialize)]
+struct ErrorResponse {
+    code: u16,
+    message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    line: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    column: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    offset: Option<usize>,
+}
+
+impl ErrorResponse {
+    fn reply(self, status: StatusCode) -> Reply {
+        (
+            status,
+            [("content-type", "application/json")],
+            to_string(&self).expect("error responses are plain data"),
+        )
+    }
+}
+
+fn error_reply(status: StatusCode, message: impl Into<String>) -> Reply {
+    ErrorResponse {
+        code: status.as_u16(),
+        message: message.into(),
+        line: None,
+        column: None,
+        offset: None,
+    }
+    .reply(status)
+}
+
+/// Parse the request body as it streams in, so that malformed bodies fail
+/// early.
+async fn read_json(body: Body) -> Result<Json, Reply> {
+    let mut stream = body.into_data_stream();
+    let mut parser = JsonPushParser::new();
+    let mut length = 0;
+    let result = loop {
+        let chunk = match stream.next().await {
+            Some(Ok(chunk)) => chunk,
+            Some(Err(e)) => {
+                return Err(error_reply(
+                    StatusCode::BAD_REQUEST,
+                    format!("Failed to read body: {}", e),
+                ));
+            }
+            None => break parser.finish(),
+        };
+        length += chunk.len();
+        if length > BODY_LIMIT {
+            return Err(error_reply(
+                StatusCode::PAYLOAD_TOO_LARGE,
+                "Payload Too Large",
+            ));
+        }
+        parser.feed(&chunk);
+        match parser.next_value() {
+            Ok(Some(json)) => break Ok(json),
+            Ok(None) => {}
+            Err(e) => break Err(e),
+        }
+    };
+
+    result.map_err(|e| {
+        ErrorResponse {
+            code: 400,
+            message: e.to_string(),
+            line: Some(e.line),
+            column: Some(e.column),
+            offset: Some(e.offset),
+        }
+        .reply(StatusCode::BAD_REQUEST)
+    })
+}
 
 async fn parse_endpoint(
-    (content_type, body): (Option<TypedHeader<ContentType>>, axum::body::Bytes),
+    (content_type, body): (Option<TypedHeader<ContentType>>, Body),
 ) -> impl IntoResponse {
     match content_type {
         Some(TypedHeader(ct)) if ct == ContentType::from(mime::TEXT_PLAIN) => {}
         _ => {
-            return (
-                StatusCode::UNSUPPORTED_MEDIA_TYPE,
-                [("content-type", "application/json")],
-                json!({ "code": 415, "message": "Unsupported Media Type" }).to_string(),
-            );
+            return error_reply(StatusCode::UNSUPPORTED_MEDIA_TYPE, "Unsupported Media Type");
         }
     }
 
-    let text = match String::from_utf8(body.to_vec()) {
-        Ok(s) => s,
-        Err(e) => {
-            return (
-                StatusCode::BAD_REQUEST,
-                [("content-type", "application/json")],
-                json!({ "code": 400, "message": format!("Invalid UTF-8: {}", e) }).to_string(),
-            );
-        }
-    };
-
-    match parse(&text) {
+    match read_json(body).await {
         Ok(result) => (
             StatusCode::OK,
             [("content-type", "text/plain")],
             pretty_print_token(&result.token, 0),
         ),
-        Err(e) => (
+        Err(reply) => reply,
+    }
+}
+
+/// Apply an RFC 6902 patch. The body is `{"document": ..., "patch": [...]}`
+/// and the response is the patched document.
+async fn patch_endpoint(
+    (content_type, body): (Option<TypedHeader<ContentType>>, Body),
+) -> impl IntoResponse {
+    match content_type {
+        Some(TypedHeader(ct)) if ct == ContentType::json() => {}
+        _ => {
+            return error_reply(StatusCode::UNSUPPORTED_MEDIA_TYPE, "Unsupported Media Type");
+        }
+    }
+
+    let request = match read_json(body).await {
+        Ok(request) => request,
+        Err(reply) => return reply,
+    };
+    let (Some(document), Some(patch)) = (request.pointer("/document"), request.pointer("/patch"))
+    else {
+        return error_reply(
             StatusCode::BAD_REQUEST,
+            "Expected \"document\" and \"patch\" members",
+        );
+    };
+    let mut document = document.clone();
+    match JsonPatch::from_token(patch).and_then(|patch| patch.apply(&mut document)) {
+        Ok(()) => (
+            StatusCode::OK,
             [("content-type", "application/json")],
-            json!({ "code": 400, "message": format!("{}", e) }).to_string(),
+            to_json_string(&document),
         ),
+        Err(e) => {
+            // RFC 5789: a malformed patch is unprocessable, a patch that does
+            // not fit the document is a conflict.
+            let status = match e {
+                PatchError::NoValue { .. } | PatchError::TestFailed { .. } => StatusCode::CONFLICT,
+                _ => StatusCode::UNPROCESSABLE_ENTITY,
+            };
+            error_reply(status, e.to_string())
+        }
     }
 }
 
 #[tokio::main]
 async fn main() {
-    let app = Router::new().route("/api/v1/parse", post(parse_endpoint));
+    let app = Router::new()
+        .route("/api/v1/parse", post(parse_endpoint))
+        .route("/api/v1/patch", post(patch_endpoint));
     let listener = tokio::net::TcpListener::bind("0.0.0.0:8000").await.unwrap();
     let service = app.into_make_service();
     let server = axum::serve(listener, service);
@@ -3,57 +3,144 @@ extern crate rocket;
 
 use rocket::data::{Data, ToByteUnit};
 use rocket::http::{ContentType, Status};
-use rocket::serde::json::json;
-use shared_lib::{parse, pretty_print_token};
+use rocket::tokio::io::AsyncReadExt;
+use serde::Serialize;
+use shared_lib::{
+    Json, JsonPatch, JsonPushParser, PatchError, pretty_print_token, to_json_string, to_string,
+};
+
+type Failure = (Status, (ContentType, String));
+
+/// Largest accepted request body, the same as the axum and actix servers.
+const BODY_LIMIT: usize = 2 * 1024 * 1024;
+
+#[derive(Serialize)]
+struct ErrorResponse {
+    code: u16,
+    message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    line: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    column: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    offset: Option<usize>,
+}
+
+impl ErrorResponse {
+    fn failure(self, status: Status) -> Failure {
+        let body = to_string(&self).expect("error responses are plain data");
+        (status, (ContentType::JSON, body))
+    }
+}
+
+fn failure(status: Status, message: impl Into<String>) -> Failure {
+    ErrorResponse {
+        code: status.code,
+        message: message.into(),
+        line: None,
+        column: None,
+        offset: None,
+    }
+    .failure(status)
+}
+
+/// Parse the request body as it streams in, so that malformed bodies fail
+/// early.
+async fn read_json(input: Data<'_>) -> Result<Json, Failure> {
+    // One byte over the limit tells a body that is too large from one that
+    // is exactly at it.
+    let mut stream = input.open((BODY_LIMIT + 1).bytes());
+    let mut parser = JsonPushParser::new();
+    let mut chunk = vec![0; 8 * 1024];
+    let mut length = 0;
+    let result = loop {
+        let read = match stream.read(&mut chunk).await {
+            Ok(read) => read,
+            Err(e) => {
+                return Err(failure(
+                    Status::BadRequest,
+                    format!("Failed to read body: {}", e),
+                ));
+            }
+        };
+        if read == 0 {
+            break parser.finish();
+        }
+        length += read;
+        if length > BODY_LIMIT {
+            return Err(failure(Status::PayloadTooLarge, "Payload Too Large"));
+        }
+        parser.feed(&chunk[..read]);
+        match parser.next_value() {
+            Ok(Some(json)) => break Ok(json),
+            Ok(None) => {}
+            Err(e) => break Err(e),
+        }
+    };
+
+    result.map_err(|e| {
+        ErrorResponse {
+            code: 400,
+            message: e.to_string(),
+            line: Some(e.line),
+            column: Some(e.column),
+            offset: Some(e.offset),
+        }
+        .failure(Status::BadRequest)
+    })
+}
 
 #[post("/api/v1/parse", data = "<input>")]
 async fn parse_endpoint(
     input: Data<'_>,
     content_type: Option<&ContentType>,
-) -> Result<(ContentType, String), (Status, (ContentType, String))> {
+) -> Result<(ContentType, String), Failure> {
     if content_type != Some(&ContentType::Plain) {
-        let err_json = json!({ "code": 415, "message": "Unsupported Media Type" });
-        return Err((
+        return Err(failure(
             Status::UnsupportedMediaType,
-            (ContentType::JSON, err_json.to_string()),
+            "Unsupported Media Type",
         ));
     }
 
-    let bytes = input.open(128.kibibytes()).into_bytes().await;
+    let result = read_json(input).await?;
+    Ok((ContentType::Plain, pretty_print_token(&result.token, 0)))
+}
 
-    let text = match bytes {
-        Ok(b) => match String::from_utf8(b.value) {
-            Ok(s) => s,
-            Err(e) => {
-                let err_json = json!({ "code": 400, "message": format!("Invalid UTF-8: {}", e) });
-                return Err((
-                    Status::BadRequest,
-                    (ContentType::JSON, err_json.to_string()),
-                ));
-            }
-        },
-        Err(e) => {
-            let err_json = json!({ "code": 400, "message": format!("Failed to read body: {}", e) });
-            return Err((
-                Status::BadRequest,
-                (ContentType::JSON, err_json.to_string()),
-            ));
-        }
-    };
+/// Apply an RFC 6902 patch. The body is `{"document": ..., "patch": [...]}`
+/// and the response is the patched document.
+#[post("/api/v1/patch", data = "<input>")]
+async fn patch_endpoint(
+    input: Data<'_>,
+    content_type: Option<&ContentType>,
+) -> Result<(ContentType, String), Failure> {
+    if content_type != Some(&ContentType::JSON) {
+        return Err(failure(
+            Status::UnsupportedMediaType,
+            "Unsupported Media Type",
+        ));
+    }
 
-    match parse(&text) {
-        Ok(result) => Ok((ContentType::Plain, pretty_print_token(&result.token, 0))),
-        Err(e) => {
-            let err_json = json!({ "code": 400, "message": format!("{}", e) });
-            Err((
-                Status::BadRequest,
-                (ContentType::JSON, err_json.to_string()),
-            ))
+    let request = read_json(input).await?;
+    let (Some(document), Some(patch)) = (request.pointer("/document"), request.pointer("/patch"))
+    else {
+        return Err(failure(
+            Status::BadRequest,
+            "Expected \"document\" and \"patch\" members",
+        ));
+    };
+    let mut document = document.clone();
+    match JsonPatch::from_token(patch).and_then(|patch| patch.apply(&mut document)) {
+        Ok(()) => Ok((ContentType::JSON, to_json_string(&document))),
+        // RFC 5789: a malformed patch is unprocessable, a patch that does not
+        // fit the document is a conflict.
+        Err(e @ (PatchError::NoValue { .. } | PatchError::TestFailed { .. })) => {
+            Err(failure(Status::Conflict, e.to_string()))
         }
+        Err(e) => Err(failure(Status::UnprocessableEntity, e.to_string())),
     }
 }
 
 #[launch]
 fn rocket() -> _ {
-    rocket::build().mount("/", routes![parse_endpoint])
+    rocket::build().mount("/", routes![parse_endpoint, patch_endpoint])
 }
@@ -1,7 +1,174 @@
-use shared_lib::{Json, parse, pretty_print_token};
-use std::io::{self, Read};
+use shared_lib::{
+    ArrayAlignment, Change, DiffOptions, Json, JsonLinesReader, JsonPatch, JsonPointer, ReadError,
+    ValueToken, diff_with_options, merge_patch, parse, pretty_print_token, to_json_pretty,
+};
+use std::fs;
+use std::io::{self, IsTerminal, Read};
+use std::process;
+
+const USAGE: &str = "Usage: cli [--lines] [--pointer <json-pointer>] < input.json
+       cli patch <patch.json> < input.json
+       cli patch --generate <from.json> <to.json>
+       cli merge <target.json> <merge-patch.json>
+       cli diff [--lcs] [--patch] <a.json> <b.json>";
 
 fn main() {
+    let mut lines = false;
+    let mut pointer = JsonPointer::root();
+    let mut args = std::env::args().skip(1).peekable();
+    match args.peek().map(String::as_str) {
+        Some("patch") => {
+            args.next();
+            patch_command(args.collect());
+            return;
+        }
+        Some("merge") => {
+            args.next();
+            merge_command(args.collect());
+            return;
+        }
+        Some("diff") => {
+            args.next();
+            diff_command(args.collect());
+            return;
+        }
+        _ => {}
+    }
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--lines" => lines = true,
+            "--pointer" => match args.next().map(|arg| JsonPointer::parse(&arg)) {
+                Some(Ok(parsed)) => pointer = parsed,
+                Some(Err(e)) => usage_error(&e.to_string()),
+                None => usage_error("Missing value for --pointer"),
+            },
+            _ => usage_error(&format!("Unknown argument: {}", arg)),
+        }
+    }
+
+    if lines {
+        parse_lines(&pointer);
+    } else {
+        parse_document(&pointer);
+    }
+}
+
+fn usage_error(message: &str) -> ! {
+    eprintln!("{}\n{}", message, USAGE);
+    process::exit(2);
+}
+
+/// Report `message` and exit with a failure status.
+fn fail(message: &str) -> ! {
+    eprintln!("{}", message);
+    process::exit(1);
+}
+
+/// Read and parse a JSON file.
+fn read_file(path: &str) -> Json {
+    let input = fs::read_to_string(path)
+        .unwrap_or_else(|e| fail(&format!("Failed to read {}: {}", path, e)));
+    parse(&input).unwrap_or_else(|e| fail(&format!("Parse error in {}: {}", path, e)))
+}
+
+/// `patch <patch.json>` applies an RFC 6902 patch to stdin and prints the
+/// result; `patch --generate <from.json> <to.json>` prints a patch that
+/// turns one file into the other.
+fn patch_command(args: Vec<String>) {
+    let patched = match &args[..] {
+        [flag, from, to] if flag == "--generate" => {
+            let (from, to) = (read_file(from), read_file(to));
+            JsonPatch::diff(&from.token, &to.token).to_token()
+        }
+        [patch] => {
+            let patch = JsonPatch::from_token(&read_file(patch).token)
+                .unwrap_or_else(|e| fail(&format!("Invalid patch: {}", e)));
+            let mut input = String::new();
+            io::stdin()
+                .read_to_string(&mut input)
+                .expect("Failed to read stdin");
+            let mut document = parse(&input)
+                .unwrap_or_else(|e| fail(&format!("Parse error: {}", e)))
+                .token;
+            if let Err(e) = patch.apply(&mut document) {
+                fail(&e.to_string());
+            }
+            *document
+        }
+        _ => usage_error("Expected a patch file, or --generate with two files"),
+    };
+    println!("{}", to_json_pretty(&patched, 2));
+}
+
+/// `merge <target.json> <merge-patch.json>` applies an RFC 7386 merge patch
+/// and prints the result.
+fn merge_command(args: Vec<String>) {
+    let [target, patch] = &args[..] else {
+        usage_error("Expected a target file and a merge patch file");
+    };
+    let mut target = read_file(target).token;
+    merge_patch(&mut target, &read_file(patch).token);
+    println!("{}", to_json_pretty(&target, 2));
+}
+
+/// `diff [--lcs] [--patch] <a.json> <b.json>` prints the structural
+/// differences between two files, one change per line, or as an RFC 6902
+/// patch with `--patch`. `--lcs` aligns array elements by longest common
+/// subsequence instead of by index.
+fn diff_command(args: Vec<String>) {
+    let mut options = DiffOptions::default();
+    let mut as_patch = false;
+    let mut files = Vec::new();
+    for arg in args {
+        match arg.as_str() {
+            "--lcs" => options.arrays = ArrayAlignment::Lcs,
+            "--patch" => as_patch = true,
+            _ if arg.starts_with("--") => usage_error(&format!("Unknown argument: {}", arg)),
+            _ => files.push(arg),
+        }
+    }
+    let [a, b] = &files[..] else {
+        usage_error("Expected two files to compare");
+    };
+    let changes = diff_with_options(&read_file(a).token, &read_file(b).token, &options);
+    if as_patch {
+        let patch = JsonPatch::from_changes(&changes).to_token();
+        println!("{}", to_json_pretty(&patch, 2));
+        return;
+    }
+    // Color only a terminal, and respect https://no-color.org.
+    let color = io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none();
+    for change in &changes {
+        let code = match change {
+            Change::Added { .. } => "32",
+            Change::Removed { .. } => "31",
+            Change::Changed { .. } => "33",
+        };
+        if color {
+            println!("\x1b[{}m{}\x1b[0m", code, change);
+        } else {
+            println!("{}", change);
+        }
+    }
+}
+
+/// Print the value `pointer` selects within `token`, or report that there
+/// is none. Returns whether a value was printed.
+fn print_selected(token: &ValueToken, pointer: &JsonPointer) -> bool {
+    match pointer.resolve(token) {
+        Some(selected) => {
+            println!("{}", pretty_print_token(selected, 0));
+            true
+        }
+        None => {
+            eprintln!("No value at JSON Pointer {}", pointer);
+            false
+        }
+    }
+}
+
+/// Parse stdin as a single JSON document.
+fn parse_document(pointer: &JsonPointer) {
     // Read all input from stdin
     let mut input = String::new();
     io::stdin()
@@ -11,11 +178,30 @@ fn main() {
     // Parse input using shared_lib::parse
     match parse(&input) {
         Ok(Json { token, .. }) => {
-            let pretty = pretty_print_token(&token, 0);
-            println!("{}", pretty);
+            if !print_selected(&token, pointer) {
+                process::exit(1);
+            }
         }
-        Err(e) => {
-            eprintln!("Parse error: {:?}", e);
+        Err(e) => fail(&format!("Parse error: {}", e)),
+    }
+}
+
+/// Parse stdin as JSON Lines. Each record is handled on its own, so a bad
+/// line is reported and the following lines are still parsed; the exit
+/// status is 1 if any line failed.
+fn parse_lines(pointer: &JsonPointer) {
+    let mut failed = false;
+    for result in JsonLinesReader::new(io::stdin().lock()) {
+        match result {
+            Ok(Json { token, .. }) => failed |= !print_selected(&token, pointer),
+            Err(ReadError::Parse(e)) => {
+                eprintln!("Parse error: {}", e);
+                failed = true;
+            }
+            Err(ReadError::Io(e)) => fail(&format!("Failed to read stdin: {}", e)),
         }
     }
+    if failed {
+        process::exit(1);
+    }
 }
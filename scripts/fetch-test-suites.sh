@@ -0,0 +1,46 @@
+#!/bin/sh
+# Vendor the upstream conformance suites that the ignored shared-lib tests
+# run, recording the commit each was taken from next to its license.
+#
+#   scripts/fetch-test-suites.sh [cts-ref] [json-schema-ref]
+#
+# Refs default to the upstream default branch. Commit the files this writes,
+# then run the suites with:
+#
+#   cargo test -p shared-lib -- --ignored
+set -eu
+
+root=$(cd "$(dirname "$0")/.." && pwd)
+work=$(mktemp -d)
+trap 'rm -rf "$work"' EXIT
+
+# fetch <url> <ref> <dir>: shallow clone of <ref> into $work/<dir>.
+fetch() {
+    git -C "$work" init -q "$3"
+    git -C "$work/$3" fetch -q --depth 1 "$1" "$2"
+    git -C "$work/$3" checkout -q FETCH_HEAD
+}
+
+# upstream <url> <dir> <destination>: note where the files came from.
+upstream() {
+    {
+        echo "Source: $1"
+        echo "Commit: $(git -C "$work/$2" rev-parse HEAD)"
+        echo "Fetched: $(date -u +%Y-%m-%d)"
+    } > "$3/UPSTREAM"
+}
+
+cts_url=https://github.com/jsonpath-standard/jsonpath-compliance-test-suite
+cts="$root/shared-lib/testdata/jsonpath/cts"
+fetch "$cts_url" "${1:-HEAD}" cts
+mkdir -p "$cts"
+cp "$work/cts/cts.json" "$work/cts/LICENSE" "$cts/"
+upstream "$cts_url" cts "$cts"
+
+schema_url=https://github.com/json-schema-org/JSON-Schema-Test-Suite
+schema="$root/shared-lib/testdata/json-schema/suite"
+fetch "$schema_url" "${2:-HEAD}" schema
+mkdir -p "$schema/draft2020-12"
+cp "$work/schema/LICENSE" "$schema/"
+cp "$work/schema/tests/draft2020-12/"*.json "$schema/draft2020-12/"
+upstream "$schema_url" schema "$schema"
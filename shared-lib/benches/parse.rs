@@ -0,0 +1,34 @@
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use shared_lib::parse;
+use std::hint::black_box;
+
+/// Build an array of `records` small objects covering every value type.
+fn document(records: usize) -> String {
+    let mut json = String::from("[");
+    for i in 0..records {
+        if i > 0 {
+            json.push(',');
+        }
+        json.push_str(&format!(
+            r#"{{"id":{},"name":"record é {}","score":{}.5e-1,"tags":["a","b"],"active":true,"parent":null}}"#,
+            i, i, i
+        ));
+    }
+    json.push(']');
+    json
+}
+
+fn parse_scaling(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parse");
+    for records in [100, 1_000, 10_000] {
+        let json = document(records);
+        group.throughput(Throughput::Bytes(json.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(json.len()), &json, |b, json| {
+            b.iter(|| parse(black_box(json)).unwrap())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, parse_scaling);
+criterion_main!(benches);
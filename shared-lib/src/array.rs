@@ -1,6 +1,7 @@
-use crate::types::{Array, ValueToken};
+use crate::cursor::{ARRAY_DELIMITERS, Cursor};
+use crate::error::{ErrorKind, ParseError, PathSegment};
+use crate::types::{Array, Span, ValueToken};
 use crate::value::parse_value;
-use regex::Regex;
 
 enum Mode {
     Scanning,
@@ -9,120 +10,116 @@ enum Mode {
     End,
 }
 
-lazy_static! {
-    static ref WHITESPACE: Regex = Regex::new(r"[ \n\r\t]").unwrap();
-}
-
-pub fn parse_array(array: &str) -> Result<ValueToken, &'static str> {
+pub fn parse_array(cursor: &mut Cursor) -> Result<ValueToken, ParseError> {
     let mut mode = Mode::Scanning;
-    let mut pos: usize = 0;
+    let start = cursor.chars();
+    let mut span_start = cursor.position();
     let mut values: Vec<Box<ValueToken>> = Vec::new();
 
-    while let Some(ch) = array.chars().nth(pos) {
-        let char = &ch.to_string()[..];
-
+    while let Some(ch) = cursor.peek() {
         match mode {
             Mode::Scanning => {
-                if WHITESPACE.is_match(char) {
-                    pos += 1;
+                if cursor.is_trivia(ch) {
+                    cursor.skip_trivia(ch)?;
                 } else if ch == '[' {
-                    pos += 1;
+                    span_start = cursor.position();
+                    cursor.enter()?;
+                    cursor.advance(ch);
                     mode = Mode::Element;
                 } else {
-                    return Err("Expected '['");
+                    return Err(ParseError::at(ErrorKind::Expected("'['"), cursor.pos()));
                 }
             }
             Mode::Element => {
-                if WHITESPACE.is_match(char) {
-                    pos += 1;
+                if cursor.is_trivia(ch) {
+                    cursor.skip_trivia(ch)?;
                 } else if ch == ']' {
-                    if !values.is_empty() {
-                        return Err("Unexpected ','");
+                    if !values.is_empty() && !cursor.dialect().allows_trailing_commas() {
+                        return Err(ParseError::at(ErrorKind::UnexpectedComma, cursor.pos()));
                     }
 
-                    pos += 1;
+                    cursor.advance(ch);
                     mode = Mode::End;
                 } else {
-                    let slice: String = array.chars().skip(pos).collect();
-                    match parse_value(&slice, r"[ \n\r\t\],]") {
-                        Ok(ValueToken::ArrayToken { skip, token }) => {
-                            values.push(Box::new(ValueToken::ArrayToken { skip, token }));
-                            pos += skip;
-                            mode = Mode::Delimiter;
+                    let pos = cursor.pos();
+                    match parse_value(cursor, ARRAY_DELIMITERS) {
+                        Ok(ValueToken::PairToken { .. }) => {
+                            return Err(ParseError::at(ErrorKind::UnexpectedToken, pos));
                         }
-                        Ok(ValueToken::FalseToken { skip, token }) => {
-                            values.push(Box::new(ValueToken::FalseToken { skip, token }));
-                            pos += skip;
+                        Ok(token) => {
+                            values.push(Box::new(token));
                             mode = Mode::Delimiter;
                         }
-                        Ok(ValueToken::NullToken { skip }) => {
-                            values.push(Box::new(ValueToken::NullToken { skip }));
-                            pos += skip;
-                            mode = Mode::Delimiter;
-                        }
-                        Ok(ValueToken::NumberToken { skip, token }) => {
-                            values.push(Box::new(ValueToken::NumberToken { skip, token }));
-                            pos += skip;
-                            mode = Mode::Delimiter;
-                        }
-                        Ok(ValueToken::ObjectToken { skip, token }) => {
-                            values.push(Box::new(ValueToken::ObjectToken { skip, token }));
-                            pos += skip;
-                            mode = Mode::Delimiter;
-                        }
-                        Ok(ValueToken::StringToken { skip, token }) => {
-                            values.push(Box::new(ValueToken::StringToken { skip, token }));
-                            pos += skip;
-                            mode = Mode::Delimiter;
-                        }
-                        Ok(ValueToken::TrueToken { skip, token }) => {
-                            values.push(Box::new(ValueToken::TrueToken { skip, token }));
-                            pos += skip;
-                            mode = Mode::Delimiter;
-                        }
-                        _ => {
-                            return Err("Unexpected token");
+                        Err(e) => {
+                            return Err(e.within(PathSegment::Index(values.len())));
                         }
                     }
                 }
             }
             Mode::Delimiter => {
-                if WHITESPACE.is_match(char) {
-                    pos += 1;
+                if cursor.is_trivia(ch) {
+                    cursor.skip_trivia(ch)?;
                 } else if ch == ',' {
-                    pos += 1;
+                    cursor.advance(ch);
                     mode = Mode::Element;
                 } else if ch == ']' {
-                    pos += 1;
+                    cursor.advance(ch);
                     mode = Mode::End;
                 } else {
-                    return Err("Expected ',' or ']'");
+                    return Err(ParseError::at(
+                        ErrorKind::Expected("',' or ']'"),
+                        cursor.pos(),
+                    ));
                 }
             }
             Mode::End => break,
         }
     }
 
+    match mode {
+        Mode::End => {}
+        Mode::Scanning => {
+            return Err(ParseError::at(ErrorKind::Expected("'['"), cursor.pos()));
+        }
+        Mode::Element => {
+            return Err(ParseError::at(ErrorKind::Expected("value"), cursor.pos())
+                .within(PathSegment::Index(values.len())));
+        }
+        Mode::Delimiter => {
+            return Err(ParseError::at(
+                ErrorKind::Expected("',' or ']'"),
+                cursor.pos(),
+            ));
+        }
+    }
+
+    cursor.leave();
     Ok(ValueToken::ArrayToken {
-        skip: pos,
+        skip: cursor.chars() - start,
+        span: Span {
+            start: span_start,
+            end: cursor.position(),
+        },
         token: Array { values },
     })
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::json::parse;
-    use crate::types::{Json, ValueToken};
+    use crate::error::ErrorKind;
+    use crate::json::{parse, parse_with_options};
+    use crate::options::{Dialect, ParseOptions};
+    use crate::types::{Json, Position, Span, ValueToken};
 
     #[test]
     fn array_empty() {
         let input = "[]";
         match parse(input) {
-            Ok(Json { skip, token }) => {
+            Ok(Json { skip, token, .. }) => {
                 assert_eq!(2, skip);
                 let unboxed = *token;
                 match unboxed {
-                    ValueToken::ArrayToken { skip, token } => {
+                    ValueToken::ArrayToken { skip, token, .. } => {
                         assert_eq!(2, skip);
                         assert_eq!(0, token.values.len());
                     }
@@ -137,11 +134,11 @@ mod tests {
     fn array_empty_spaces() {
         let input = " [ ] ";
         match parse(input) {
-            Ok(Json { skip, token }) => {
+            Ok(Json { skip, token, .. }) => {
                 assert_eq!(4, skip);
                 let unboxed = *token;
                 match unboxed {
-                    ValueToken::ArrayToken { skip, token } => {
+                    ValueToken::ArrayToken { skip, token, .. } => {
                         assert_eq!(3, skip);
                         assert_eq!(0, token.values.len());
                     }
@@ -156,11 +153,11 @@ mod tests {
     fn array_nested_empty() {
         let input = "[[]]";
         match parse(input) {
-            Ok(Json { skip, token }) => {
+            Ok(Json { skip, token, .. }) => {
                 assert_eq!(4, skip);
                 let unboxed = *token;
                 match unboxed {
-                    ValueToken::ArrayToken { skip, token } => {
+                    ValueToken::ArrayToken { skip, token, .. } => {
                         assert_eq!(4, skip);
                         assert_eq!(1, token.values.len());
                     }
@@ -175,11 +172,11 @@ mod tests {
     fn array_nested_empty_spaces() {
         let input = " [ [ ] ] ";
         match parse(input) {
-            Ok(Json { skip, token }) => {
+            Ok(Json { skip, token, .. }) => {
                 assert_eq!(8, skip);
                 let unboxed = *token;
                 match unboxed {
-                    ValueToken::ArrayToken { skip, token } => {
+                    ValueToken::ArrayToken { skip, token, .. } => {
                         assert_eq!(7, skip);
                         assert_eq!(1, token.values.len());
                     }
@@ -194,11 +191,11 @@ mod tests {
     fn array_mixed_types() {
         let input = "[[], false]";
         match parse(input) {
-            Ok(Json { skip, token }) => {
+            Ok(Json { skip, token, .. }) => {
                 assert_eq!(11, skip);
                 let unboxed = *token;
                 match unboxed {
-                    ValueToken::ArrayToken { skip, token } => {
+                    ValueToken::ArrayToken { skip, token, .. } => {
                         assert_eq!(11, skip);
                         assert_eq!(2, token.values.len());
                     }
@@ -213,11 +210,11 @@ mod tests {
     fn array_mixed_types_more() {
         let input = "[[], false, null]";
         match parse(input) {
-            Ok(Json { skip, token }) => {
+            Ok(Json { skip, token, .. }) => {
                 assert_eq!(17, skip);
                 let unboxed = *token;
                 match unboxed {
-                    ValueToken::ArrayToken { skip, token } => {
+                    ValueToken::ArrayToken { skip, token, .. } => {
                         assert_eq!(17, skip);
                         assert_eq!(3, token.values.len());
                     }
@@ -232,11 +229,11 @@ mod tests {
     fn array_numbers() {
         let input = "[[], false, null, 1.2e3]";
         match parse(input) {
-            Ok(Json { skip, token }) => {
+            Ok(Json { skip, token, .. }) => {
                 assert_eq!(24, skip);
                 let unboxed = *token;
                 match unboxed {
-                    ValueToken::ArrayToken { skip, token } => {
+                    ValueToken::ArrayToken { skip, token, .. } => {
                         assert_eq!(24, skip);
                         assert_eq!(4, token.values.len());
                     }
@@ -251,11 +248,11 @@ mod tests {
     fn array_objects() {
         let input = r#"[[], false, null, 1.2e3, {}]"#;
         match parse(input) {
-            Ok(Json { skip, token }) => {
+            Ok(Json { skip, token, .. }) => {
                 assert_eq!(28, skip);
                 let unboxed = *token;
                 match unboxed {
-                    ValueToken::ArrayToken { skip, token } => {
+                    ValueToken::ArrayToken { skip, token, .. } => {
                         assert_eq!(28, skip);
                         assert_eq!(5, token.values.len());
                     }
@@ -270,11 +267,11 @@ mod tests {
     fn array_strings() {
         let input = r#"[[], false, null, 1.2e3, {}, "Hello, world!"]"#;
         match parse(input) {
-            Ok(Json { skip, token }) => {
+            Ok(Json { skip, token, .. }) => {
                 assert_eq!(45, skip);
                 let unboxed = *token;
                 match unboxed {
-                    ValueToken::ArrayToken { skip, token } => {
+                    ValueToken::ArrayToken { skip, token, .. } => {
                         assert_eq!(45, skip);
                         assert_eq!(6, token.values.len());
                     }
@@ -410,4 +407,132 @@ mod tests {
             Err(e) => panic!("{}", e),
         }
     }
+
+    #[test]
+    fn array_too_deep() {
+        let input = "[".repeat(200_000);
+        match parse(&input) {
+            Ok(_) => panic!("Expected error"),
+            Err(e) => {
+                assert_eq!(ErrorKind::NestingTooDeep, e.kind);
+                assert_eq!(128, e.offset);
+            }
+        }
+    }
+
+    #[test]
+    fn array_at_max_depth() {
+        let input = format!("{}{}", "[".repeat(128), "]".repeat(128));
+        if let Err(e) = parse(&input) {
+            panic!("{}", e);
+        }
+    }
+
+    #[test]
+    fn array_custom_max_depth() {
+        let options = ParseOptions {
+            max_depth: 2,
+            ..ParseOptions::default()
+        };
+        if let Err(e) = parse_with_options("[[1]]", &options) {
+            panic!("{}", e);
+        }
+        match parse_with_options("[[[1]]]", &options) {
+            Ok(_) => panic!("Expected error"),
+            Err(e) => assert_eq!(ErrorKind::NestingTooDeep, e.kind),
+        }
+    }
+
+    #[test]
+    fn array_element_spans() {
+        let input = "[ 1,\n  \"\u{e9}\" , [] ]";
+        match parse(input) {
+            Ok(Json { token, .. }) => match *token {
+                ValueToken::ArrayToken { span, token, .. } => {
+                    assert_eq!(0, span.start.offset);
+                    assert_eq!(input.len(), span.end.offset);
+                    let spans: Vec<Span> = token
+                        .values
+                        .iter()
+                        .map(|value| match **value {
+                            ValueToken::NumberToken { span, .. }
+                            | ValueToken::StringToken { span, .. }
+                            | ValueToken::ArrayToken { span, .. } => span,
+                            _ => panic!("Unexpected token"),
+                        })
+                        .collect();
+                    assert_eq!(
+                        Span {
+                            start: Position {
+                                offset: 2,
+                                line: 1,
+                                column: 3
+                            },
+                            end: Position {
+                                offset: 3,
+                                line: 1,
+                                column: 4
+                            },
+                        },
+                        spans[0]
+                    );
+                    assert_eq!(
+                        Span {
+                            start: Position {
+                                offset: 7,
+                                line: 2,
+                                column: 3
+                            },
+                            end: Position {
+                                offset: 11,
+                                line: 2,
+                                column: 6
+                            },
+                        },
+                        spans[1]
+                    );
+                    assert_eq!("[]", &input[spans[2].start.offset..spans[2].end.offset]);
+                }
+                _ => panic!("Expected ArrayToken"),
+            },
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    #[test]
+    fn array_trailing_comma() {
+        let options = ParseOptions {
+            dialect: Dialect::Json5,
+            ..ParseOptions::default()
+        };
+        match parse_with_options("[1, 2, ]", &options) {
+            Ok(Json { token, .. }) => match *token {
+                ValueToken::ArrayToken { token, .. } => assert_eq!(2, token.values.len()),
+                _ => panic!("Expected ArrayToken"),
+            },
+            Err(e) => panic!("{}", e),
+        }
+        match parse("[1, 2, ]") {
+            Ok(_) => panic!("Trailing comma should be rejected"),
+            Err(e) => assert_eq!(ErrorKind::UnexpectedComma, e.kind),
+        }
+        assert!(parse_with_options("[,]", &options).is_err());
+        assert!(parse_with_options("[1,,]", &options).is_err());
+    }
+
+    #[test]
+    fn array_truncated() {
+        for (input, kind, offset) in [
+            ("[", ErrorKind::Expected("value"), 1),
+            ("[1", ErrorKind::Expected("',' or ']'"), 2),
+            ("[1,", ErrorKind::Expected("value"), 3),
+            ("[1, [2] ", ErrorKind::Expected("',' or ']'"), 8),
+            ("[[1]", ErrorKind::Expected("',' or ']'"), 4),
+        ] {
+            match parse(input) {
+                Ok(json) => panic!("{:?} parsed as {:?}", input, json),
+                Err(e) => assert_eq!((kind, offset), (e.kind, e.offset), "{:?}", input),
+            }
+        }
+    }
 }
@@ -0,0 +1,229 @@
+use crate::error::PathSegment;
+use crate::types::{Comment, CommentPlacement, Span, ValueToken};
+
+/// Attach each comment to the value nearest to it.
+///
+/// A comment belongs to the innermost array or object that encloses it.
+/// There it trails the preceding element if it starts on the line that
+/// element ends on, and otherwise leads the following element; after the
+/// last element it is an inner comment of the container, and between a key
+/// and its value it belongs to that member. Comments outside the root value
+/// lead it, trail it on its last line, or follow it on later lines.
+pub fn attach(comments: &mut [Comment], root: &ValueToken) {
+    for comment in comments {
+        let (path, placement) = locate(comment.span, root);
+        comment.path = path;
+        comment.placement = placement;
+    }
+}
+
+fn locate(comment: Span, root: &ValueToken) -> (Vec<PathSegment>, CommentPlacement) {
+    let root_span = root.span();
+    if comment.end.offset <= root_span.start.offset {
+        return (Vec::new(), CommentPlacement::Leading);
+    }
+    if comment.start.offset >= root_span.end.offset {
+        if comment.start.line == root_span.end.line {
+            return (Vec::new(), CommentPlacement::Trailing);
+        }
+        return (Vec::new(), CommentPlacement::After);
+    }
+
+    let mut path = Vec::new();
+    let mut container = root;
+    loop {
+        let children = children(container);
+        if let Some((segment, _, value)) = children
+            .iter()
+            .find(|(_, span, _)| contains(*span, comment))
+        {
+            path.push(segment.clone());
+            if contains(value.span(), comment) {
+                container = value;
+                continue;
+            }
+            // Between an object key and its value.
+            return (path, CommentPlacement::AfterKey);
+        }
+
+        let previous = children
+            .iter()
+            .rev()
+            .find(|(_, span, _)| span.end.offset <= comment.start.offset);
+        let next = children
+            .iter()
+            .find(|(_, span, _)| span.start.offset >= comment.end.offset);
+        return match (previous, next) {
+            (Some((segment, span, _)), _) if span.end.line == comment.start.line => {
+                path.push(segment.clone());
+                (path, CommentPlacement::Trailing)
+            }
+            (_, Some((segment, _, _))) => {
+                path.push(segment.clone());
+                (path, CommentPlacement::Leading)
+            }
+            _ => (path, CommentPlacement::Inner),
+        };
+    }
+}
+
+/// The elements or members of a container with their source spans. A
+/// member's span runs from its key to the end of its value.
+fn children(token: &ValueToken) -> Vec<(PathSegment, Span, &ValueToken)> {
+    match token {
+        ValueToken::ArrayToken { token, .. } => token
+            .values
+            .iter()
+            .enumerate()
+            .map(|(index, value)| (PathSegment::Index(index), value.span(), &**value))
+            .collect(),
+        ValueToken::ObjectToken { token, .. } => token
+            .iter()
+            .map(|pair| {
+                let span = Span {
+                    start: pair.key_span.start,
+                    end: pair.value.span().end,
+                };
+                (PathSegment::Key(pair.key.clone()), span, &*pair.value)
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn contains(outer: Span, inner: Span) -> bool {
+    outer.start.offset <= inner.start.offset && inner.end.offset <= outer.end.offset
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::PathSegment;
+    use crate::json::parse_with_options;
+    use crate::options::{Dialect, ParseOptions};
+    use crate::types::{CommentKind, CommentPlacement};
+
+    fn attached(input: &str) -> Vec<(String, Vec<PathSegment>, CommentPlacement)> {
+        let options = ParseOptions {
+            dialect: Dialect::Jsonc,
+            preserve_comments: true,
+            ..ParseOptions::default()
+        };
+        match parse_with_options(input, &options) {
+            Ok(json) => json
+                .comments
+                .into_iter()
+                .map(|comment| (comment.text, comment.path, comment.placement))
+                .collect(),
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    fn key(key: &str) -> PathSegment {
+        PathSegment::Key(key.to_string())
+    }
+
+    #[test]
+    fn leading_trailing_and_inner() {
+        let input = "// head
+{
+  // about a
+  \"a\": 1, // a is one
+  \"b\": [
+    true, /* first */
+    false
+    // end of b
+  ],
+  \"c\": {
+    // nothing here
+  }
+}
+// tail";
+        assert_eq!(
+            vec![
+                (" head".to_string(), vec![], CommentPlacement::Leading),
+                (
+                    " about a".to_string(),
+                    vec![key("a")],
+                    CommentPlacement::Leading
+                ),
+                (
+                    " a is one".to_string(),
+                    vec![key("a")],
+                    CommentPlacement::Trailing
+                ),
+                (
+                    " first ".to_string(),
+                    vec![key("b"), PathSegment::Index(0)],
+                    CommentPlacement::Trailing
+                ),
+                (
+                    " end of b".to_string(),
+                    vec![key("b")],
+                    CommentPlacement::Inner
+                ),
+                (
+                    " nothing here".to_string(),
+                    vec![key("c")],
+                    CommentPlacement::Inner
+                ),
+                (" tail".to_string(), vec![], CommentPlacement::After),
+            ],
+            attached(input)
+        );
+    }
+
+    #[test]
+    fn comment_between_key_and_value() {
+        assert_eq!(
+            vec![(
+                " x ".to_string(),
+                vec![key("a")],
+                CommentPlacement::AfterKey
+            )],
+            attached("{\"a\" /* x */ : 1}")
+        );
+    }
+
+    #[test]
+    fn comments_after_root() {
+        assert_eq!(
+            vec![
+                (" same line".to_string(), vec![], CommentPlacement::Trailing),
+                (" next line".to_string(), vec![], CommentPlacement::After),
+            ],
+            attached("[\n  1\n] // same line\n// next line\n")
+        );
+    }
+
+    #[test]
+    fn comments_dropped_by_default() {
+        let options = ParseOptions {
+            dialect: Dialect::Jsonc,
+            ..ParseOptions::default()
+        };
+        match parse_with_options("// c\n[1]", &options) {
+            Ok(json) => assert!(json.comments.is_empty()),
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    #[test]
+    fn block_comment_span_and_kind() {
+        let options = ParseOptions {
+            dialect: Dialect::Jsonc,
+            preserve_comments: true,
+            ..ParseOptions::default()
+        };
+        match parse_with_options("[1,\n /* a\n b */ 2]", &options) {
+            Ok(json) => {
+                let comment = &json.comments[0];
+                assert_eq!(CommentKind::Block, comment.kind);
+                assert_eq!(" a\n b ", comment.text);
+                assert_eq!((2, 2), (comment.span.start.line, comment.span.start.column));
+                assert_eq!((3, 6), (comment.span.end.line, comment.span.end.column));
+                assert_eq!(vec![PathSegment::Index(1)], comment.path);
+            }
+            Err(e) => panic!("{}", e),
+        }
+    }
+}
@@ -0,0 +1,215 @@
+use crate::error::{ErrorKind, ParseError};
+use crate::options::{Dialect, ParseOptions};
+use crate::types::{Comment, CommentKind, CommentPlacement, Position, RawString, Span};
+
+/// Single-pass reader over the input shared by every parser.
+///
+/// `pos` is a byte offset used for slicing and error locations; `chars` counts
+/// the characters consumed so far, which is what the `skip` fields report.
+/// Spans are reported relative to `origin`, the place in a larger text where
+/// `input` begins.
+/// The cursor also carries the parse options, the current nesting depth and
+/// any comments kept under [`ParseOptions::preserve_comments`] and strings
+/// kept under [`crate::LoneSurrogates::Preserve`].
+pub struct Cursor<'a> {
+    input: &'a str,
+    options: &'a ParseOptions,
+    pos: usize,
+    chars: usize,
+    line: usize,
+    column: usize,
+    depth: usize,
+    comments: Vec<Comment>,
+    raw_strings: Vec<RawString>,
+    origin: Position,
+}
+
+impl<'a> Cursor<'a> {
+    pub fn new(input: &'a str, options: &'a ParseOptions) -> Self {
+        Cursor::with_origin(input, options, Position::START)
+    }
+
+    pub fn with_origin(input: &'a str, options: &'a ParseOptions, origin: Position) -> Self {
+        Cursor {
+            input,
+            options,
+            pos: 0,
+            chars: 0,
+            line: 1,
+            column: 1,
+            depth: 0,
+            comments: Vec::new(),
+            raw_strings: Vec::new(),
+            origin,
+        }
+    }
+
+    pub fn options(&self) -> &'a ParseOptions {
+        self.options
+    }
+
+    pub fn dialect(&self) -> Dialect {
+        self.options.dialect
+    }
+
+    /// Whether `ch` is whitespace or, where the dialect allows comments, the
+    /// start of one.
+    pub fn is_trivia(&self, ch: char) -> bool {
+        match self.options.dialect {
+            Dialect::Json => is_whitespace(ch),
+            Dialect::Jsonc => is_whitespace(ch) || ch == '/',
+            Dialect::Json5 => is_json5_whitespace(ch) || ch == '/',
+        }
+    }
+
+    /// Consume the whitespace character or comment that starts with `ch`,
+    /// which must satisfy [`Cursor::is_trivia`].
+    pub fn skip_trivia(&mut self, ch: char) -> Result<(), ParseError> {
+        if ch != '/' || !self.dialect().allows_comments() {
+            self.advance(ch);
+            return Ok(());
+        }
+        let start = self.position();
+        let start_pos = self.pos;
+        let kind = if self.eat("//") {
+            while let Some(ch) = self.peek() {
+                if is_line_terminator(ch) {
+                    break;
+                }
+                self.advance(ch);
+            }
+            CommentKind::Line
+        } else if self.eat("/*") {
+            while !self.eat("*/") {
+                match self.peek() {
+                    Some(ch) => self.advance(ch),
+                    None => {
+                        return Err(ParseError::at(ErrorKind::UnterminatedComment, start_pos));
+                    }
+                }
+            }
+            CommentKind::Block
+        } else {
+            return Err(ParseError::at(ErrorKind::UnexpectedCharacter, start_pos));
+        };
+        if self.options.preserve_comments {
+            let text = match kind {
+                CommentKind::Line => &self.input[start_pos + 2..self.pos],
+                CommentKind::Block => &self.input[start_pos + 2..self.pos - 2],
+            };
+            self.comments.push(Comment {
+                kind,
+                text: text.to_string(),
+                span: Span {
+                    start,
+                    end: self.position(),
+                },
+                path: Vec::new(),
+                placement: CommentPlacement::Leading,
+            });
+        }
+        Ok(())
+    }
+
+    /// Comments recorded so far, in source order.
+    pub fn take_comments(&mut self) -> Vec<Comment> {
+        std::mem::take(&mut self.comments)
+    }
+
+    pub fn push_raw_string(&mut self, raw: RawString) {
+        self.raw_strings.push(raw);
+    }
+
+    /// Strings recorded so far, in source order.
+    pub fn take_raw_strings(&mut self) -> Vec<RawString> {
+        std::mem::take(&mut self.raw_strings)
+    }
+
+    /// Descend into an array or object, failing once `max_depth` is exceeded.
+    pub fn enter(&mut self) -> Result<(), ParseError> {
+        if self.depth >= self.options.max_depth {
+            return Err(ParseError::at(ErrorKind::NestingTooDeep, self.pos));
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
+    pub fn leave(&mut self) {
+        self.depth -= 1;
+    }
+
+    /// Byte offset of the next character.
+    pub fn pos(&self) -> usize {
+        self.pos
+    }
+
+    /// Number of characters consumed so far.
+    pub fn chars(&self) -> usize {
+        self.chars
+    }
+
+    /// Source position of the next character.
+    pub fn position(&self) -> Position {
+        Position {
+            offset: self.pos,
+            line: self.line,
+            column: self.column,
+        }
+        .offset_by(self.origin)
+    }
+
+    pub fn peek(&self) -> Option<char> {
+        self.input[self.pos..].chars().next()
+    }
+
+    /// Consume `ch`, which must be the character returned by [`Cursor::peek`].
+    pub fn advance(&mut self, ch: char) {
+        self.pos += ch.len_utf8();
+        self.chars += 1;
+        if ch == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+    }
+
+    /// Consume `literal` if the remaining input starts with it.
+    /// `literal` must be ASCII without line breaks.
+    pub fn eat(&mut self, literal: &str) -> bool {
+        if self.input[self.pos..].starts_with(literal) {
+            self.pos += literal.len();
+            self.chars += literal.len();
+            self.column += literal.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn input(&self) -> &'a str {
+        self.input
+    }
+}
+
+pub fn is_whitespace(ch: char) -> bool {
+    matches!(ch, ' ' | '\n' | '\r' | '\t')
+}
+
+/// JSON5 whitespace: ECMAScript `WhiteSpace` and `LineTerminator`, i.e. the
+/// Unicode `Zs` category plus tab, vertical tab, form feed, BOM and the line
+/// and paragraph separators.
+pub fn is_json5_whitespace(ch: char) -> bool {
+    (ch.is_whitespace() && ch != '\u{85}') || ch == '\u{feff}'
+}
+
+pub fn is_line_terminator(ch: char) -> bool {
+    matches!(ch, '\n' | '\r' | '\u{2028}' | '\u{2029}')
+}
+
+/// Characters that may directly follow a top-level value.
+pub const TOP_LEVEL_DELIMITERS: &[char] = &[' ', '\n', '\r', '\t'];
+/// Characters that may directly follow an array element.
+pub const ARRAY_DELIMITERS: &[char] = &[' ', '\n', '\r', '\t', ']', ','];
+/// Characters that may directly follow an object member value.
+pub const OBJECT_DELIMITERS: &[char] = &[' ', '\n', '\r', '\t', '}', ','];
@@ -0,0 +1,679 @@
+use crate::error::DeserializeError;
+use crate::json::parse_with_options;
+use crate::options::ParseOptions;
+use crate::types::{Number, Pair, Span, ValueToken};
+use serde::de::value::StrDeserializer;
+use serde::de::{self, DeserializeSeed, Unexpected, Visitor};
+use serde::{Deserialize, forward_to_deserialize_any};
+
+/// Deserialize a `T` from JSON text.
+///
+/// Strings without escapes are borrowed from `input`, so `T` may hold
+/// `&str` fields. Errors say where in the input the offending value starts.
+///
+/// ```
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize, Debug, PartialEq)]
+/// enum Shape {
+///     Circle { radius: f64 },
+///     Point,
+/// }
+///
+/// #[derive(Deserialize, Debug)]
+/// struct Drawing<'a> {
+///     name: &'a str,
+///     shapes: Vec<Shape>,
+///     scale: Option<u32>,
+/// }
+///
+/// let drawing: Drawing =
+///     shared_lib::from_str(r#"{"name": "a", "shapes": [{"Circle": {"radius": 2}}, "Point"]}"#)
+///         .unwrap();
+/// assert_eq!("a", drawing.name);
+/// assert_eq!(vec![Shape::Circle { radius: 2.0 }, Shape::Point], drawing.shapes);
+/// assert_eq!(None, drawing.scale);
+///
+/// let error = shared_lib::from_str::<Drawing>(r#"{"name": 1, "shapes": []}"#).unwrap_err();
+/// assert_eq!(
+///     "invalid type: integer `1`, expected a borrowed string at line 1, column 10",
+///     error.to_string()
+/// );
+/// ```
+pub fn from_str<'de, T: Deserialize<'de>>(input: &'de str) -> Result<T, DeserializeError> {
+    from_str_with_options(input, &ParseOptions::default())
+}
+
+/// [`from_str`] for input in the dialect and limits given by `options`.
+pub fn from_str_with_options<'de, T: Deserialize<'de>>(
+    input: &'de str,
+    options: &ParseOptions,
+) -> Result<T, DeserializeError> {
+    let json = parse_with_options(input, options)?;
+    T::deserialize(TokenDeserializer {
+        token: &json.token,
+        source: Input(input),
+    })
+}
+
+/// Deserialize a `T` from an already parsed tree, borrowing strings from it.
+pub fn from_token<'de, T: Deserialize<'de>>(token: &'de ValueToken) -> Result<T, DeserializeError> {
+    T::deserialize(TokenDeserializer {
+        token,
+        source: Tree,
+    })
+}
+
+impl de::Error for DeserializeError {
+    fn custom<T: std::fmt::Display>(message: T) -> Self {
+        DeserializeError::Invalid {
+            message: message.to_string(),
+            position: None,
+        }
+    }
+}
+
+/// Give an error raised while deserializing the value at `span` that
+/// position, unless a value nested in it already did.
+fn locate<T>(result: Result<T, DeserializeError>, span: Span) -> Result<T, DeserializeError> {
+    result.map_err(|e| match e {
+        // Tokens built in code have no position.
+        DeserializeError::Invalid {
+            message,
+            position: None,
+        } if span.start.line > 0 => DeserializeError::Invalid {
+            message,
+            position: Some(span.start),
+        },
+        e => e,
+    })
+}
+
+/// Where strings that outlive the deserializer can be borrowed from.
+trait Source<'t, 'de>: Copy {
+    /// `text`, which was parsed from `span`, borrowed for `'de` if possible.
+    fn borrow(self, text: &'t str, span: Span) -> Option<&'de str>;
+}
+
+/// Borrow strings from the tree itself.
+#[derive(Clone, Copy)]
+struct Tree;
+
+impl<'de> Source<'de, 'de> for Tree {
+    fn borrow(self, text: &'de str, _: Span) -> Option<&'de str> {
+        Some(text)
+    }
+}
+
+/// Borrow strings from the text the tree was parsed from, which is only
+/// possible for strings without escapes.
+#[derive(Clone, Copy)]
+struct Input<'de>(&'de str);
+
+impl<'de> Source<'_, 'de> for Input<'de> {
+    fn borrow(self, text: &str, span: Span) -> Option<&'de str> {
+        let raw = self.0.get(span.start.offset..span.end.offset)?;
+        // A JSON5 identifier key is not quoted at all.
+        if raw == text {
+            return Some(raw);
+        }
+        let quote = raw.chars().next()?;
+        let inner = raw.strip_prefix(quote)?.strip_suffix(quote)?;
+        (inner == text).then_some(inner)
+    }
+}
+
+fn unexpected(token: &ValueToken) -> Unexpected<'_> {
+    match token {
+        ValueToken::NullToken { .. } => Unexpected::Unit,
+        ValueToken::TrueToken { .. } => Unexpected::Bool(true),
+        ValueToken::FalseToken { .. } => Unexpected::Bool(false),
+        ValueToken::NumberToken { token, .. } => Unexpected::Float(token.value),
+        ValueToken::StringToken { token, .. } => Unexpected::Str(token),
+        ValueToken::ArrayToken { .. } => Unexpected::Seq,
+        ValueToken::ObjectToken { .. } | ValueToken::PairToken { .. } => Unexpected::Map,
+    }
+}
+
+/// Integers are visited as such when their text fits `u64` or `i64`, so
+/// large IDs keep every digit. A literal too large for `f64` is an error
+/// rather than infinity.
+fn visit_number<'de, V: Visitor<'de>>(
+    number: &Number,
+    visitor: V,
+) -> Result<V::Value, DeserializeError> {
+    if let Some(value) = number.as_u64() {
+        visitor.visit_u64(value)
+    } else if let Some(value) = number.as_i64() {
+        visitor.visit_i64(value)
+    } else if let Some(value) = number.as_f64() {
+        visitor.visit_f64(value)
+    } else {
+        Err(de::Error::custom(format!(
+            "number {} is out of range",
+            number.value_as_string
+        )))
+    }
+}
+
+struct TokenDeserializer<'t, S> {
+    token: &'t ValueToken,
+    source: S,
+}
+
+impl<'t, 'de, S: Source<'t, 'de>> de::Deserializer<'de> for TokenDeserializer<'t, S> {
+    type Error = DeserializeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeserializeError> {
+        let result = match self.token {
+            ValueToken::NullToken { .. } => visitor.visit_unit(),
+            ValueToken::TrueToken { .. } => visitor.visit_bool(true),
+            ValueToken::FalseToken { .. } => visitor.visit_bool(false),
+            ValueToken::NumberToken { token, .. } => visit_number(token, visitor),
+            ValueToken::StringToken { token, span, .. } => match self.source.borrow(token, *span) {
+                Some(borrowed) => visitor.visit_borrowed_str(borrowed),
+                None => visitor.visit_str(token),
+            },
+            ValueToken::ArrayToken { token, .. } => {
+                let mut access = SeqAccess {
+                    values: token.values.iter(),
+                    source: self.source,
+                };
+                visitor
+                    .visit_seq(&mut access)
+                    .and_then(|value| match access.values.len() {
+                        0 => Ok(value),
+                        _ => Err(de::Error::invalid_length(
+                            token.values.len(),
+                            &"fewer elements in array",
+                        )),
+                    })
+            }
+            ValueToken::ObjectToken { token, .. } => {
+                let mut access = MapAccess {
+                    members: token.iter(),
+                    value: None,
+                    source: self.source,
+                };
+                visitor
+                    .visit_map(&mut access)
+                    .and_then(|value| match access.members.len() {
+                        0 => Ok(value),
+                        _ => Err(de::Error::invalid_length(
+                            token.len(),
+                            &"fewer members in object",
+                        )),
+                    })
+            }
+            ValueToken::PairToken { .. } => Err(de::Error::custom("unexpected object member")),
+        };
+        locate(result, self.token.span())
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeserializeError> {
+        let span = self.token.span();
+        let result = match self.token {
+            ValueToken::NullToken { .. } => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        };
+        locate(result, span)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, DeserializeError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    /// A unit variant is its name as a string; any other variant is an
+    /// object with the name as its only key, as serde's derive writes them.
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, DeserializeError> {
+        let result = match self.token {
+            ValueToken::StringToken { token, .. } => {
+                visitor.visit_enum(StrDeserializer::<DeserializeError>::new(token))
+            }
+            ValueToken::ObjectToken { token, .. } if token.len() == 1 => {
+                visitor.visit_enum(EnumAccess {
+                    pair: &token.members()[0],
+                    source: self.source,
+                })
+            }
+            token => Err(de::Error::invalid_type(
+                unexpected(token),
+                &"a string or an object with one member",
+            )),
+        };
+        locate(result, self.token.span())
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, DeserializeError> {
+        visitor.visit_unit()
+    }
+
+    /// 128-bit integers are read from the literal, as `deserialize_any` only
+    /// visits those that fit 64 bits.
+    fn deserialize_i128<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeserializeError> {
+        match self.token {
+            ValueToken::NumberToken { token, span, .. } => match token.value_as_string.parse() {
+                Ok(value) => locate(visitor.visit_i128(value), *span),
+                Err(_) => self.deserialize_any(visitor),
+            },
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_u128<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeserializeError> {
+        match self.token {
+            ValueToken::NumberToken { token, span, .. } => match token.value_as_string.parse() {
+                Ok(value) => locate(visitor.visit_u128(value), *span),
+                Err(_) => self.deserialize_any(visitor),
+            },
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 u8 u16 u32 u64 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map struct
+        identifier
+    }
+}
+
+struct SeqAccess<'t, S> {
+    values: std::slice::Iter<'t, Box<ValueToken>>,
+    source: S,
+}
+
+impl<'t, 'de, S: Source<'t, 'de>> de::SeqAccess<'de> for SeqAccess<'t, S> {
+    type Error = DeserializeError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, DeserializeError> {
+        match self.values.next() {
+            Some(token) => seed
+                .deserialize(TokenDeserializer {
+                    token,
+                    source: self.source,
+                })
+                .map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.values.len())
+    }
+}
+
+struct MapAccess<'t, S> {
+    members: std::slice::Iter<'t, Pair>,
+    /// Value of the member whose key was just read.
+    value: Option<&'t ValueToken>,
+    source: S,
+}
+
+impl<'t, 'de, S: Source<'t, 'de>> de::MapAccess<'de> for MapAccess<'t, S> {
+    type Error = DeserializeError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, DeserializeError> {
+        let Some(pair) = self.members.next() else {
+            return Ok(None);
+        };
+        self.value = Some(&pair.value);
+        let key = KeyDeserializer {
+            pair,
+            source: self.source,
+        };
+        locate(seed.deserialize(key), pair.key_span).map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, DeserializeError> {
+        match self.value.take() {
+            Some(token) => seed.deserialize(TokenDeserializer {
+                token,
+                source: self.source,
+            }),
+            None => Err(de::Error::custom("value requested before key")),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.members.len())
+    }
+}
+
+/// An object key, which can also stand for an integer map key.
+struct KeyDeserializer<'t, S> {
+    pair: &'t Pair,
+    source: S,
+}
+
+macro_rules! deserialize_integer_key {
+    ($($method:ident => $visit:ident,)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeserializeError> {
+                match self.pair.key.parse() {
+                    Ok(value) => visitor.$visit(value),
+                    Err(_) => Err(de::Error::invalid_type(Unexpected::Str(&self.pair.key), &visitor)),
+                }
+            }
+        )*
+    };
+}
+
+impl<'t, 'de, S: Source<'t, 'de>> de::Deserializer<'de> for KeyDeserializer<'t, S> {
+    type Error = DeserializeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeserializeError> {
+        match self.source.borrow(&self.pair.key, self.pair.key_span) {
+            Some(borrowed) => visitor.visit_borrowed_str(borrowed),
+            None => visitor.visit_str(&self.pair.key),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeserializeError> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, DeserializeError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, DeserializeError> {
+        visitor.visit_enum(StrDeserializer::<DeserializeError>::new(&self.pair.key))
+    }
+
+    deserialize_integer_key! {
+        deserialize_i8 => visit_i8,
+        deserialize_i16 => visit_i16,
+        deserialize_i32 => visit_i32,
+        deserialize_i64 => visit_i64,
+        deserialize_u8 => visit_u8,
+        deserialize_u16 => visit_u16,
+        deserialize_u32 => visit_u32,
+        deserialize_u64 => visit_u64,
+    }
+
+    forward_to_deserialize_any! {
+        bool i128 u128 f32 f64 char str string bytes byte_buf unit unit_struct
+        seq tuple tuple_struct map struct identifier ignored_any
+    }
+}
+
+struct EnumAccess<'t, S> {
+    pair: &'t Pair,
+    source: S,
+}
+
+impl<'t, 'de, S: Source<'t, 'de>> de::EnumAccess<'de> for EnumAccess<'t, S> {
+    type Error = DeserializeError;
+    type Variant = TokenDeserializer<'t, S>;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(
+        self,
+        seed: V,
+    ) -> Result<(V::Value, Self::Variant), DeserializeError> {
+        let key = KeyDeserializer {
+            pair: self.pair,
+            source: self.source,
+        };
+        let variant = locate(seed.deserialize(key), self.pair.key_span)?;
+        let value = TokenDeserializer {
+            token: &self.pair.value,
+            source: self.source,
+        };
+        Ok((variant, value))
+    }
+}
+
+/// The content of a variant written as `{"Variant": content}`.
+impl<'t, 'de, S: Source<'t, 'de>> de::VariantAccess<'de> for TokenDeserializer<'t, S> {
+    type Error = DeserializeError;
+
+    fn unit_variant(self) -> Result<(), DeserializeError> {
+        <()>::deserialize(self)
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(
+        self,
+        seed: T,
+    ) -> Result<T::Value, DeserializeError> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, DeserializeError> {
+        de::Deserializer::deserialize_any(self, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, DeserializeError> {
+        de::Deserializer::deserialize_any(self, visitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{from_str, from_str_with_options, from_token};
+    use crate::error::DeserializeError;
+    use crate::json::parse;
+    use crate::options::{Dialect, ParseOptions};
+    use serde::Deserialize;
+    use std::borrow::Cow;
+    use std::collections::{BTreeMap, HashMap};
+
+    fn value<'de, T: Deserialize<'de>>(input: &'de str) -> T {
+        match from_str(input) {
+            Ok(value) => value,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    fn error<'de, T: Deserialize<'de> + std::fmt::Debug>(input: &'de str) -> String {
+        match from_str::<T>(input) {
+            Ok(value) => panic!("expected an error, got {:?}", value),
+            Err(e) => e.to_string(),
+        }
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    #[serde(rename_all = "lowercase")]
+    enum Status {
+        Active,
+        Suspended { until: String },
+        Moved(u32),
+        Span(u32, u32),
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Account {
+        id: u64,
+        name: String,
+        email: Option<String>,
+        tags: Vec<String>,
+        limits: HashMap<String, f64>,
+        status: Status,
+        #[serde(default)]
+        history: Vec<Status>,
+    }
+
+    #[test]
+    fn structs_enums_options_and_collections() {
+        let account: Account = value(
+            r#"{
+                "id": 18446744073709551615,
+                "name": "Ada \"A\" L.",
+                "email": null,
+                "tags": ["admin", "ops"],
+                "limits": {"cpu": 1.5, "memory": 2048},
+                "status": {"suspended": {"until": "2027-01-01"}},
+                "unknown": [1, {"ignored": true}]
+            }"#,
+        );
+        assert_eq!(
+            Account {
+                id: u64::MAX,
+                name: "Ada \"A\" L.".to_string(),
+                email: None,
+                tags: vec!["admin".to_string(), "ops".to_string()],
+                limits: HashMap::from([("cpu".to_string(), 1.5), ("memory".to_string(), 2048.0)]),
+                status: Status::Suspended {
+                    until: "2027-01-01".to_string()
+                },
+                history: Vec::new(),
+            },
+            account
+        );
+        assert_eq!(
+            vec![Status::Active, Status::Moved(7), Status::Span(1, 2)],
+            value::<Vec<Status>>(r#"["active", {"moved": 7}, {"span": [1, 2]}]"#)
+        );
+        assert_eq!(Some(-3i8), value::<Option<i8>>("-3"));
+        assert_eq!(
+            (u128::MAX, i128::MIN, 9007199254740993u64),
+            value::<(u128, i128, u64)>(
+                "[340282366920938463463374607431768211455, \
+                 -170141183460469231731687303715884105728, 9007199254740993]"
+            )
+        );
+        assert_eq!(
+            (1u8, 'x', true),
+            value::<(u8, char, bool)>(r#"[1, "x", true]"#)
+        );
+        assert_eq!(
+            BTreeMap::from([(1u32, ()), (20, ())]),
+            value::<BTreeMap<u32, ()>>(r#"{"20": null, "1": null}"#)
+        );
+    }
+
+    #[test]
+    fn borrows_strings_without_escapes() {
+        #[derive(Deserialize)]
+        struct Borrowed<'a> {
+            plain: &'a str,
+            #[serde(borrow)]
+            escaped: Cow<'a, str>,
+            #[serde(borrow)]
+            unescaped: Cow<'a, str>,
+            #[serde(borrow)]
+            keys: HashMap<&'a str, &'a str>,
+        }
+        let input =
+            r#"{"plain": "héllo", "escaped": "a\nb", "unescaped": "c", "keys": {"k": "v"}}"#;
+        let borrowed: Borrowed = value(input);
+        assert_eq!("héllo", borrowed.plain);
+        assert!(matches!(borrowed.escaped, Cow::Owned(ref s) if s == "a\nb"));
+        assert!(matches!(borrowed.unescaped, Cow::Borrowed("c")));
+        assert_eq!(Some(&"v"), borrowed.keys.get("k"));
+
+        let options = ParseOptions {
+            dialect: Dialect::Json5,
+            ..ParseOptions::default()
+        };
+        let map: HashMap<&str, &str> =
+            match from_str_with_options("{key: 'single', \"double\": \"x\"}", &options) {
+                Ok(map) => map,
+                Err(e) => panic!("{}", e),
+            };
+        assert_eq!(Some(&"single"), map.get("key"));
+        assert_eq!(Some(&"x"), map.get("double"));
+
+        let json = match parse(r#"{"a": "b\tc"}"#) {
+            Ok(json) => json,
+            Err(e) => panic!("{}", e),
+        };
+        match from_token::<HashMap<&str, &str>>(&json.token) {
+            Ok(map) => assert_eq!(Some(&"b\tc"), map.get("a")),
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    #[test]
+    fn errors_carry_positions() {
+        assert_eq!(
+            "invalid type: string \"7\", expected u64 at line 2, column 11",
+            error::<Account>("{\n    \"id\": \"7\"}")
+        );
+        assert_eq!(
+            "missing field `name` at line 1, column 1",
+            error::<Account>(r#"{"id": 7}"#)
+        );
+        assert_eq!(
+            "invalid value: integer `300`, expected u8 at line 1, column 5",
+            error::<Vec<u8>>("[1, 300]")
+        );
+        assert_eq!(
+            "unknown variant `gone`, expected one of `active`, `suspended`, `moved`, `span` at line 1, column 1",
+            error::<Status>(r#""gone""#)
+        );
+        assert_eq!(
+            "invalid length 3, expected fewer elements in array at line 1, column 1",
+            error::<(u8, u8)>("[1, 2, 3]")
+        );
+        assert_eq!(
+            "invalid type: string \"x\", expected u32 at line 1, column 2",
+            error::<HashMap<u32, u8>>(r#"{"x": 1}"#)
+        );
+        assert_eq!(
+            "invalid type: string \"a\\\\b\", expected a borrowed string at line 1, column 1",
+            error::<&str>(r#""a\\b""#)
+        );
+        assert_eq!(
+            "number 1e400 is out of range at line 1, column 2",
+            error::<Vec<f64>>("[1e400]")
+        );
+        assert!(matches!(
+            from_str::<u8>("tru"),
+            Err(DeserializeError::Parse(_))
+        ));
+    }
+
+    #[test]
+    fn truncated_input_is_an_error() {
+        for (input, line, column) in [("[1, 2", 1, 6), ("{\n  \"id\": 7,\n", 3, 1)] {
+            match from_str::<Vec<u32>>(input) {
+                Err(DeserializeError::Parse(e)) => {
+                    assert_eq!((line, column), (e.line, e.column), "{:?}", input)
+                }
+                other => panic!("{:?}: expected a parse error, got {:?}", input, other),
+            }
+        }
+        assert_eq!(
+            "Expected ',' or ']' at line 1, column 6",
+            error::<Vec<u32>>("[1, 2")
+        );
+    }
+}
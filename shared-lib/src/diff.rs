@@ -0,0 +1,355 @@
+use crate::options::{ArrayAlignment, DiffOptions};
+use crate::patch::{JsonPatch, PatchOperation, equal, keys};
+use crate::pointer::JsonPointer;
+use crate::types::{Object, ValueToken};
+use std::fmt;
+
+/// One difference between two JSON documents.
+///
+/// Paths are JSON Pointers into the document as it is after the changes
+/// before this one, so applying the changes in order turns the first
+/// document into the second, as [`JsonPatch::from_changes`] does.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+    Added {
+        path: JsonPointer,
+        value: ValueToken,
+    },
+    Removed {
+        path: JsonPointer,
+        value: ValueToken,
+    },
+    /// A scalar changed, or a value was replaced by one of another type.
+    Changed {
+        path: JsonPointer,
+        from: ValueToken,
+        to: ValueToken,
+    },
+}
+
+impl Change {
+    pub fn path(&self) -> &JsonPointer {
+        match self {
+            Change::Added { path, .. }
+            | Change::Removed { path, .. }
+            | Change::Changed { path, .. } => path,
+        }
+    }
+}
+
+/// One line per change: `+ "/path": value`, `- "/path": value` or
+/// `~ "/path": old -> new`, with values as compact JSON.
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let json = crate::serializer::to_json_string;
+        match self {
+            Change::Added { path, value } => write!(f, "+ \"{}\": {}", path, json(value)),
+            Change::Removed { path, value } => write!(f, "- \"{}\": {}", path, json(value)),
+            Change::Changed { path, from, to } => {
+                write!(f, "~ \"{}\": {} -> {}", path, json(from), json(to))
+            }
+        }
+    }
+}
+
+/// Compare two documents, object members by key and array elements by index.
+///
+/// ```
+/// use shared_lib::{Change, diff, parse};
+///
+/// let a = parse(r#"{"name": "api", "replicas": 2, "ports": [80]}"#).unwrap();
+/// let b = parse(r#"{"name": "api", "replicas": 3, "ports": [80, 443]}"#).unwrap();
+/// let changes: Vec<String> = diff(&a.token, &b.token).iter().map(Change::to_string).collect();
+/// assert_eq!(vec![r#"~ "/replicas": 2 -> 3"#, r#"+ "/ports/1": 443"#], changes);
+/// ```
+pub fn diff(from: &ValueToken, to: &ValueToken) -> Vec<Change> {
+    diff_with_options(from, to, &DiffOptions::default())
+}
+
+/// [`diff`] with a choice of how array elements are aligned.
+pub fn diff_with_options(from: &ValueToken, to: &ValueToken, options: &DiffOptions) -> Vec<Change> {
+    let mut differ = Differ {
+        options,
+        path: JsonPointer::root(),
+        changes: Vec::new(),
+    };
+    differ.value(from, to);
+    differ.changes
+}
+
+impl JsonPatch {
+    /// The patch that makes the given changes, in order.
+    pub fn from_changes(changes: &[Change]) -> Self {
+        let operations = changes
+            .iter()
+            .map(|change| match change {
+                Change::Added { path, value } => PatchOperation::Add {
+                    path: path.clone(),
+                    value: value.clone(),
+                },
+                Change::Removed { path, .. } => PatchOperation::Remove { path: path.clone() },
+                Change::Changed { path, to, .. } => PatchOperation::Replace {
+                    path: path.clone(),
+                    value: to.clone(),
+                },
+            })
+            .collect();
+        JsonPatch { operations }
+    }
+}
+
+struct Differ<'a> {
+    options: &'a DiffOptions,
+    /// Path of the values being compared.
+    path: JsonPointer,
+    changes: Vec<Change>,
+}
+
+impl Differ<'_> {
+    fn value(&mut self, from: &ValueToken, to: &ValueToken) {
+        match (from, to) {
+            (
+                ValueToken::ObjectToken { token: from, .. },
+                ValueToken::ObjectToken { token: to, .. },
+            ) => self.object(from, to),
+            (
+                ValueToken::ArrayToken { token: from, .. },
+                ValueToken::ArrayToken { token: to, .. },
+            ) => match self.options.arrays {
+                ArrayAlignment::Index => self.array_by_index(&from.values, &to.values),
+                ArrayAlignment::Lcs => self.array_by_lcs(&from.values, &to.values),
+            },
+            _ if equal(from, to) => {}
+            _ => self.changes.push(Change::Changed {
+                path: self.path.clone(),
+                from: from.clone(),
+                to: to.clone(),
+            }),
+        }
+    }
+
+    fn at(&mut self, token: &str, from: &ValueToken, to: &ValueToken) {
+        self.path.push(token);
+        self.value(from, to);
+        if let Some((parent, _)) = self.path.split_last() {
+            self.path = parent;
+        }
+    }
+
+    fn child(&self, token: &str) -> JsonPointer {
+        let mut path = self.path.clone();
+        path.push(token);
+        path
+    }
+
+    fn added(&mut self, token: &str, value: &ValueToken) {
+        let path = self.child(token);
+        self.changes.push(Change::Added {
+            path,
+            value: value.clone(),
+        });
+    }
+
+    fn removed(&mut self, token: &str, value: &ValueToken) {
+        let path = self.child(token);
+        self.changes.push(Change::Removed {
+            path,
+            value: value.clone(),
+        });
+    }
+
+    fn object(&mut self, from: &Object, to: &Object) {
+        for key in keys(from) {
+            if let (Some(value), false) = (from.get(key), to.contains_key(key)) {
+                self.removed(key, value);
+            }
+        }
+        for key in keys(to) {
+            let Some(to_value) = to.get(key) else {
+                continue;
+            };
+            match from.get(key) {
+                Some(from_value) => self.at(key, from_value, to_value),
+                None => self.added(key, to_value),
+            }
+        }
+    }
+
+    fn array_by_index(&mut self, from: &[Box<ValueToken>], to: &[Box<ValueToken>]) {
+        let common = from.len().min(to.len());
+        for (index, (from, to)) in from.iter().zip(to).enumerate() {
+            self.at(&index.to_string(), from, to);
+        }
+        // Remove from the end so that earlier indexes stay valid.
+        for index in (common..from.len()).rev() {
+            self.removed(&index.to_string(), &from[index]);
+        }
+        for (index, value) in to.iter().enumerate().skip(common) {
+            self.added(&index.to_string(), value);
+        }
+    }
+
+    /// Keep a longest common subsequence of equal elements and compare the
+    /// runs between them. Within a run, removed and added elements are
+    /// paired up and compared, so a changed object still shows as changes
+    /// to its members; the rest are removed or added.
+    fn array_by_lcs(&mut self, from: &[Box<ValueToken>], to: &[Box<ValueToken>]) {
+        // lengths[i][j] is the LCS length of from[i..] and to[j..].
+        let mut lengths = vec![vec![0usize; to.len() + 1]; from.len() + 1];
+        for i in (0..from.len()).rev() {
+            for j in (0..to.len()).rev() {
+                lengths[i][j] = if equal(&from[i], &to[j]) {
+                    lengths[i + 1][j + 1] + 1
+                } else {
+                    lengths[i + 1][j].max(lengths[i][j + 1])
+                };
+            }
+        }
+
+        // `index` is the position in the array as patched so far.
+        let (mut i, mut j, mut index) = (0, 0, 0);
+        while i < from.len() || j < to.len() {
+            if i < from.len() && j < to.len() && equal(&from[i], &to[j]) {
+                i += 1;
+                j += 1;
+                index += 1;
+                continue;
+            }
+            let (run_start_i, run_start_j) = (i, j);
+            while i < from.len()
+                && (j == to.len() || lengths[i + 1][j] >= lengths[i][j + 1])
+                && !(j < to.len() && equal(&from[i], &to[j]))
+            {
+                i += 1;
+            }
+            while j < to.len()
+                && (i == from.len() || lengths[i][j + 1] > lengths[i + 1][j])
+                && !(i < from.len() && equal(&from[i], &to[j]))
+            {
+                j += 1;
+            }
+            let removed = &from[run_start_i..i];
+            let added = &to[run_start_j..j];
+            let paired = removed.len().min(added.len());
+            for (from, to) in removed.iter().zip(added) {
+                self.at(&index.to_string(), from, to);
+                index += 1;
+            }
+            for value in &removed[paired..] {
+                self.removed(&index.to_string(), value);
+            }
+            for value in &added[paired..] {
+                self.added(&index.to_string(), value);
+                index += 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Change, diff, diff_with_options};
+    use crate::json::parse;
+    use crate::options::{ArrayAlignment, DiffOptions};
+    use crate::patch::{JsonPatch, equal};
+    use crate::types::ValueToken;
+
+    fn token(input: &str) -> ValueToken {
+        match parse(input) {
+            Ok(json) => *json.token,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    fn lines(changes: &[Change]) -> Vec<String> {
+        changes.iter().map(Change::to_string).collect()
+    }
+
+    const LCS: DiffOptions = DiffOptions {
+        arrays: ArrayAlignment::Lcs,
+    };
+
+    #[test]
+    fn objects_by_key() {
+        let from = token(r#"{"a": 1, "b": {"c": true, "d": [1]}, "e": null}"#);
+        let to = token(r#"{"b": {"d": [1], "c": false}, "a": 1, "f": "x"}"#);
+        assert_eq!(
+            vec![
+                r#"- "/e": null"#,
+                r#"~ "/b/c": true -> false"#,
+                r#"+ "/f": "x""#
+            ],
+            lines(&diff(&from, &to))
+        );
+        assert!(diff(&from, &from).is_empty());
+        assert_eq!(
+            vec![r#"~ "": {"a":1} -> [1]"#],
+            lines(&diff(&token(r#"{"a": 1}"#), &token("[1]")))
+        );
+    }
+
+    #[test]
+    fn arrays_by_index() {
+        let from = token("[1, 2, 3, 4]");
+        assert_eq!(
+            vec![
+                r#"~ "/0": 1 -> 0"#,
+                r#"~ "/1": 2 -> 1"#,
+                r#"~ "/2": 3 -> 2"#,
+                r#"~ "/3": 4 -> 3"#,
+                r#"+ "/4": 4"#
+            ],
+            lines(&diff(&from, &token("[0, 1, 2, 3, 4]")))
+        );
+        assert_eq!(
+            vec![r#"- "/3": 4"#, r#"- "/2": 3"#],
+            lines(&diff(&from, &token("[1, 2]")))
+        );
+    }
+
+    #[test]
+    fn arrays_by_lcs() {
+        let from = token("[1, 2, 3, 4]");
+        assert_eq!(
+            vec![r#"+ "/0": 0"#],
+            lines(&diff_with_options(&from, &token("[0, 1, 2, 3, 4]"), &LCS))
+        );
+        assert_eq!(
+            vec![r#"- "/1": 2"#, r#"~ "/2": 4 -> 5"#],
+            lines(&diff_with_options(&from, &token("[1, 3, 5]"), &LCS))
+        );
+        assert_eq!(
+            vec![r#"~ "/1/name": "b" -> "B""#],
+            lines(&diff_with_options(
+                &token(r#"[{"name": "a"}, {"name": "b"}, {"name": "c"}]"#),
+                &token(r#"[{"name": "a"}, {"name": "B"}, {"name": "c"}]"#),
+                &LCS
+            ))
+        );
+    }
+
+    #[test]
+    fn changes_apply_as_a_patch() {
+        let cases = [
+            (
+                r#"{"a": [1, 2, 3], "b": {"c": 1}}"#,
+                r#"{"a": [3, 1], "b": {}, "d": [[]]}"#,
+            ),
+            ("[1, 2, 3, 4, 5, 6]", "[0, 2, 7, 4, 6, 8, 9]"),
+            (r#"[{"x": 1}, 2, [3]]"#, r#"[2, {"x": 2}, [3, 4], 5]"#),
+            ("[]", "[1, 2]"),
+            ("[1, 2]", "[]"),
+        ];
+        for options in [DiffOptions::default(), LCS] {
+            for (from, to) in cases {
+                let (from, to) = (token(from), token(to));
+                let changes = diff_with_options(&from, &to, &options);
+                let mut patched = from.clone();
+                match JsonPatch::from_changes(&changes).apply(&mut patched) {
+                    Ok(()) => assert!(equal(&patched, &to), "{:?}", lines(&changes)),
+                    Err(e) => panic!("{:?}: {}", lines(&changes), e),
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,471 @@
+use crate::pointer::JsonPointer;
+use crate::types::Position;
+use std::fmt;
+use std::io;
+
+/// The reason a parse failed.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ErrorKind {
+    /// A specific character or token was required; the payload describes it.
+    Expected(&'static str),
+    /// A `\uXXXX` escape for a surrogate that is not part of a pair.
+    LoneSurrogate,
+    InvalidUnicodeEscape,
+    UnexpectedCharacter,
+    UnexpectedComma,
+    UnexpectedEscapeCharacter,
+    UnexpectedToken,
+    /// An object repeated a key under `DuplicateKeys::Error`.
+    DuplicateKey,
+    /// More nested arrays and objects than `ParseOptions::max_depth` allows.
+    NestingTooDeep,
+    /// Non-whitespace input after the top-level value.
+    TrailingCharacters,
+    UnterminatedString,
+    /// A `/*` comment without a closing `*/`.
+    UnterminatedComment,
+    /// A raw U+0000 to U+001F character inside a string.
+    ControlCharacter,
+    /// A number has a leading zero followed by more digits, as in `012`.
+    LeadingZero,
+    /// A `-` sign is not followed by a digit.
+    MissingIntegerDigits,
+    /// A decimal point is not followed by a digit.
+    MissingFractionDigits,
+    /// An exponent marker (and optional sign) is not followed by a digit.
+    MissingExponentDigits,
+    /// A complete number runs straight into a letter, sign or second `.`.
+    InvalidNumberSuffix,
+    /// Bytes read from a stream are not valid UTF-8.
+    InvalidUtf8,
+    /// A top-level number or literal in a JSON text sequence is not followed
+    /// by whitespace, so it may have been cut short.
+    Truncated,
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ErrorKind::Expected(what) => write!(f, "Expected {}", what),
+            ErrorKind::LoneSurrogate => write!(f, "Unpaired surrogate in unicode escape"),
+            ErrorKind::InvalidUnicodeEscape => write!(f, "Invalid unicode escape"),
+            ErrorKind::UnexpectedCharacter => write!(f, "Unexpected character"),
+            ErrorKind::UnexpectedComma => write!(f, "Unexpected ','"),
+            ErrorKind::UnexpectedEscapeCharacter => write!(f, "Unexpected escape character"),
+            ErrorKind::UnexpectedToken => write!(f, "Unexpected token"),
+            ErrorKind::DuplicateKey => write!(f, "Duplicate key"),
+            ErrorKind::NestingTooDeep => write!(f, "Nesting too deep"),
+            ErrorKind::TrailingCharacters => write!(f, "Unexpected trailing characters"),
+            ErrorKind::UnterminatedString => write!(f, "Unterminated string"),
+            ErrorKind::UnterminatedComment => write!(f, "Unterminated comment"),
+            ErrorKind::ControlCharacter => write!(f, "Unescaped control character in string"),
+            ErrorKind::LeadingZero => write!(f, "Leading zero in number"),
+            ErrorKind::MissingIntegerDigits => write!(f, "Expected digit after '-'"),
+            ErrorKind::MissingFractionDigits => write!(f, "Expected digit after '.'"),
+            ErrorKind::MissingExponentDigits => write!(f, "Expected digit in exponent"),
+            ErrorKind::InvalidNumberSuffix => write!(f, "Unexpected character after number"),
+            ErrorKind::InvalidUtf8 => write!(f, "Invalid UTF-8"),
+            ErrorKind::Truncated => write!(f, "Possibly truncated value"),
+        }
+    }
+}
+
+/// One step of the path from the root value to the value being parsed.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PathSegment {
+    Index(usize),
+    Key(String),
+}
+
+/// Error returned by [`crate::parse`] and [`crate::parse_with_options`].
+///
+/// `offset` is an absolute byte offset into the input; `line` and `column`
+/// are 1-based, with columns counted in characters.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub kind: ErrorKind,
+    pub offset: usize,
+    pub line: usize,
+    pub column: usize,
+    /// Nesting path from the root, outermost segment first.
+    pub path: Vec<PathSegment>,
+}
+
+impl ParseError {
+    /// Error at a byte offset into the input. The line and column are
+    /// filled in by [`ParseError::locate`] once the error reaches the
+    /// top-level parser.
+    pub(crate) fn at(kind: ErrorKind, offset: usize) -> Self {
+        ParseError {
+            kind,
+            offset,
+            line: 0,
+            column: 0,
+            path: Vec::new(),
+        }
+    }
+
+    /// Record that the error happened inside the given array element or
+    /// object member.
+    pub(crate) fn within(mut self, segment: PathSegment) -> Self {
+        self.path.insert(0, segment);
+        self
+    }
+
+    /// Compute the line and column of `offset` within `input`.
+    pub(crate) fn locate(mut self, input: &str) -> Self {
+        let before = &input[..self.offset];
+        self.line = before.matches('\n').count() + 1;
+        self.column = match before.rfind('\n') {
+            Some(newline) => before[newline + 1..].chars().count() + 1,
+            None => before.chars().count() + 1,
+        };
+        self
+    }
+
+    /// Translate the location of an error found in a slice into the larger
+    /// text, given where the slice begins.
+    pub(crate) fn offset_by(mut self, origin: Position) -> Self {
+        let position = Position {
+            offset: self.offset,
+            line: self.line,
+            column: self.column,
+        }
+        .offset_by(origin);
+        self.offset = position.offset;
+        self.line = position.line;
+        self.column = position.column;
+        self
+    }
+}
+
+impl fmt::Display for PathSegment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PathSegment::Index(index) => write!(f, "[{}]", index),
+            PathSegment::Key(key) => write!(f, "[{:?}]", key),
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} at line {}, column {}",
+            self.kind, self.line, self.column
+        )?;
+        if !self.path.is_empty() {
+            write!(f, " (in $")?;
+            for segment in &self.path {
+                write!(f, "{}", segment)?;
+            }
+            write!(f, ")")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Error returned by [`crate::JsonPointer::parse`] for text that is not an
+/// RFC 6901 JSON Pointer.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PointerError {
+    /// A non-empty pointer does not start with `/`.
+    MissingSlash,
+    /// A `~` is not followed by `0` or `1`; the payload is its byte offset.
+    InvalidEscape(usize),
+}
+
+impl fmt::Display for PointerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PointerError::MissingSlash => write!(f, "JSON Pointer must start with '/'"),
+            PointerError::InvalidEscape(offset) => {
+                write!(f, "Invalid escape at offset {} in JSON Pointer", offset)
+            }
+        }
+    }
+}
+
+impl std::error::Error for PointerError {}
+
+/// Error returned by [`crate::JsonPath::parse`] for a query that is not
+/// well-formed or not well-typed under RFC 9535.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsonPathError {
+    pub message: String,
+    /// Byte offset in the query where the problem was found.
+    pub offset: usize,
+}
+
+impl JsonPathError {
+    pub(crate) fn at(message: &str, offset: usize) -> Self {
+        JsonPathError {
+            message: message.to_string(),
+            offset,
+        }
+    }
+}
+
+impl fmt::Display for JsonPathError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at offset {} in JSONPath", self.message, self.offset)
+    }
+}
+
+impl std::error::Error for JsonPathError {}
+
+/// Error returned while reading JSON from a stream: either the underlying
+/// reader failed or the text is not valid JSON.
+#[derive(Debug)]
+pub enum ReadError {
+    Io(io::Error),
+    Parse(ParseError),
+}
+
+impl fmt::Display for ReadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReadError::Io(e) => write!(f, "I/O error: {}", e),
+            ReadError::Parse(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for ReadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ReadError::Io(e) => Some(e),
+            ReadError::Parse(e) => Some(e),
+        }
+    }
+}
+
+impl From<io::Error> for ReadError {
+    fn from(e: io::Error) -> Self {
+        ReadError::Io(e)
+    }
+}
+
+impl From<ParseError> for ReadError {
+    fn from(e: ParseError) -> Self {
+        ReadError::Parse(e)
+    }
+}
+
+/// Error returned by [`crate::JsonReader::with_options`] and
+/// [`crate::JsonPushParser::with_options`] for [`crate::ParseOptions`] they
+/// cannot honour.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnsupportedOption {
+    /// Name of the `ParseOptions` field.
+    pub option: &'static str,
+    pub reason: &'static str,
+}
+
+impl fmt::Display for UnsupportedOption {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Unsupported option {}: {}", self.option, self.reason)
+    }
+}
+
+impl std::error::Error for UnsupportedOption {}
+
+/// Error returned when an RFC 6902 JSON Patch cannot be read or applied.
+/// `index` is the position of the failing operation in the patch.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PatchError {
+    /// The patch text is not valid JSON.
+    Parse(ParseError),
+    /// The patch is not an array of operation objects, or an operation is
+    /// missing a member, has an unknown `op`, or is otherwise malformed.
+    Invalid { index: usize, message: String },
+    /// `path` or `from` does not refer to a value, or the value that
+    /// contains it.
+    NoValue { index: usize, path: JsonPointer },
+    /// A `test` operation found a different value.
+    TestFailed { index: usize, path: JsonPointer },
+}
+
+impl fmt::Display for PatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PatchError::Parse(e) => write!(f, "{}", e),
+            PatchError::Invalid { index, message } => {
+                write!(f, "Invalid patch operation {}: {}", index, message)
+            }
+            PatchError::NoValue { index, path } => {
+                write!(f, "Patch operation {}: no value at \"{}\"", index, path)
+            }
+            PatchError::TestFailed { index, path } => {
+                write!(f, "Patch operation {}: test failed at \"{}\"", index, path)
+            }
+        }
+    }
+}
+
+impl std::error::Error for PatchError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PatchError::Parse(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<ParseError> for PatchError {
+    fn from(e: ParseError) -> Self {
+        PatchError::Parse(e)
+    }
+}
+
+/// Error returned by [`crate::JsonSchema::new`] for a schema that cannot be
+/// used.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SchemaError {
+    /// The schema text is not valid JSON.
+    Parse(ParseError),
+    /// A keyword at `path` has a value the validator cannot use, such as a
+    /// `pattern` that is not a valid regular expression or a `$ref` that
+    /// does not resolve within the document.
+    Invalid { path: JsonPointer, message: String },
+}
+
+impl fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SchemaError::Parse(e) => write!(f, "{}", e),
+            SchemaError::Invalid { path, message } => {
+                write!(f, "Invalid schema at \"{}\": {}", path, message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SchemaError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SchemaError::Parse(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<ParseError> for SchemaError {
+    fn from(e: ParseError) -> Self {
+        SchemaError::Parse(e)
+    }
+}
+
+/// One way an instance fails a JSON Schema.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidationError {
+    /// Location of the failing value in the instance.
+    pub instance_path: JsonPointer,
+    /// Location of the failing keyword in the schema document. After a
+    /// `$ref` this is within the referenced schema.
+    pub schema_path: JsonPointer,
+    pub message: String,
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "\"{}\": {} (schema \"{}\")",
+            self.instance_path, self.message, self.schema_path
+        )
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+/// Error returned by [`crate::from_str`] and [`crate::from_token`].
+#[cfg(feature = "serde")]
+#[derive(Debug, Clone, PartialEq)]
+pub enum DeserializeError {
+    /// The input is not valid JSON.
+    Parse(ParseError),
+    /// The JSON does not fit the target type. `position` is where the
+    /// offending value starts, if it came from parsed text.
+    Invalid {
+        message: String,
+        position: Option<Position>,
+    },
+}
+
+#[cfg(feature = "serde")]
+impl fmt::Display for DeserializeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DeserializeError::Parse(e) => write!(f, "{}", e),
+            DeserializeError::Invalid {
+                message,
+                position: Some(position),
+            } => write!(
+                f,
+                "{} at line {}, column {}",
+                message, position.line, position.column
+            ),
+            DeserializeError::Invalid { message, .. } => write!(f, "{}", message),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl std::error::Error for DeserializeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            DeserializeError::Parse(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<ParseError> for DeserializeError {
+    fn from(e: ParseError) -> Self {
+        DeserializeError::Parse(e)
+    }
+}
+
+/// Error returned by [`crate::to_value`] and the functions built on it.
+#[cfg(feature = "serde")]
+#[derive(Debug)]
+pub enum SerializeError {
+    /// Writing the output failed.
+    Io(io::Error),
+    /// The value cannot be represented as JSON, such as a map with keys
+    /// that are not strings, numbers or booleans, or its `Serialize`
+    /// implementation failed.
+    Invalid(String),
+}
+
+#[cfg(feature = "serde")]
+impl fmt::Display for SerializeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SerializeError::Io(e) => write!(f, "I/O error: {}", e),
+            SerializeError::Invalid(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl std::error::Error for SerializeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SerializeError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<io::Error> for SerializeError {
+    fn from(e: io::Error) -> Self {
+        SerializeError::Io(e)
+    }
+}
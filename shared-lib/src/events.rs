@@ -0,0 +1,615 @@
+use crate::cursor::Cursor;
+use crate::error::{ErrorKind, ParseError, PathSegment, UnsupportedOption};
+use crate::number::parse_number;
+use crate::options::{Dialect, DuplicateKeys, LoneSurrogates, ParseOptions};
+use crate::string::parse_string;
+use crate::types::{Number, Position, Span, ValueToken};
+use std::collections::HashSet;
+
+/// One step through a document, as reported by [`crate::JsonReader`] and
+/// [`crate::JsonPushParser`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum JsonEvent {
+    StartObject,
+    EndObject,
+    StartArray,
+    EndArray,
+    /// The key of an object member; the member's value follows.
+    Key(String),
+    String(String),
+    Number(Number),
+    Bool(bool),
+    Null,
+}
+
+/// Outcome of [`EventParser::step`].
+pub enum Step {
+    Event(JsonEvent),
+    /// The input ran out before the next event was complete.
+    NeedMore,
+    /// The document is over, or parsing failed earlier.
+    End,
+}
+
+/// Bytes handed to [`EventParser::step`], and whether more will follow.
+pub struct Input<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    eof: bool,
+}
+
+impl<'a> Input<'a> {
+    pub fn new(bytes: &'a [u8], eof: bool) -> Self {
+        Input { bytes, pos: 0, eof }
+    }
+
+    /// Number of bytes the parser has taken; they need not be offered again.
+    pub fn consumed(&self) -> usize {
+        self.pos
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.pos).copied()
+    }
+}
+
+enum Frame {
+    /// `index` is the position of the current or next element.
+    Array {
+        index: usize,
+        start: Position,
+        skip_start: usize,
+    },
+    /// `key` is the key of the current member, once it has been read.
+    /// `keys` holds the keys seen so far unless duplicates are allowed.
+    Object {
+        key: Option<String>,
+        keys: HashSet<String>,
+        start: Position,
+        skip_start: usize,
+    },
+}
+
+/// A member whose key repeats an earlier one, and whose value is still
+/// being read.
+struct Duplicate {
+    /// Stack depth of the object the member belongs to.
+    depth: usize,
+    key_start: Position,
+}
+
+enum State {
+    /// Expecting a value: at the start, after `:` or after `,` in an array.
+    Value,
+    /// Just after `[`: a value or `]`.
+    ArrayStart,
+    /// Just after `{`: a key or `}`.
+    ObjectStart,
+    /// After `,` in an object.
+    Key,
+    Colon,
+    /// After a value inside an array or object: `,` or the closing bracket.
+    AfterValue,
+    /// Inside a string or key, which may span several inputs.
+    Quoted {
+        key: bool,
+    },
+    /// Inside a number or literal, which may span several inputs.
+    Word,
+    /// After the top-level value.
+    Done,
+    /// After an error; no more events.
+    Failed,
+}
+
+/// Resumable strict-JSON event parser shared by the pull and push readers.
+///
+/// Bytes are offered through [`Input`]s; everything offered is consumed, and
+/// a string or number cut off at the end of one input is kept and finished
+/// from the next. Strings and numbers are decoded by the tree parser's own
+/// token functions, so decoding rules, errors, positions and paths match
+/// [`crate::parse`].
+///
+/// A repeated key fails under [`DuplicateKeys::Error`] once its value has
+/// been read, as in the tree parser. Under [`DuplicateKeys::FirstWins`] the
+/// repeated member produces no events. [`DuplicateKeys::LastWins`] cannot be
+/// applied to events that are already out, so they are reported as read.
+pub struct EventParser {
+    options: ParseOptions,
+    position: Position,
+    chars: usize,
+    stack: Vec<Frame>,
+    state: State,
+    token: Vec<u8>,
+    token_start: Position,
+    escaped: bool,
+    /// Character count where the current value started, by the rules the
+    /// tree parser uses for `skip`.
+    skip_start: usize,
+    span: Span,
+    skip: usize,
+    duplicate: Option<Duplicate>,
+}
+
+impl EventParser {
+    /// Fails for the options only the tree parser supports: a dialect other
+    /// than [`Dialect::Json`] and [`LoneSurrogates::Preserve`], whose bytes
+    /// an event has nowhere to carry. `preserve_comments` has no effect on
+    /// strict JSON, here as in the tree parser.
+    pub fn new(options: ParseOptions) -> Result<Self, UnsupportedOption> {
+        if options.dialect != Dialect::Json {
+            return Err(UnsupportedOption {
+                option: "dialect",
+                reason: "streaming parsers accept strict JSON only",
+            });
+        }
+        if options.lone_surrogates == LoneSurrogates::Preserve {
+            return Err(UnsupportedOption {
+                option: "lone_surrogates",
+                reason: "events cannot carry WTF-8 strings",
+            });
+        }
+        Ok(EventParser {
+            options,
+            position: Position {
+                offset: 0,
+                line: 1,
+                column: 1,
+            },
+            chars: 0,
+            stack: Vec::new(),
+            state: State::Value,
+            token: Vec::new(),
+            token_start: Position::default(),
+            escaped: false,
+            skip_start: 0,
+            span: Span::default(),
+            skip: 0,
+            duplicate: None,
+        })
+    }
+
+    pub fn options(&self) -> &ParseOptions {
+        &self.options
+    }
+
+    /// Source position of the next unread byte.
+    pub fn position(&self) -> Position {
+        self.position
+    }
+
+    /// Number of characters consumed so far.
+    pub fn chars(&self) -> usize {
+        self.chars
+    }
+
+    /// Span of the value completed or key read by the latest event.
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    /// `skip` of the value completed by the latest event, as the tree
+    /// parser would report it.
+    pub fn skip(&self) -> usize {
+        self.skip
+    }
+
+    pub fn step(&mut self, input: &mut Input) -> Result<Step, ParseError> {
+        let step = self.next_step(input);
+        if step.is_err() {
+            self.state = State::Failed;
+        }
+        step
+    }
+
+    /// The next step, with the events of a member whose key repeats an
+    /// earlier one held back or turned into an error.
+    fn next_step(&mut self, input: &mut Input) -> Result<Step, ParseError> {
+        loop {
+            let step = self.advance(input)?;
+            let Some(duplicate) = &self.duplicate else {
+                return Ok(step);
+            };
+            let Step::Event(event) = step else {
+                return Ok(step);
+            };
+            // The member's value is complete once the stack is back to the
+            // object and the event is neither the key nor the start of a
+            // container.
+            let complete = self.stack.len() == duplicate.depth
+                && !matches!(
+                    event,
+                    JsonEvent::Key(_) | JsonEvent::StartArray | JsonEvent::StartObject
+                );
+            match self.options.duplicate_keys {
+                DuplicateKeys::Error if complete => {
+                    let key_start = duplicate.key_start;
+                    self.duplicate = None;
+                    return Err(self.error_at(ErrorKind::DuplicateKey, key_start, true));
+                }
+                DuplicateKeys::FirstWins => {
+                    if complete {
+                        self.duplicate = None;
+                    }
+                }
+                _ => return Ok(Step::Event(event)),
+            }
+        }
+    }
+
+    fn advance(&mut self, input: &mut Input) -> Result<Step, ParseError> {
+        loop {
+            match self.state {
+                State::Failed => return Ok(Step::End),
+                State::Quoted { key } => {
+                    if self.quoted(input) {
+                        return self.finish_string(key).map(Step::Event);
+                    } else if input.eof {
+                        return Err(self.error(ErrorKind::UnterminatedString, !key));
+                    }
+                    return Ok(Step::NeedMore);
+                }
+                State::Word => {
+                    if self.word(input) || input.eof {
+                        return self.finish_word().map(Step::Event);
+                    }
+                    return Ok(Step::NeedMore);
+                }
+                _ => {}
+            }
+
+            let byte = self.skip_whitespace(input);
+            match self.state {
+                // Whatever follows is not read when trailing content is allowed.
+                State::Done if self.options.allow_trailing_content => return Ok(Step::End),
+                _ if byte.is_none() && !input.eof => return Ok(Step::NeedMore),
+                State::Done => {
+                    return match byte {
+                        Some(_) => Err(self.error(ErrorKind::TrailingCharacters, false)),
+                        None => Ok(Step::End),
+                    };
+                }
+                State::ArrayStart if byte == Some(b']') => {
+                    return Ok(Step::Event(self.close(input, b']')));
+                }
+                State::Value | State::ArrayStart => {
+                    if let Some(event) = self.value(input, byte)? {
+                        return Ok(Step::Event(event));
+                    }
+                }
+                State::ObjectStart | State::Key => match byte {
+                    Some(b'"') => self.start_token(input, State::Quoted { key: true }),
+                    Some(b'}') if matches!(self.state, State::ObjectStart) => {
+                        return Ok(Step::Event(self.close(input, b'}')));
+                    }
+                    Some(b'}') => return Err(self.error(ErrorKind::UnexpectedComma, false)),
+                    _ => return Err(self.error(ErrorKind::Expected("'\"'"), false)),
+                },
+                State::Colon => match byte {
+                    Some(b':') => {
+                        self.bump(input, b':');
+                        // Member values count the whitespace after the colon.
+                        self.skip_start = self.chars;
+                        self.state = State::Value;
+                    }
+                    _ => return Err(self.error(ErrorKind::Expected("':'"), false)),
+                },
+                State::AfterValue => {
+                    let in_array = self.in_array();
+                    match byte {
+                        Some(b',') => {
+                            self.bump(input, b',');
+                            if let Some(Frame::Array { index, .. }) = self.stack.last_mut() {
+                                *index += 1;
+                                self.state = State::Value;
+                            } else {
+                                self.state = State::Key;
+                            }
+                        }
+                        Some(b']') if in_array => return Ok(Step::Event(self.close(input, b']'))),
+                        Some(b'}') if !in_array => {
+                            return Ok(Step::Event(self.close(input, b'}')));
+                        }
+                        _ if in_array => {
+                            return Err(self.error(ErrorKind::Expected("',' or ']'"), false));
+                        }
+                        _ => return Err(self.error(ErrorKind::Expected("',' or '}'"), false)),
+                    }
+                }
+                State::Quoted { .. } | State::Word | State::Failed => unreachable!(),
+            }
+        }
+    }
+
+    /// Start the value that begins with `byte`. Containers produce their
+    /// start event at once; strings, numbers and literals switch to
+    /// collecting their bytes and return `None`.
+    fn value(
+        &mut self,
+        input: &mut Input,
+        byte: Option<u8>,
+    ) -> Result<Option<JsonEvent>, ParseError> {
+        if !matches!(self.stack.last(), Some(Frame::Object { .. })) {
+            self.skip_start = self.chars;
+        }
+        match byte {
+            Some(bracket @ (b'{' | b'[')) => {
+                if self.stack.len() >= self.options.max_depth {
+                    return Err(self.error(ErrorKind::NestingTooDeep, true));
+                }
+                let start = self.position;
+                let skip_start = self.skip_start;
+                self.bump(input, bracket);
+                if bracket == b'{' {
+                    self.stack.push(Frame::Object {
+                        key: None,
+                        keys: HashSet::new(),
+                        start,
+                        skip_start,
+                    });
+                    self.state = State::ObjectStart;
+                    Ok(Some(JsonEvent::StartObject))
+                } else {
+                    self.stack.push(Frame::Array {
+                        index: 0,
+                        start,
+                        skip_start,
+                    });
+                    self.state = State::ArrayStart;
+                    Ok(Some(JsonEvent::StartArray))
+                }
+            }
+            Some(b'"') => {
+                self.start_token(input, State::Quoted { key: false });
+                Ok(None)
+            }
+            Some(b'-' | b'0'..=b'9' | b't' | b'f' | b'n') => {
+                self.start_token(input, State::Word);
+                Ok(None)
+            }
+            Some(b']') if matches!(self.state, State::Value) && self.in_array() => {
+                Err(self.error(ErrorKind::UnexpectedComma, false))
+            }
+            Some(b']' | b'}' | b',') => Err(self.error(ErrorKind::Expected("value token"), true)),
+            Some(_) => Err(self.error(ErrorKind::UnexpectedCharacter, true)),
+            None => Err(self.error(ErrorKind::Expected("value"), true)),
+        }
+    }
+
+    fn start_token(&mut self, input: &mut Input, state: State) {
+        self.token.clear();
+        self.token_start = self.position;
+        self.escaped = false;
+        if let State::Quoted { .. } = state {
+            self.bump(input, b'"');
+            self.token.push(b'"');
+        }
+        self.state = state;
+    }
+
+    /// Collect string bytes; true once the closing quote has been taken.
+    fn quoted(&mut self, input: &mut Input) -> bool {
+        while let Some(byte) = input.peek() {
+            self.bump(input, byte);
+            self.token.push(byte);
+            if self.escaped {
+                self.escaped = false;
+            } else if byte == b'\\' {
+                self.escaped = true;
+            } else if byte == b'"' {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Collect number or literal bytes; true once a byte that ends the word
+    /// is next. Letters, signs and points are taken too so that the number
+    /// rules can report them as a bad suffix.
+    fn word(&mut self, input: &mut Input) -> bool {
+        while let Some(byte) = input.peek() {
+            if !(byte.is_ascii_alphanumeric() || matches!(byte, b'+' | b'-' | b'.')) {
+                return true;
+            }
+            self.bump(input, byte);
+            self.token.push(byte);
+        }
+        false
+    }
+
+    fn finish_string(&mut self, key: bool) -> Result<JsonEvent, ParseError> {
+        let string = match self.decode(parse_string, !key)? {
+            ValueToken::StringToken { token, .. } => token,
+            _ => return Err(self.error(ErrorKind::Expected("string"), !key)),
+        };
+        self.span = Span {
+            start: self.token_start,
+            end: self.position,
+        };
+        if key {
+            // A repeat inside a member being skipped is skipped with it; one
+            // inside a member that will fail fails first, as in the tree
+            // parser.
+            let track = match self.options.duplicate_keys {
+                DuplicateKeys::Allow | DuplicateKeys::LastWins => false,
+                DuplicateKeys::FirstWins => self.duplicate.is_none(),
+                DuplicateKeys::Error => true,
+            };
+            let depth = self.stack.len();
+            if let Some(Frame::Object {
+                key: current, keys, ..
+            }) = self.stack.last_mut()
+            {
+                if track && !keys.insert(string.clone()) {
+                    self.duplicate = Some(Duplicate {
+                        depth,
+                        key_start: self.token_start,
+                    });
+                }
+                *current = Some(string.clone());
+            }
+            self.state = State::Colon;
+            Ok(JsonEvent::Key(string))
+        } else {
+            self.complete_scalar();
+            Ok(JsonEvent::String(string))
+        }
+    }
+
+    fn finish_word(&mut self) -> Result<JsonEvent, ParseError> {
+        let literal = match self.token[0] {
+            b't' => Some(("true", JsonEvent::Bool(true), "'true'")),
+            b'f' => Some(("false", JsonEvent::Bool(false), "'false'")),
+            b'n' => Some(("null", JsonEvent::Null, "'null'")),
+            _ => None,
+        };
+        let event = match literal {
+            Some((text, event, expected)) => {
+                if self.token != text.as_bytes() {
+                    return Err(self.error_at(
+                        ErrorKind::Expected(expected),
+                        self.token_start,
+                        true,
+                    ));
+                }
+                event
+            }
+            None => match self.decode(parse_number, true)? {
+                ValueToken::NumberToken { token, .. } => JsonEvent::Number(token),
+                _ => return Err(self.error(ErrorKind::Expected("number"), true)),
+            },
+        };
+        self.span = Span {
+            start: self.token_start,
+            end: self.position,
+        };
+        self.complete_scalar();
+        Ok(event)
+    }
+
+    fn complete_scalar(&mut self) {
+        self.skip = self.chars - self.skip_start;
+        self.state = self.after_value();
+    }
+
+    /// Decode the collected token with `parse`, one of the tree parser's
+    /// token functions.
+    fn decode(
+        &self,
+        parse: fn(&mut Cursor) -> Result<ValueToken, ParseError>,
+        in_value: bool,
+    ) -> Result<ValueToken, ParseError> {
+        let text = match std::str::from_utf8(&self.token) {
+            Ok(text) => text,
+            Err(e) => {
+                let valid = std::str::from_utf8(&self.token[..e.valid_up_to()]).unwrap_or_default();
+                return Err(self.error_within(ErrorKind::InvalidUtf8, valid, in_value));
+            }
+        };
+        let mut cursor = Cursor::new(text, &self.options);
+        parse(&mut cursor).map_err(|e| self.error_within(e.kind, &text[..e.offset], in_value))
+    }
+
+    /// Consume the closing bracket of the innermost array or object.
+    fn close(&mut self, input: &mut Input, bracket: u8) -> JsonEvent {
+        self.bump(input, bracket);
+        let (start, skip_start) = match self.stack.pop() {
+            Some(Frame::Array {
+                start, skip_start, ..
+            })
+            | Some(Frame::Object {
+                start, skip_start, ..
+            }) => (start, skip_start),
+            None => (self.position, self.chars),
+        };
+        self.span = Span {
+            start,
+            end: self.position,
+        };
+        self.skip = self.chars - skip_start;
+        self.state = self.after_value();
+        if bracket == b']' {
+            JsonEvent::EndArray
+        } else {
+            JsonEvent::EndObject
+        }
+    }
+
+    fn after_value(&self) -> State {
+        if self.stack.is_empty() {
+            State::Done
+        } else {
+            State::AfterValue
+        }
+    }
+
+    fn in_array(&self) -> bool {
+        matches!(self.stack.last(), Some(Frame::Array { .. }))
+    }
+
+    fn skip_whitespace(&mut self, input: &mut Input) -> Option<u8> {
+        while let Some(byte) = input.peek() {
+            if !matches!(byte, b' ' | b'\n' | b'\r' | b'\t') {
+                return Some(byte);
+            }
+            self.bump(input, byte);
+        }
+        None
+    }
+
+    /// Consume `byte`, the next one in `input`. Columns and `chars` count
+    /// characters, so UTF-8 continuation bytes do not advance them.
+    fn bump(&mut self, input: &mut Input, byte: u8) {
+        input.pos += 1;
+        self.position.offset += 1;
+        if byte == b'\n' {
+            self.position.line += 1;
+            self.position.column = 1;
+            self.chars += 1;
+        } else if byte & 0xC0 != 0x80 {
+            self.position.column += 1;
+            self.chars += 1;
+        }
+    }
+
+    fn error(&self, kind: ErrorKind, in_value: bool) -> ParseError {
+        self.error_at(kind, self.position, in_value)
+    }
+
+    /// Error inside the collected token, after the text `before`.
+    fn error_within(&self, kind: ErrorKind, before: &str, in_value: bool) -> ParseError {
+        let mut position = self.token_start;
+        position.offset += before.len();
+        match before.rfind('\n') {
+            Some(newline) => {
+                position.line += before.matches('\n').count();
+                position.column = before[newline + 1..].chars().count() + 1;
+            }
+            None => position.column += before.chars().count(),
+        }
+        self.error_at(kind, position, in_value)
+    }
+
+    /// The path leads to the innermost array element or object member only
+    /// when the error is inside its value, as with [`crate::parse`].
+    pub fn error_at(&self, kind: ErrorKind, position: Position, in_value: bool) -> ParseError {
+        let frames = if in_value {
+            &self.stack[..]
+        } else {
+            &self.stack[..self.stack.len().saturating_sub(1)]
+        };
+        ParseError {
+            kind,
+            offset: position.offset,
+            line: position.line,
+            column: position.column,
+            path: frames
+                .iter()
+                .filter_map(|frame| match frame {
+                    Frame::Array { index, .. } => Some(PathSegment::Index(*index)),
+                    Frame::Object { key, .. } => key.clone().map(PathSegment::Key),
+                })
+                .collect(),
+        }
+    }
+}
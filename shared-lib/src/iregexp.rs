@@ -0,0 +1,255 @@
+use regex::Regex;
+use std::iter::Peekable;
+use std::str::Chars;
+
+/// Unicode general categories that I-Regexp allows in `\p{..}` and `\P{..}`.
+const CATEGORIES: &[&str] = &[
+    "L", "Ll", "Lm", "Lo", "Lt", "Lu", "M", "Mc", "Me", "Mn", "N", "Nd", "Nl", "No", "P", "Pc",
+    "Pd", "Pe", "Pf", "Pi", "Po", "Ps", "Z", "Zl", "Zp", "Zs", "S", "Sc", "Sk", "Sm", "So", "C",
+    "Cc", "Cf", "Cn", "Co",
+];
+
+/// Compile an RFC 9485 I-Regexp, or `None` if `pattern` is not one.
+///
+/// With `anchored` the expression must match the whole string, as for the
+/// JSONPath `match()` function; otherwise any substring, as for `search()`.
+pub fn compile(pattern: &str, anchored: bool) -> Option<Regex> {
+    let mut translator = Translator {
+        chars: pattern.chars().peekable(),
+        out: String::new(),
+    };
+    translator.regexp()?;
+    if translator.chars.next().is_some() {
+        return None;
+    }
+    let translated = if anchored {
+        format!(r"\A(?:{})\z", translator.out)
+    } else {
+        translator.out
+    };
+    Regex::new(&translated).ok()
+}
+
+/// Rewrites I-Regexp syntax into the `regex` crate's, rejecting anything
+/// outside the I-Regexp grammar.
+struct Translator<'a> {
+    chars: Peekable<Chars<'a>>,
+    out: String,
+}
+
+impl Translator<'_> {
+    fn regexp(&mut self) -> Option<()> {
+        self.branch()?;
+        while self.chars.next_if_eq(&'|').is_some() {
+            self.out.push('|');
+            self.branch()?;
+        }
+        Some(())
+    }
+
+    fn branch(&mut self) -> Option<()> {
+        while let Some(&ch) = self.chars.peek() {
+            if ch == '|' || ch == ')' {
+                break;
+            }
+            self.atom()?;
+            self.quantifier()?;
+        }
+        Some(())
+    }
+
+    fn atom(&mut self) -> Option<()> {
+        match self.chars.next()? {
+            '(' => {
+                self.out.push_str("(?:");
+                self.regexp()?;
+                self.chars.next_if_eq(&')')?;
+                self.out.push(')');
+            }
+            // I-Regexp's dot matches anything but line breaks.
+            '.' => self.out.push_str(r"[^\n\r]"),
+            '[' => self.class()?,
+            '\\' => match self.escape()? {
+                Escape::Char(ch) => push_literal(&mut self.out, ch),
+                Escape::Category(category) => self.out.push_str(&category),
+            },
+            ')' | '*' | '+' | '?' | '{' | '}' | ']' | '|' => return None,
+            ch => push_literal(&mut self.out, ch),
+        }
+        Some(())
+    }
+
+    fn quantifier(&mut self) -> Option<()> {
+        match self.chars.peek() {
+            Some(&quantifier @ ('*' | '+' | '?')) => {
+                self.chars.next();
+                self.out.push(quantifier);
+            }
+            Some('{') => {
+                self.chars.next();
+                self.out.push('{');
+                self.digits()?;
+                if self.chars.next_if_eq(&',').is_some() {
+                    self.out.push(',');
+                    if self.chars.peek().is_some_and(char::is_ascii_digit) {
+                        self.digits()?;
+                    }
+                }
+                self.chars.next_if_eq(&'}')?;
+                self.out.push('}');
+            }
+            _ => {}
+        }
+        Some(())
+    }
+
+    fn digits(&mut self) -> Option<()> {
+        let start = self.out.len();
+        while let Some(digit) = self.chars.next_if(char::is_ascii_digit) {
+            self.out.push(digit);
+        }
+        (self.out.len() > start).then_some(())
+    }
+
+    /// A bracketed character class, after the `[`.
+    fn class(&mut self) -> Option<()> {
+        self.out.push('[');
+        if self.chars.next_if_eq(&'^').is_some() {
+            self.out.push('^');
+        }
+        let mut first = true;
+        loop {
+            match self.chars.next()? {
+                ']' if !first => break,
+                // A `-` is literal only at the start or the end.
+                '-' if first || self.chars.peek() == Some(&']') => push_literal(&mut self.out, '-'),
+                '-' | '[' | ']' => return None,
+                '\\' => match self.escape()? {
+                    Escape::Char(ch) => self.range(ch)?,
+                    Escape::Category(category) => self.out.push_str(&category),
+                },
+                ch => self.range(ch)?,
+            }
+            first = false;
+        }
+        self.out.push(']');
+        Some(())
+    }
+
+    /// A class character, or a range if a `-` and another character follow.
+    fn range(&mut self, start: char) -> Option<()> {
+        push_literal(&mut self.out, start);
+        let mut lookahead = self.chars.clone();
+        if lookahead.next() != Some('-') || matches!(lookahead.next(), Some(']') | None) {
+            return Some(());
+        }
+        self.chars.next();
+        let end = match self.chars.next()? {
+            '\\' => match self.escape()? {
+                Escape::Char(ch) => ch,
+                Escape::Category(_) => return None,
+            },
+            '-' | '[' | ']' => return None,
+            ch => ch,
+        };
+        if end < start {
+            return None;
+        }
+        self.out.push('-');
+        push_literal(&mut self.out, end);
+        Some(())
+    }
+
+    /// An escape, after the backslash.
+    fn escape(&mut self) -> Option<Escape> {
+        match self.chars.next()? {
+            'n' => Some(Escape::Char('\n')),
+            'r' => Some(Escape::Char('\r')),
+            't' => Some(Escape::Char('\t')),
+            ch @ ('(' | ')' | '*' | '+' | '-' | '.' | '?' | '[' | '\\' | ']' | '^' | '{' | '|'
+            | '}') => Some(Escape::Char(ch)),
+            negation @ ('p' | 'P') => {
+                self.chars.next_if_eq(&'{')?;
+                let mut name = String::new();
+                while let Some(ch) = self.chars.next_if(char::is_ascii_alphabetic) {
+                    name.push(ch);
+                }
+                self.chars.next_if_eq(&'}')?;
+                CATEGORIES
+                    .contains(&name.as_str())
+                    .then(|| Escape::Category(format!(r"\{}{{{}}}", negation, name)))
+            }
+            _ => None,
+        }
+    }
+}
+
+enum Escape {
+    Char(char),
+    /// A translated `\p{..}` or `\P{..}`.
+    Category(String),
+}
+
+fn push_literal(out: &mut String, ch: char) {
+    match ch {
+        '\n' => out.push_str(r"\n"),
+        '\r' => out.push_str(r"\r"),
+        '\t' => out.push_str(r"\t"),
+        ch if ch.is_ascii_punctuation() => {
+            out.push('\\');
+            out.push(ch);
+        }
+        ch => out.push(ch),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::compile;
+
+    fn matches(pattern: &str, text: &str) -> Option<bool> {
+        compile(pattern, true).map(|regex| regex.is_match(text))
+    }
+
+    #[test]
+    fn translates_i_regexp() {
+        assert_eq!(Some(true), matches("a.c", "abc"));
+        assert_eq!(Some(false), matches("a.c", "a\nc"));
+        assert_eq!(Some(false), matches("b", "abc"));
+        assert_eq!(Some(true), matches("a|b(c|d)*", "bcdc"));
+        assert_eq!(Some(true), matches("x{2,3}", "xxx"));
+        assert_eq!(Some(false), matches("x{2}", "xxx"));
+        assert_eq!(Some(true), matches("[a-c-]+", "ab-c"));
+        assert_eq!(Some(true), matches("[^\\p{Lu}]", "a"));
+        assert_eq!(Some(true), matches("\\p{Lu}\\P{Lu}", "Ab"));
+        assert_eq!(Some(true), matches("a$^b", "a$^b"));
+        assert_eq!(Some(true), matches("\\.\\[\\n", ".[\n"));
+        assert!(compile("b", false).is_some_and(|regex| regex.is_match("abc")));
+    }
+
+    #[test]
+    fn rejects_other_syntax() {
+        for pattern in [
+            "(",
+            "a)",
+            "*",
+            "a**",
+            "a*?",
+            "a{",
+            "a{,2}",
+            "[",
+            "[]",
+            "[a-]b-c]",
+            "[z-a]",
+            "\\d",
+            "\\w",
+            "\\p{Xx}",
+            "\\p{Cs}",
+            "(?:a)",
+            "\\1",
+            "[\\p{L}-z]",
+        ] {
+            assert!(compile(pattern, true).is_none(), "{}", pattern);
+        }
+    }
+}
@@ -1,91 +1,87 @@
-use crate::types::{Json, ValueToken};
+use crate::comment;
+use crate::cursor::{Cursor, TOP_LEVEL_DELIMITERS};
+use crate::error::{ErrorKind, ParseError};
+use crate::options::ParseOptions;
+use crate::types::{Json, Position, ValueToken};
 use crate::value::parse_value;
-use lazy_static::lazy_static;
-use regex::Regex;
 
 enum Mode {
     Scanning,
     Value,
+    End,
 }
 
-lazy_static! {
-    static ref WHITESPACE: Regex = Regex::new(r"[ \n\r\t]").unwrap();
+pub fn parse(json: &str) -> Result<Json, ParseError> {
+    parse_with_options(json, &ParseOptions::default())
 }
 
-pub fn parse(json: &str) -> Result<Json, &'static str> {
+pub fn parse_with_options(json: &str, options: &ParseOptions) -> Result<Json, ParseError> {
+    parse_at(json, Position::START, options)
+}
+
+/// Parse `json`, a slice of a larger text that begins at `origin`. Spans and
+/// errors are positions in the larger text; `skip` counts from the start of
+/// the slice.
+pub(crate) fn parse_at(
+    json: &str,
+    origin: Position,
+    options: &ParseOptions,
+) -> Result<Json, ParseError> {
+    let mut cursor = Cursor::with_origin(json, options, origin);
+    parse_document(&mut cursor).map_err(|e| e.locate(json).offset_by(origin))
+}
+
+fn parse_document(cursor: &mut Cursor) -> Result<Json, ParseError> {
     let mut mode = Mode::Scanning;
-    let mut pos: usize = 0;
-
-    loop {
-        match json.chars().nth(pos) {
-            Some(ch) => {
-                match mode {
-                    Mode::Scanning => {
-                        let char = &ch.to_string()[..];
-                        if WHITESPACE.is_match(char) {
-                            pos += 1;
-                        } else {
-                            mode = Mode::Value;
-                        }
-                    }
-                    Mode::Value => {
-                        let slice: String = json.chars().skip(pos).collect();
-                        match parse_value(&slice, r"[ \n\r\t]") {
-                            // None
-                            Ok(ValueToken::ArrayToken { skip, token }) => {
-                                return Ok(Json {
-                                    skip: pos + skip,
-                                    token: Box::new(ValueToken::ArrayToken { skip, token }),
-                                });
-                            }
-                            Ok(ValueToken::FalseToken { skip, token }) => {
-                                return Ok(Json {
-                                    skip: pos + skip,
-                                    token: Box::new(ValueToken::FalseToken { skip, token }),
-                                });
-                            }
-                            Ok(ValueToken::NullToken { skip }) => {
-                                return Ok(Json {
-                                    skip: pos + skip,
-                                    token: Box::new(ValueToken::NullToken { skip }),
-                                });
-                            }
-                            Ok(ValueToken::NumberToken { skip, token }) => {
-                                return Ok(Json {
-                                    skip: pos + skip,
-                                    token: Box::new(ValueToken::NumberToken { skip, token }),
-                                });
-                            }
-                            Ok(ValueToken::ObjectToken { skip, token }) => {
-                                return Ok(Json {
-                                    skip: pos + skip,
-                                    token: Box::new(ValueToken::ObjectToken { skip, token }),
-                                });
-                            }
-                            Ok(ValueToken::StringToken { skip, token }) => {
-                                return Ok(Json {
-                                    skip: pos + skip,
-                                    token: Box::new(ValueToken::StringToken { skip, token }),
-                                });
-                            }
-                            Ok(ValueToken::TrueToken { skip, token }) => {
-                                return Ok(Json {
-                                    skip: pos + skip,
-                                    token: Box::new(ValueToken::TrueToken { skip, token }),
-                                });
-                            }
-                            _ => {
-                                return Err("Unexpected token");
-                            }
-                        }
-                    }
+    let mut result: Option<Json> = None;
+
+    while let Some(ch) = cursor.peek() {
+        match mode {
+            Mode::Scanning => {
+                if cursor.is_trivia(ch) {
+                    cursor.skip_trivia(ch)?;
+                } else {
+                    mode = Mode::Value;
                 }
             }
-            None => {
-                return Err("Expected value");
+            Mode::Value => {
+                let pos = cursor.pos();
+                let token = match parse_value(cursor, TOP_LEVEL_DELIMITERS)? {
+                    ValueToken::PairToken { .. } => {
+                        return Err(ParseError::at(ErrorKind::UnexpectedToken, pos));
+                    }
+                    token => token,
+                };
+                result = Some(Json {
+                    skip: cursor.chars(),
+                    token: Box::new(token),
+                    comments: Vec::new(),
+                    raw_strings: Vec::new(),
+                });
+                mode = Mode::End;
+            }
+            Mode::End => {
+                if cursor.options().allow_trailing_content {
+                    break;
+                } else if cursor.is_trivia(ch) {
+                    cursor.skip_trivia(ch)?;
+                } else {
+                    return Err(ParseError::at(ErrorKind::TrailingCharacters, cursor.pos()));
+                }
             }
         }
     }
+
+    match result {
+        Some(mut result) => {
+            // Comments after the value are only known once input runs out.
+            result.comments = cursor.take_comments();
+            comment::attach(&mut result.comments, &result.token);
+            result.raw_strings = cursor.take_raw_strings();
+            Ok(result)
+        }
+        None => Err(ParseError::at(ErrorKind::Expected("value"), cursor.pos())),
+    }
 }
 
 #[cfg(test)]
@@ -93,15 +89,18 @@ mod tests {
     use crate::Json;
     use crate::ValueToken;
     use crate::parse;
+    use crate::{
+        Dialect, ErrorKind, ParseOptions, PathSegment, parse_with_options, to_json_string,
+    };
 
     #[test]
     fn false_test() {
         match parse("false") {
-            Ok(Json { skip, token }) => {
+            Ok(Json { skip, token, .. }) => {
                 assert_eq!(skip, 5);
 
                 match *token {
-                    ValueToken::FalseToken { skip, token } => {
+                    ValueToken::FalseToken { skip, token, .. } => {
                         assert_eq!(skip, 5);
                         assert!(!token);
                     }
@@ -119,11 +118,11 @@ mod tests {
     #[test]
     fn null_test() {
         match parse("null") {
-            Ok(Json { skip, token }) => {
+            Ok(Json { skip, token, .. }) => {
                 assert_eq!(skip, 4);
 
                 match *token {
-                    ValueToken::NullToken { skip } => {
+                    ValueToken::NullToken { skip, .. } => {
                         assert_eq!(skip, 4);
                     }
                     _ => {
@@ -140,11 +139,11 @@ mod tests {
     #[test]
     fn true_test() {
         match parse("true") {
-            Ok(Json { skip, token }) => {
+            Ok(Json { skip, token, .. }) => {
                 assert_eq!(skip, 4);
 
                 match *token {
-                    ValueToken::TrueToken { skip, token } => {
+                    ValueToken::TrueToken { skip, token, .. } => {
                         assert_eq!(skip, 4);
                         assert!(token);
                     }
@@ -158,4 +157,176 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn error_location_test() {
+        match parse("{\n  \"a\": [1, x]\n}") {
+            Ok(_) => panic!("Expected error"),
+            Err(e) => {
+                assert_eq!(ErrorKind::UnexpectedCharacter, e.kind);
+                assert_eq!(13, e.offset);
+                assert_eq!(2, e.line);
+                assert_eq!(12, e.column);
+                assert_eq!(
+                    vec![PathSegment::Key("a".to_string()), PathSegment::Index(1)],
+                    e.path
+                );
+                assert_eq!(
+                    "Unexpected character at line 2, column 12 (in $[\"a\"][1])",
+                    e.to_string()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn error_byte_offset_test() {
+        match parse("[\"\u{e9}\", x]") {
+            Ok(_) => panic!("Expected error"),
+            Err(e) => {
+                assert_eq!(7, e.offset);
+                assert_eq!(1, e.line);
+                assert_eq!(7, e.column);
+            }
+        }
+    }
+
+    #[test]
+    fn error_empty_input_test() {
+        match parse("  ") {
+            Ok(_) => panic!("Expected error"),
+            Err(e) => {
+                assert_eq!(ErrorKind::Expected("value"), e.kind);
+                assert_eq!(2, e.offset);
+                assert_eq!(3, e.column);
+                assert!(e.path.is_empty());
+            }
+        }
+    }
+
+    #[test]
+    fn trailing_content_test() {
+        for input in [r#"{"a":1} xyz"#, "[1][2]", "null null", "true,"] {
+            match parse(input) {
+                Ok(_) => panic!("Expected error for {:?}", input),
+                Err(e) => assert_eq!(ErrorKind::TrailingCharacters, e.kind),
+            }
+        }
+    }
+
+    #[test]
+    fn trailing_content_position_test() {
+        match parse("[1] \n x") {
+            Ok(_) => panic!("Expected error"),
+            Err(e) => {
+                assert_eq!(6, e.offset);
+                assert_eq!(2, e.line);
+                assert_eq!(2, e.column);
+            }
+        }
+    }
+
+    #[test]
+    fn allow_trailing_content_test() {
+        let options = ParseOptions {
+            allow_trailing_content: true,
+            ..ParseOptions::default()
+        };
+        match parse_with_options(" [1][2]", &options) {
+            Ok(Json { skip, token, .. }) => {
+                assert_eq!(4, skip);
+                assert!(matches!(*token, ValueToken::ArrayToken { skip: 3, .. }));
+            }
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    fn json5() -> ParseOptions {
+        ParseOptions {
+            dialect: Dialect::Json5,
+            ..ParseOptions::default()
+        }
+    }
+
+    #[test]
+    fn json5_document_test() {
+        // The example from https://json5.org.
+        let input = "{
+  // comments
+  unquoted: 'and you can quote me on that',
+  singleQuotes: 'I can use \"double quotes\" here',
+  lineBreaks: \"Look, Mom! \\
+No \\\\n's!\",
+  hexadecimal: 0xdecaf,
+  leadingDecimalPoint: .8675309, andTrailing: 8675309.,
+  positiveSign: +1,
+  trailingComma: 'in objects', andIn: ['arrays',],
+  \"backwardsCompatible\": \"with JSON\",
+}
+";
+        match parse_with_options(input, &json5()) {
+            Ok(json) => assert_eq!(
+                r#"{"unquoted":"and you can quote me on that","singleQuotes":"I can use \"double quotes\" here","lineBreaks":"Look, Mom! No \\n's!","hexadecimal":912559,"leadingDecimalPoint":0.8675309,"andTrailing":8675309,"positiveSign":1,"trailingComma":"in objects","andIn":["arrays"],"backwardsCompatible":"with JSON"}"#,
+                to_json_string(&json.token)
+            ),
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    #[test]
+    fn json5_comments_test() {
+        let input = "/* head */ [1 /* a */, // b\n 2 // tail";
+        match parse_with_options(&format!("{}\n]", input), &json5()) {
+            Ok(json) => assert_eq!("[1,2]", to_json_string(&json.token)),
+            Err(e) => panic!("{}", e),
+        }
+        for (input, kind, offset) in [
+            ("[1] /* open", ErrorKind::UnterminatedComment, 4),
+            ("[1 / 2]", ErrorKind::UnexpectedCharacter, 3),
+        ] {
+            match parse_with_options(input, &json5()) {
+                Ok(_) => panic!("Expected error for {:?}", input),
+                Err(e) => assert_eq!((kind, offset), (e.kind, e.offset), "{:?}", input),
+            }
+        }
+    }
+
+    #[test]
+    fn json5_extensions_rejected_by_default_test() {
+        for input in [
+            "// c\n1",
+            "[1,]",
+            "{\"a\":1,}",
+            "'a'",
+            "{a:1}",
+            "0x1",
+            "+1",
+            ".5",
+            "Infinity",
+            "\u{a0}1",
+        ] {
+            assert!(parse(input).is_err(), "{:?} should not parse", input);
+            assert!(parse_with_options(input, &json5()).is_ok(), "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn jsonc_test() {
+        let options = ParseOptions {
+            dialect: Dialect::Jsonc,
+            ..ParseOptions::default()
+        };
+        let input = "{\n  // editor\n  \"a\": [1, /* two */ 2,],\n}";
+        match parse_with_options(input, &options) {
+            Ok(json) => assert_eq!(r#"{"a":[1,2]}"#, to_json_string(&json.token)),
+            Err(e) => panic!("{}", e),
+        }
+        for input in ["{a:1}", "'a'", "0x1", "\u{a0}1", "[1] /*"] {
+            assert!(
+                parse_with_options(input, &options).is_err(),
+                "{:?} should not parse",
+                input
+            );
+        }
+    }
 }
@@ -0,0 +1,317 @@
+use crate::ordered_map::{Member, OrderedMap};
+use crate::types::{Json, Number, ValueToken};
+use std::ops::Index;
+
+/// Owned JSON document without parse bookkeeping.
+///
+/// Object members keep their source order, including duplicate keys, so
+/// converting from a token tree is lossless.
+#[derive(Debug, Clone, PartialEq)]
+pub enum JsonValue {
+    Null,
+    Bool(bool),
+    Number(Number),
+    String(String),
+    Array(Vec<JsonValue>),
+    Object(JsonObject),
+}
+
+/// Members of a [`JsonValue::Object`] in source order, with a key index
+/// for O(1) lookup like [`crate::Object`].
+pub type JsonObject = OrderedMap<(String, JsonValue)>;
+
+impl Member for (String, JsonValue) {
+    type Value = JsonValue;
+    type Owned = JsonValue;
+
+    fn new(key: String, value: JsonValue) -> Self {
+        (key, value)
+    }
+
+    fn key(&self) -> &str {
+        &self.0
+    }
+
+    fn value(&self) -> &JsonValue {
+        &self.1
+    }
+
+    fn value_mut(&mut self) -> &mut JsonValue {
+        &mut self.1
+    }
+
+    fn replace(&mut self, value: JsonValue) -> JsonValue {
+        std::mem::replace(&mut self.1, value)
+    }
+
+    fn into_value(self) -> JsonValue {
+        self.1
+    }
+}
+
+static NULL: JsonValue = JsonValue::Null;
+
+impl JsonValue {
+    pub fn is_null(&self) -> bool {
+        matches!(self, JsonValue::Null)
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            JsonValue::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    pub fn as_number(&self) -> Option<&Number> {
+        match self {
+            JsonValue::Number(number) => Some(number),
+            _ => None,
+        }
+    }
+
+    /// See [`Number::as_i64`].
+    pub fn as_i64(&self) -> Option<i64> {
+        self.as_number().and_then(Number::as_i64)
+    }
+
+    /// See [`Number::as_u64`].
+    pub fn as_u64(&self) -> Option<u64> {
+        self.as_number().and_then(Number::as_u64)
+    }
+
+    /// See [`Number::as_f64`]; `None` when the number overflows `f64`.
+    pub fn as_f64(&self) -> Option<f64> {
+        self.as_number().and_then(Number::as_f64)
+    }
+
+    /// See [`Number::as_decimal`].
+    pub fn as_decimal(&self) -> Option<String> {
+        self.as_number().and_then(Number::as_decimal)
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            JsonValue::String(string) => Some(string),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&Vec<JsonValue>> {
+        match self {
+            JsonValue::Array(values) => Some(values),
+            _ => None,
+        }
+    }
+
+    pub fn as_object(&self) -> Option<&JsonObject> {
+        match self {
+            JsonValue::Object(members) => Some(members),
+            _ => None,
+        }
+    }
+
+    /// Look up an array element by position or an object member by key.
+    /// With duplicate keys the last member wins, as in most JSON readers.
+    pub fn get<I: JsonIndex>(&self, index: I) -> Option<&JsonValue> {
+        index.index_into(self)
+    }
+
+    pub fn get_mut<I: JsonIndex>(&mut self, index: I) -> Option<&mut JsonValue> {
+        index.index_into_mut(self)
+    }
+}
+
+/// Types that can index into a [`JsonValue`]: `usize` for arrays and string
+/// keys for objects.
+pub trait JsonIndex {
+    fn index_into<'a>(&self, value: &'a JsonValue) -> Option<&'a JsonValue>;
+    fn index_into_mut<'a>(&self, value: &'a mut JsonValue) -> Option<&'a mut JsonValue>;
+}
+
+impl JsonIndex for usize {
+    fn index_into<'a>(&self, value: &'a JsonValue) -> Option<&'a JsonValue> {
+        match value {
+            JsonValue::Array(values) => values.get(*self),
+            _ => None,
+        }
+    }
+
+    fn index_into_mut<'a>(&self, value: &'a mut JsonValue) -> Option<&'a mut JsonValue> {
+        match value {
+            JsonValue::Array(values) => values.get_mut(*self),
+            _ => None,
+        }
+    }
+}
+
+impl JsonIndex for str {
+    fn index_into<'a>(&self, value: &'a JsonValue) -> Option<&'a JsonValue> {
+        match value {
+            JsonValue::Object(members) => members.get(self),
+            _ => None,
+        }
+    }
+
+    fn index_into_mut<'a>(&self, value: &'a mut JsonValue) -> Option<&'a mut JsonValue> {
+        match value {
+            JsonValue::Object(members) => members.get_mut(self),
+            _ => None,
+        }
+    }
+}
+
+impl JsonIndex for String {
+    fn index_into<'a>(&self, value: &'a JsonValue) -> Option<&'a JsonValue> {
+        self.as_str().index_into(value)
+    }
+
+    fn index_into_mut<'a>(&self, value: &'a mut JsonValue) -> Option<&'a mut JsonValue> {
+        self.as_str().index_into_mut(value)
+    }
+}
+
+impl<T: JsonIndex + ?Sized> JsonIndex for &T {
+    fn index_into<'a>(&self, value: &'a JsonValue) -> Option<&'a JsonValue> {
+        (**self).index_into(value)
+    }
+
+    fn index_into_mut<'a>(&self, value: &'a mut JsonValue) -> Option<&'a mut JsonValue> {
+        (**self).index_into_mut(value)
+    }
+}
+
+/// Missing elements and members index to `Null` rather than panicking.
+impl<I: JsonIndex> Index<I> for JsonValue {
+    type Output = JsonValue;
+
+    fn index(&self, index: I) -> &JsonValue {
+        index.index_into(self).unwrap_or(&NULL)
+    }
+}
+
+impl From<ValueToken> for JsonValue {
+    /// A bare `PairToken` becomes a single-member object.
+    fn from(token: ValueToken) -> Self {
+        match token {
+            ValueToken::ArrayToken { token, .. } => JsonValue::Array(
+                token
+                    .values
+                    .into_iter()
+                    .map(|value| JsonValue::from(*value))
+                    .collect(),
+            ),
+            ValueToken::ObjectToken { token, .. } => JsonValue::Object(
+                token
+                    .into_iter()
+                    .map(|pair| (pair.key, JsonValue::from(*pair.value)))
+                    .collect(),
+            ),
+            ValueToken::PairToken { token, .. } => JsonValue::Object(JsonObject::from(vec![(
+                token.key,
+                JsonValue::from(*token.value),
+            )])),
+            ValueToken::StringToken { token, .. } => JsonValue::String(token),
+            ValueToken::NumberToken { token, .. } => JsonValue::Number(token),
+            ValueToken::TrueToken { .. } => JsonValue::Bool(true),
+            ValueToken::FalseToken { .. } => JsonValue::Bool(false),
+            ValueToken::NullToken { .. } => JsonValue::Null,
+        }
+    }
+}
+
+impl From<Json> for JsonValue {
+    fn from(json: Json) -> Self {
+        JsonValue::from(*json.token)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{JsonObject, JsonValue};
+    use crate::json::parse;
+
+    fn value(input: &str) -> JsonValue {
+        match parse(input) {
+            Ok(json) => JsonValue::from(json),
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    #[test]
+    fn converts_every_type() {
+        let v = value(r#"{"s":"x","n":1.5,"t":true,"f":false,"l":null,"a":[1],"o":{}}"#);
+        assert_eq!(Some("x"), v["s"].as_str());
+        assert_eq!(Some(1.5), v["n"].as_f64());
+        assert_eq!("1.5", v["n"].as_number().unwrap().value_as_string);
+        assert_eq!(Some(true), v["t"].as_bool());
+        assert_eq!(Some(false), v["f"].as_bool());
+        assert!(v["l"].is_null());
+        assert_eq!(1, v["a"].as_array().unwrap().len());
+        assert!(v["o"].as_object().unwrap().is_empty());
+    }
+
+    #[test]
+    fn get_by_key_and_index() {
+        let v = value(r#"{"items":[{"name":"a"},{"name":"b"}]}"#);
+        let key = String::from("items");
+        assert_eq!(Some("b"), v["items"][1]["name"].as_str());
+        assert_eq!(
+            Some("a"),
+            v.get(&key)
+                .and_then(|items| items.get(0))
+                .and_then(|item| item.get("name"))
+                .and_then(JsonValue::as_str)
+        );
+        assert!(v.get("missing").is_none());
+        assert!(v["items"].get(2).is_none());
+        assert!(v["items"]["name"].is_null());
+        assert!(v[0].is_null());
+    }
+
+    #[test]
+    fn number_accessors() {
+        let v = value("[-1, 18446744073709551615, 2.50, 1e400, true]");
+        assert_eq!((Some(-1), None), (v[0].as_i64(), v[0].as_u64()));
+        assert_eq!((None, Some(u64::MAX)), (v[1].as_i64(), v[1].as_u64()));
+        assert_eq!(Some(2.5), v[2].as_f64());
+        assert_eq!(Some("2.5".to_string()), v[2].as_decimal());
+        assert_eq!(None, v[3].as_f64());
+        assert_eq!(Some("1e400".to_string()), v[3].as_decimal());
+        assert_eq!(
+            (None, None, None),
+            (v[4].as_i64(), v[4].as_f64(), v[4].as_decimal())
+        );
+    }
+
+    #[test]
+    fn duplicate_keys_are_kept() {
+        let v = value(r#"{"a":1,"a":2}"#);
+        assert_eq!(2, v.as_object().unwrap().len());
+        assert_eq!(Some(2.0), v["a"].as_f64());
+    }
+
+    #[test]
+    fn object_insert_and_remove() {
+        let mut object = JsonObject::new();
+        object.push(("a".to_string(), JsonValue::Bool(true)));
+        object.push(("b".to_string(), JsonValue::Null));
+        assert_eq!(
+            Some(JsonValue::Null),
+            object.insert("b".to_string(), JsonValue::Bool(false))
+        );
+        assert_eq!(Some(JsonValue::Bool(true)), object.remove("a"));
+        assert_eq!(None, object.remove("a"));
+        assert_eq!(Some(&JsonValue::Bool(false)), object.get("b"));
+        assert_eq!(vec!["b"], object.iter().map(|(k, _)| k).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn get_mut_updates_value() {
+        let mut v = value(r#"{"a":[1,2]}"#);
+        if let Some(element) = v.get_mut("a").and_then(|a| a.get_mut(0)) {
+            *element = JsonValue::String("x".to_string());
+        }
+        assert_eq!(Some("x"), v["a"][0].as_str());
+    }
+}
@@ -0,0 +1,1259 @@
+use crate::error::JsonPathError;
+use crate::iregexp;
+use crate::number;
+use crate::types::{Number, Object, ValueToken};
+use regex::Regex;
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+
+/// Largest magnitude of an index or slice bound, 2^53 - 1 as in I-JSON.
+const MAX_INT: i64 = (1 << 53) - 1;
+
+/// A compiled RFC 9535 JSONPath query such as `$.store.book[?@.price < 10].title`.
+///
+/// Supports child and descendant segments, name, wildcard, index, slice and
+/// filter selectors, and the standard functions `length`, `count`, `match`,
+/// `search` and `value`. Queries that are not well-formed or not well-typed
+/// are rejected by [`JsonPath::parse`].
+///
+/// ```
+/// use shared_lib::{JsonPath, ValueToken, parse};
+///
+/// let json = parse(r#"{"book": [{"title": "A", "price": 8}, {"title": "B", "price": 12}]}"#)
+///     .unwrap();
+/// let path = JsonPath::parse("$.book[?@.price < 10].title").unwrap();
+/// match path.query(&json.token)[..] {
+///     [ValueToken::StringToken { token, .. }] => assert_eq!("A", token),
+///     ref other => panic!("unexpected {:?}", other),
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct JsonPath {
+    source: String,
+    query: Query,
+}
+
+impl JsonPath {
+    pub fn parse(query: &str) -> Result<Self, JsonPathError> {
+        let mut parser = Parser {
+            input: query,
+            pos: 0,
+        };
+        let parsed = parser.query()?;
+        if !parsed.absolute {
+            return Err(JsonPathError::at("Expected '$'", 0));
+        }
+        if parser.pos < query.len() {
+            return Err(parser.error("Unexpected character"));
+        }
+        Ok(JsonPath {
+            source: query.to_string(),
+            query: parsed,
+        })
+    }
+
+    /// The nodes the query selects from `root`, in document order for each
+    /// segment.
+    pub fn query<'a>(&self, root: &'a ValueToken) -> Vec<&'a ValueToken> {
+        self.query.select(root, root)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+}
+
+impl FromStr for JsonPath {
+    type Err = JsonPathError;
+
+    fn from_str(query: &str) -> Result<Self, Self::Err> {
+        JsonPath::parse(query)
+    }
+}
+
+impl fmt::Display for JsonPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+/// `$` or `@` followed by segments.
+#[derive(Debug, Clone)]
+struct Query {
+    absolute: bool,
+    segments: Vec<Segment>,
+}
+
+#[derive(Debug, Clone)]
+struct Segment {
+    descendant: bool,
+    selectors: Vec<Selector>,
+}
+
+#[derive(Debug, Clone)]
+enum Selector {
+    Name(String),
+    Wildcard,
+    Index(i64),
+    Slice {
+        start: Option<i64>,
+        end: Option<i64>,
+        step: Option<i64>,
+    },
+    Filter(Expr),
+}
+
+/// A logical expression in a filter selector.
+#[derive(Debug, Clone)]
+enum Expr {
+    Or(Vec<Expr>),
+    And(Vec<Expr>),
+    Not(Box<Expr>),
+    Compare(Comparable, Op, Comparable),
+    /// A query used as a test: true if it selects anything.
+    Exists(Query),
+    /// A function returning a logical value used as a test.
+    Test(Call),
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug, Clone)]
+enum Comparable {
+    Literal(Literal),
+    /// Always a singular query.
+    Query(Query),
+    /// Always a function returning a value.
+    Call(Call),
+}
+
+#[derive(Debug, Clone)]
+enum Literal {
+    Null,
+    Bool(bool),
+    Number(Number),
+    String(String),
+}
+
+#[derive(Debug, Clone)]
+struct Call {
+    function: Function,
+    args: Vec<Argument>,
+    /// The compiled pattern of `match` or `search` when it is a literal;
+    /// `None` inside if the literal is not a valid I-Regexp.
+    regex: Option<Option<Regex>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Function {
+    Length,
+    Count,
+    Match,
+    Search,
+    Value,
+}
+
+/// The three types of RFC 9535 function arguments and results.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Type {
+    Value,
+    Logical,
+    Nodes,
+}
+
+impl Function {
+    fn parameters(self) -> &'static [Type] {
+        match self {
+            Function::Length => &[Type::Value],
+            Function::Count | Function::Value => &[Type::Nodes],
+            Function::Match | Function::Search => &[Type::Value, Type::Value],
+        }
+    }
+
+    fn result(self) -> Type {
+        match self {
+            Function::Length | Function::Count | Function::Value => Type::Value,
+            Function::Match | Function::Search => Type::Logical,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Argument {
+    Literal(Literal),
+    Query(Query),
+    /// A logical expression. None of the standard functions takes one, so
+    /// it is parsed only to be rejected as an argument of the wrong type.
+    Logical,
+    Call(Call),
+}
+
+impl Argument {
+    /// Whether the argument can be passed where `expected` is declared.
+    fn fits(&self, expected: Type) -> bool {
+        match (self, expected) {
+            (Argument::Literal(_), Type::Value) => true,
+            (Argument::Query(query), Type::Value) => query.is_singular(),
+            (Argument::Query(_), Type::Logical | Type::Nodes) => true,
+            (Argument::Logical, Type::Logical) => true,
+            (Argument::Call(call), expected) => {
+                let result = call.function.result();
+                result == expected || (result == Type::Nodes && expected == Type::Logical)
+            }
+            _ => false,
+        }
+    }
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<char> {
+        self.input[self.pos..].chars().next()
+    }
+
+    fn eat(&mut self, text: &str) -> bool {
+        if self.input[self.pos..].starts_with(text) {
+            self.pos += text.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, text: &str, message: &'static str) -> Result<(), JsonPathError> {
+        if self.eat(text) {
+            Ok(())
+        } else {
+            Err(self.error(message))
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(ch @ (' ' | '\t' | '\n' | '\r')) = self.peek() {
+            self.pos += ch.len_utf8();
+        }
+    }
+
+    fn error(&self, message: &'static str) -> JsonPathError {
+        JsonPathError::at(message, self.pos)
+    }
+
+    /// `$` or `@` and the segments after it.
+    fn query(&mut self) -> Result<Query, JsonPathError> {
+        let absolute = match self.peek() {
+            Some('$') => true,
+            Some('@') => false,
+            _ => return Err(self.error("Expected '$'")),
+        };
+        self.pos += 1;
+        let mut segments = Vec::new();
+        loop {
+            let before = self.pos;
+            self.skip_whitespace();
+            match self.peek() {
+                Some('[' | '.') => segments.push(self.segment()?),
+                // Whitespace that does not lead to a segment is not ours.
+                _ => {
+                    self.pos = before;
+                    break;
+                }
+            }
+        }
+        Ok(Query { absolute, segments })
+    }
+
+    fn segment(&mut self) -> Result<Segment, JsonPathError> {
+        let descendant = self.eat("..");
+        let selectors = if self.peek() == Some('[') {
+            self.bracketed()?
+        } else if !descendant && !self.eat(".") {
+            return Err(self.error("Expected '.' or '['"));
+        } else if self.eat("*") {
+            vec![Selector::Wildcard]
+        } else {
+            vec![Selector::Name(self.member_name()?)]
+        };
+        Ok(Segment {
+            descendant,
+            selectors,
+        })
+    }
+
+    /// A member name written after `.` or `..`.
+    fn member_name(&mut self) -> Result<String, JsonPathError> {
+        let start = self.pos;
+        while let Some(ch) = self.peek() {
+            let allowed = ch.is_ascii_alphabetic()
+                || ch == '_'
+                || ch as u32 >= 0x80
+                || (self.pos > start && ch.is_ascii_digit());
+            if !allowed {
+                break;
+            }
+            self.pos += ch.len_utf8();
+        }
+        if self.pos == start {
+            return Err(self.error("Expected member name"));
+        }
+        Ok(self.input[start..self.pos].to_string())
+    }
+
+    fn bracketed(&mut self) -> Result<Vec<Selector>, JsonPathError> {
+        self.expect("[", "Expected '['")?;
+        let mut selectors = Vec::new();
+        loop {
+            self.skip_whitespace();
+            selectors.push(self.selector()?);
+            self.skip_whitespace();
+            if self.eat("]") {
+                return Ok(selectors);
+            }
+            self.expect(",", "Expected ',' or ']'")?;
+        }
+    }
+
+    fn selector(&mut self) -> Result<Selector, JsonPathError> {
+        match self.peek() {
+            Some('\'' | '"') => Ok(Selector::Name(self.string()?)),
+            Some('*') => {
+                self.pos += 1;
+                Ok(Selector::Wildcard)
+            }
+            Some('?') => {
+                self.pos += 1;
+                self.skip_whitespace();
+                Ok(Selector::Filter(self.or()?))
+            }
+            _ => {
+                let start = self.optional_int()?;
+                self.skip_whitespace();
+                if !self.eat(":") {
+                    return match start {
+                        Some(index) => Ok(Selector::Index(index)),
+                        None => Err(self.error("Expected selector")),
+                    };
+                }
+                self.skip_whitespace();
+                let end = self.optional_int()?;
+                self.skip_whitespace();
+                let step = if self.eat(":") {
+                    self.skip_whitespace();
+                    self.optional_int()?
+                } else {
+                    None
+                };
+                Ok(Selector::Slice { start, end, step })
+            }
+        }
+    }
+
+    fn optional_int(&mut self) -> Result<Option<i64>, JsonPathError> {
+        match self.peek() {
+            Some('-' | '0'..='9') => self.int().map(Some),
+            _ => Ok(None),
+        }
+    }
+
+    /// An integer without leading zeros or `-0`, within the I-JSON range.
+    fn int(&mut self) -> Result<i64, JsonPathError> {
+        let start = self.pos;
+        let negative = self.eat("-");
+        let digits = self.pos;
+        while let Some('0'..='9') = self.peek() {
+            self.pos += 1;
+        }
+        let text = &self.input[digits..self.pos];
+        if text.is_empty() {
+            return Err(self.error("Expected digit"));
+        }
+        if text.starts_with('0') && (text.len() > 1 || negative) {
+            return Err(JsonPathError::at("Invalid integer", start));
+        }
+        match self.input[start..self.pos].parse::<i64>() {
+            Ok(value) if value.abs() <= MAX_INT => Ok(value),
+            _ => Err(JsonPathError::at("Integer out of range", start)),
+        }
+    }
+
+    /// A single- or double-quoted string literal.
+    fn string(&mut self) -> Result<String, JsonPathError> {
+        let quote = self.peek().ok_or_else(|| self.error("Expected string"))?;
+        self.pos += 1;
+        let mut value = String::new();
+        loop {
+            let ch = self
+                .peek()
+                .ok_or_else(|| self.error("Unterminated string"))?;
+            if ch == quote {
+                self.pos += 1;
+                return Ok(value);
+            }
+            if ch < ' ' {
+                return Err(self.error("Unescaped control character in string"));
+            }
+            if ch != '\\' {
+                value.push(ch);
+                self.pos += ch.len_utf8();
+                continue;
+            }
+            self.pos += 1;
+            let escaped = match self.peek() {
+                Some('b') => '\u{8}',
+                Some('f') => '\u{c}',
+                Some('n') => '\n',
+                Some('r') => '\r',
+                Some('t') => '\t',
+                Some('/') => '/',
+                Some('\\') => '\\',
+                Some(ch) if ch == quote => ch,
+                Some('u') => {
+                    self.pos += 1;
+                    value.push(self.unicode_escape()?);
+                    continue;
+                }
+                _ => return Err(self.error("Invalid escape")),
+            };
+            self.pos += 1;
+            value.push(escaped);
+        }
+    }
+
+    /// The hex digits of a `\u` escape, and a second escape if the first is
+    /// a high surrogate.
+    fn unicode_escape(&mut self) -> Result<char, JsonPathError> {
+        let start = self.pos;
+        let high = self.hex4()?;
+        let code = match high {
+            0xD800..=0xDBFF => {
+                if !self.eat("\\u") {
+                    return Err(JsonPathError::at("Unpaired surrogate", start));
+                }
+                let low = self.hex4()?;
+                if !(0xDC00..=0xDFFF).contains(&low) {
+                    return Err(JsonPathError::at("Unpaired surrogate", start));
+                }
+                0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00)
+            }
+            0xDC00..=0xDFFF => return Err(JsonPathError::at("Unpaired surrogate", start)),
+            code => code,
+        };
+        char::from_u32(code).ok_or_else(|| JsonPathError::at("Invalid escape", start))
+    }
+
+    fn hex4(&mut self) -> Result<u32, JsonPathError> {
+        let digits = self.input[self.pos..]
+            .get(..4)
+            .filter(|digits| digits.bytes().all(|byte| byte.is_ascii_hexdigit()))
+            .ok_or_else(|| self.error("Expected four hex digits"))?;
+        self.pos += 4;
+        u32::from_str_radix(digits, 16).map_err(|_| self.error("Expected four hex digits"))
+    }
+
+    fn or(&mut self) -> Result<Expr, JsonPathError> {
+        let mut operands = vec![self.and()?];
+        loop {
+            let before = self.pos;
+            self.skip_whitespace();
+            if !self.eat("||") {
+                self.pos = before;
+                break;
+            }
+            self.skip_whitespace();
+            operands.push(self.and()?);
+        }
+        Ok(if operands.len() == 1 {
+            operands.remove(0)
+        } else {
+            Expr::Or(operands)
+        })
+    }
+
+    fn and(&mut self) -> Result<Expr, JsonPathError> {
+        let mut operands = vec![self.basic()?];
+        loop {
+            let before = self.pos;
+            self.skip_whitespace();
+            if !self.eat("&&") {
+                self.pos = before;
+                break;
+            }
+            self.skip_whitespace();
+            operands.push(self.basic()?);
+        }
+        Ok(if operands.len() == 1 {
+            operands.remove(0)
+        } else {
+            Expr::And(operands)
+        })
+    }
+
+    /// A parenthesized expression, a comparison or a test, any but a
+    /// comparison optionally negated.
+    fn basic(&mut self) -> Result<Expr, JsonPathError> {
+        if self.eat("!") {
+            self.skip_whitespace();
+            let negated = if self.peek() == Some('(') {
+                self.parenthesized()?
+            } else {
+                let start = self.pos;
+                self.test(start)?
+            };
+            return Ok(Expr::Not(Box::new(negated)));
+        }
+        if self.peek() == Some('(') {
+            return self.parenthesized();
+        }
+        let start = self.pos;
+        let left = self.operand()?;
+        let before = self.pos;
+        self.skip_whitespace();
+        let Some(op) = self.comparison_op() else {
+            self.pos = before;
+            return test_expr(left, start);
+        };
+        self.skip_whitespace();
+        let right_start = self.pos;
+        let right = self.operand()?;
+        Ok(Expr::Compare(
+            comparable(left, start)?,
+            op,
+            comparable(right, right_start)?,
+        ))
+    }
+
+    fn parenthesized(&mut self) -> Result<Expr, JsonPathError> {
+        self.expect("(", "Expected '('")?;
+        self.skip_whitespace();
+        let expr = self.or()?;
+        self.skip_whitespace();
+        self.expect(")", "Expected ')'")?;
+        Ok(expr)
+    }
+
+    fn test(&mut self, start: usize) -> Result<Expr, JsonPathError> {
+        let operand = self.operand()?;
+        test_expr(operand, start)
+    }
+
+    fn comparison_op(&mut self) -> Option<Op> {
+        for (text, op) in [
+            ("==", Op::Eq),
+            ("!=", Op::Ne),
+            ("<=", Op::Le),
+            (">=", Op::Ge),
+            ("<", Op::Lt),
+            (">", Op::Gt),
+        ] {
+            if self.eat(text) {
+                return Some(op);
+            }
+        }
+        None
+    }
+
+    /// A literal, query or function call.
+    fn operand(&mut self) -> Result<Argument, JsonPathError> {
+        match self.peek() {
+            Some('$' | '@') => Ok(Argument::Query(self.query()?)),
+            Some('\'' | '"') => Ok(Argument::Literal(Literal::String(self.string()?))),
+            Some('-' | '0'..='9') => Ok(Argument::Literal(Literal::Number(self.number()?))),
+            Some('a'..='z') => {
+                if self.eat("true") {
+                    Ok(Argument::Literal(Literal::Bool(true)))
+                } else if self.eat("false") {
+                    Ok(Argument::Literal(Literal::Bool(false)))
+                } else if self.eat("null") {
+                    Ok(Argument::Literal(Literal::Null))
+                } else {
+                    Ok(Argument::Call(self.call()?))
+                }
+            }
+            _ => Err(self.error("Expected expression")),
+        }
+    }
+
+    /// A number literal: an integer (or `-0`) with optional fraction and
+    /// exponent.
+    fn number(&mut self) -> Result<Number, JsonPathError> {
+        let start = self.pos;
+        self.eat("-");
+        let digits = self.pos;
+        while let Some('0'..='9') = self.peek() {
+            self.pos += 1;
+        }
+        let int = &self.input[digits..self.pos];
+        if int.is_empty() || (int.starts_with('0') && int.len() > 1) {
+            return Err(JsonPathError::at("Invalid number", start));
+        }
+        if self.eat(".") && !self.digits() {
+            return Err(self.error("Expected digit after '.'"));
+        }
+        if self.eat("e") || self.eat("E") {
+            if !self.eat("+") {
+                self.eat("-");
+            }
+            if !self.digits() {
+                return Err(self.error("Expected digit in exponent"));
+            }
+        }
+        let text = &self.input[start..self.pos];
+        match text.parse() {
+            Ok(value) => Ok(Number {
+                value,
+                value_as_string: text.to_string(),
+            }),
+            Err(_) => Err(JsonPathError::at("Invalid number", start)),
+        }
+    }
+
+    fn digits(&mut self) -> bool {
+        let start = self.pos;
+        while let Some('0'..='9') = self.peek() {
+            self.pos += 1;
+        }
+        self.pos > start
+    }
+
+    fn call(&mut self) -> Result<Call, JsonPathError> {
+        let start = self.pos;
+        while let Some('a'..='z' | '_' | '0'..='9') = self.peek() {
+            self.pos += 1;
+        }
+        let function = match &self.input[start..self.pos] {
+            "length" => Function::Length,
+            "count" => Function::Count,
+            "match" => Function::Match,
+            "search" => Function::Search,
+            "value" => Function::Value,
+            _ => return Err(JsonPathError::at("Unknown function", start)),
+        };
+        self.expect("(", "Expected '('")?;
+        let mut args = Vec::new();
+        self.skip_whitespace();
+        if !self.eat(")") {
+            loop {
+                let arg_start = self.pos;
+                let arg = self.argument()?;
+                let index = args.len();
+                match function.parameters().get(index) {
+                    Some(&expected) if arg.fits(expected) => args.push(arg),
+                    Some(_) => return Err(JsonPathError::at("Argument of wrong type", arg_start)),
+                    None => return Err(JsonPathError::at("Too many arguments", arg_start)),
+                }
+                self.skip_whitespace();
+                if self.eat(")") {
+                    break;
+                }
+                self.expect(",", "Expected ',' or ')'")?;
+                self.skip_whitespace();
+            }
+        }
+        if args.len() < function.parameters().len() {
+            return Err(JsonPathError::at("Too few arguments", start));
+        }
+        let regex = match (function, args.get(1)) {
+            (Function::Match, Some(Argument::Literal(Literal::String(pattern)))) => {
+                Some(iregexp::compile(pattern, true))
+            }
+            (Function::Search, Some(Argument::Literal(Literal::String(pattern)))) => {
+                Some(iregexp::compile(pattern, false))
+            }
+            _ => None,
+        };
+        Ok(Call {
+            function,
+            args,
+            regex,
+        })
+    }
+
+    /// A function argument: a literal, query or call on its own, or else a
+    /// logical expression.
+    fn argument(&mut self) -> Result<Argument, JsonPathError> {
+        let start = self.pos;
+        if !matches!(self.peek(), Some('!' | '(')) {
+            let operand = self.operand()?;
+            let after = self.pos;
+            self.skip_whitespace();
+            if matches!(self.peek(), Some(',' | ')')) {
+                self.pos = after;
+                return Ok(operand);
+            }
+        }
+        self.pos = start;
+        self.or()?;
+        Ok(Argument::Logical)
+    }
+}
+
+/// An operand used on its own as a filter test.
+fn test_expr(operand: Argument, start: usize) -> Result<Expr, JsonPathError> {
+    match operand {
+        Argument::Query(query) => Ok(Expr::Exists(query)),
+        Argument::Call(call) if call.function.result() != Type::Value => Ok(Expr::Test(call)),
+        Argument::Call(_) => Err(JsonPathError::at("Function result must be compared", start)),
+        _ => Err(JsonPathError::at("Literal must be compared", start)),
+    }
+}
+
+/// An operand used on one side of a comparison.
+fn comparable(operand: Argument, start: usize) -> Result<Comparable, JsonPathError> {
+    match operand {
+        Argument::Literal(literal) => Ok(Comparable::Literal(literal)),
+        Argument::Query(query) if query.is_singular() => Ok(Comparable::Query(query)),
+        Argument::Query(_) => Err(JsonPathError::at("Compared query must be singular", start)),
+        Argument::Call(call) if call.function.result() == Type::Value => Ok(Comparable::Call(call)),
+        _ => Err(JsonPathError::at(
+            "Function result cannot be compared",
+            start,
+        )),
+    }
+}
+
+impl Query {
+    /// Whether the query selects at most one node: only name and index
+    /// selectors, one per child segment.
+    fn is_singular(&self) -> bool {
+        self.segments.iter().all(|segment| {
+            !segment.descendant
+                && matches!(
+                    segment.selectors[..],
+                    [Selector::Name(_)] | [Selector::Index(_)]
+                )
+        })
+    }
+
+    fn select<'a>(&self, current: &'a ValueToken, root: &'a ValueToken) -> Vec<&'a ValueToken> {
+        let start = if self.absolute { root } else { current };
+        let mut nodes = vec![start];
+        for segment in &self.segments {
+            let mut selected = Vec::new();
+            for node in nodes {
+                if segment.descendant {
+                    segment.descend(node, root, &mut selected);
+                } else {
+                    segment.apply(node, root, &mut selected);
+                }
+            }
+            nodes = selected;
+        }
+        nodes
+    }
+}
+
+impl Segment {
+    fn apply<'a>(&self, node: &'a ValueToken, root: &'a ValueToken, out: &mut Vec<&'a ValueToken>) {
+        for selector in &self.selectors {
+            selector.select(node, root, out);
+        }
+    }
+
+    /// Apply the selectors to `node` and then to each of its descendants,
+    /// parents before children.
+    fn descend<'a>(
+        &self,
+        node: &'a ValueToken,
+        root: &'a ValueToken,
+        out: &mut Vec<&'a ValueToken>,
+    ) {
+        self.apply(node, root, out);
+        for child in children(node) {
+            self.descend(child, root, out);
+        }
+    }
+}
+
+impl Selector {
+    fn select<'a>(
+        &self,
+        node: &'a ValueToken,
+        root: &'a ValueToken,
+        out: &mut Vec<&'a ValueToken>,
+    ) {
+        match self {
+            Selector::Name(name) => {
+                if let ValueToken::ObjectToken { token, .. } = node {
+                    out.extend(token.get(name));
+                }
+            }
+            Selector::Wildcard => out.extend(children(node)),
+            Selector::Index(index) => {
+                if let ValueToken::ArrayToken { token, .. } = node {
+                    let len = token.values.len() as i64;
+                    let index = if *index < 0 { len + index } else { *index };
+                    if (0..len).contains(&index) {
+                        out.push(&token.values[index as usize]);
+                    }
+                }
+            }
+            Selector::Slice { start, end, step } => {
+                if let ValueToken::ArrayToken { token, .. } = node {
+                    for index in slice_indexes(token.values.len() as i64, *start, *end, *step) {
+                        out.push(&token.values[index as usize]);
+                    }
+                }
+            }
+            Selector::Filter(expr) => {
+                out.extend(children(node).filter(|child| expr.test(child, root)));
+            }
+        }
+    }
+}
+
+/// Array indexes selected by a slice, following RFC 9535, section 2.3.4.2.2.
+fn slice_indexes(len: i64, start: Option<i64>, end: Option<i64>, step: Option<i64>) -> Vec<i64> {
+    let step = step.unwrap_or(1);
+    let normalize = |index: i64| if index >= 0 { index } else { len + index };
+    let mut indexes = Vec::new();
+    if step > 0 {
+        let lower = start.map_or(0, normalize).clamp(0, len);
+        let upper = end.map_or(len, normalize).clamp(0, len);
+        let mut index = lower;
+        while index < upper {
+            indexes.push(index);
+            index += step;
+        }
+    } else if step < 0 {
+        let upper = start.map_or(len - 1, normalize).clamp(-1, len - 1);
+        let lower = end.map_or(-1, normalize).clamp(-1, len - 1);
+        let mut index = upper;
+        while lower < index {
+            indexes.push(index);
+            index += step;
+        }
+    }
+    indexes
+}
+
+/// Array elements or object member values, in order.
+fn children(node: &ValueToken) -> Box<dyn Iterator<Item = &ValueToken> + '_> {
+    match node {
+        ValueToken::ArrayToken { token, .. } => Box::new(token.values.iter().map(|value| &**value)),
+        ValueToken::ObjectToken { token, .. } => Box::new(token.iter().map(|pair| &*pair.value)),
+        _ => Box::new(std::iter::empty()),
+    }
+}
+
+/// A value during filter evaluation: a node of the document, a literal of
+/// the query or a computed number.
+#[derive(Clone, Copy)]
+enum Value<'a> {
+    Node(&'a ValueToken),
+    Literal(&'a Literal),
+    Number(f64),
+}
+
+/// What a [`Value`] holds, for comparisons.
+enum View<'a> {
+    Null,
+    Bool(bool),
+    Number(Cow<'a, Number>),
+    String(&'a str),
+    #[allow(clippy::vec_box)]
+    Array(&'a Vec<Box<ValueToken>>),
+    Object(&'a Object),
+}
+
+impl<'a> Value<'a> {
+    fn view(self) -> View<'a> {
+        match self {
+            Value::Node(node) => view(node),
+            Value::Literal(Literal::Null) => View::Null,
+            Value::Literal(Literal::Bool(value)) => View::Bool(*value),
+            Value::Literal(Literal::Number(value)) => View::Number(Cow::Borrowed(value)),
+            Value::Literal(Literal::String(value)) => View::String(value),
+            Value::Number(value) => View::Number(Cow::Owned(Number {
+                value,
+                value_as_string: value.to_string(),
+            })),
+        }
+    }
+}
+
+fn view(node: &ValueToken) -> View<'_> {
+    match node {
+        ValueToken::NullToken { .. } | ValueToken::PairToken { .. } => View::Null,
+        ValueToken::TrueToken { .. } => View::Bool(true),
+        ValueToken::FalseToken { .. } => View::Bool(false),
+        ValueToken::NumberToken { token, .. } => View::Number(Cow::Borrowed(token)),
+        ValueToken::StringToken { token, .. } => View::String(token),
+        ValueToken::ArrayToken { token, .. } => View::Array(&token.values),
+        ValueToken::ObjectToken { token, .. } => View::Object(token),
+    }
+}
+
+fn equal(left: View, right: View) -> bool {
+    match (left, right) {
+        (View::Null, View::Null) => true,
+        (View::Bool(left), View::Bool(right)) => left == right,
+        (View::Number(left), View::Number(right)) => {
+            number::compare(&left, &right) == Some(Ordering::Equal)
+        }
+        (View::String(left), View::String(right)) => left == right,
+        (View::Array(left), View::Array(right)) => {
+            left.len() == right.len()
+                && left
+                    .iter()
+                    .zip(right.iter())
+                    .all(|(left, right)| equal(view(left), view(right)))
+        }
+        (View::Object(left), View::Object(right)) => {
+            left.len() == right.len()
+                && left.iter().all(|pair| {
+                    right
+                        .get(&pair.key)
+                        .is_some_and(|value| equal(view(&pair.value), view(value)))
+                })
+        }
+        _ => false,
+    }
+}
+
+fn less(left: View, right: View) -> bool {
+    match (left, right) {
+        (View::Number(left), View::Number(right)) => {
+            number::compare(&left, &right) == Some(Ordering::Less)
+        }
+        (View::String(left), View::String(right)) => left < right,
+        _ => false,
+    }
+}
+
+/// Compare two values, either of which may be nothing.
+fn compare(left: Option<Value>, op: Op, right: Option<Value>) -> bool {
+    let eq = |left: Option<Value>, right: Option<Value>| match (left, right) {
+        (None, None) => true,
+        (Some(left), Some(right)) => equal(left.view(), right.view()),
+        _ => false,
+    };
+    let lt = |left: Option<Value>, right: Option<Value>| match (left, right) {
+        (Some(left), Some(right)) => less(left.view(), right.view()),
+        _ => false,
+    };
+    match op {
+        Op::Eq => eq(left, right),
+        Op::Ne => !eq(left, right),
+        Op::Lt => lt(left, right),
+        Op::Le => lt(left, right) || eq(left, right),
+        Op::Gt => lt(right, left),
+        Op::Ge => lt(right, left) || eq(left, right),
+    }
+}
+
+impl Expr {
+    fn test(&self, current: &ValueToken, root: &ValueToken) -> bool {
+        match self {
+            Expr::Or(operands) => operands.iter().any(|expr| expr.test(current, root)),
+            Expr::And(operands) => operands.iter().all(|expr| expr.test(current, root)),
+            Expr::Not(expr) => !expr.test(current, root),
+            Expr::Compare(left, op, right) => {
+                compare(left.value(current, root), *op, right.value(current, root))
+            }
+            Expr::Exists(query) => !query.select(current, root).is_empty(),
+            Expr::Test(call) => call.test(current, root),
+        }
+    }
+}
+
+impl Comparable {
+    fn value<'a>(&'a self, current: &'a ValueToken, root: &'a ValueToken) -> Option<Value<'a>> {
+        match self {
+            Comparable::Literal(literal) => Some(Value::Literal(literal)),
+            Comparable::Query(query) => query
+                .select(current, root)
+                .first()
+                .copied()
+                .map(Value::Node),
+            Comparable::Call(call) => call.value(current, root),
+        }
+    }
+}
+
+impl Argument {
+    fn value<'a>(&'a self, current: &'a ValueToken, root: &'a ValueToken) -> Option<Value<'a>> {
+        match self {
+            Argument::Literal(literal) => Some(Value::Literal(literal)),
+            Argument::Query(query) => query
+                .select(current, root)
+                .first()
+                .copied()
+                .map(Value::Node),
+            Argument::Call(call) => call.value(current, root),
+            Argument::Logical => None,
+        }
+    }
+
+    fn nodes<'a>(&self, current: &'a ValueToken, root: &'a ValueToken) -> Vec<&'a ValueToken> {
+        match self {
+            Argument::Query(query) => query.select(current, root),
+            _ => Vec::new(),
+        }
+    }
+}
+
+impl Call {
+    /// Result of a function returning a value; `None` is nothing.
+    fn value<'a>(&'a self, current: &'a ValueToken, root: &'a ValueToken) -> Option<Value<'a>> {
+        let arg = &self.args[0];
+        match self.function {
+            Function::Length => {
+                let length = match arg.value(current, root)?.view() {
+                    View::String(string) => string.chars().count(),
+                    View::Array(values) => values.len(),
+                    View::Object(members) => members.len(),
+                    _ => return None,
+                };
+                Some(Value::Number(length as f64))
+            }
+            Function::Count => Some(Value::Number(arg.nodes(current, root).len() as f64)),
+            Function::Value => match arg.nodes(current, root)[..] {
+                [node] => Some(Value::Node(node)),
+                _ => None,
+            },
+            Function::Match | Function::Search => None,
+        }
+    }
+
+    /// Result of a function returning a logical value.
+    fn test(&self, current: &ValueToken, root: &ValueToken) -> bool {
+        let text = self.args[0].value(current, root).map(Value::view);
+        let pattern = self.args[1].value(current, root).map(Value::view);
+        let (Some(View::String(text)), Some(View::String(pattern))) = (text, pattern) else {
+            return false;
+        };
+        let compiled;
+        let regex = match &self.regex {
+            Some(regex) => regex.as_ref(),
+            None => {
+                compiled = iregexp::compile(pattern, self.function == Function::Match);
+                compiled.as_ref()
+            }
+        };
+        regex.is_some_and(|regex| regex.is_match(text))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::JsonPath;
+    use crate::json::parse;
+    use crate::json_value::JsonValue;
+    use crate::types::{Json, ValueToken};
+
+    /// Hand-written cases laid out like the JSONPath Compliance Test Suite's
+    /// `cts.json`: a `tests` array of objects with `name`, `selector`,
+    /// optional `document`, and `result`, `results` (any one of several
+    /// orders) or `invalid_selector`.
+    const CASES: &str = include_str!("../testdata/jsonpath/cases.json");
+
+    /// Upstream suite cases this engine does not pass, by name. Every case
+    /// not listed here must pass.
+    const CTS_SKIPPED: &[&str] = &[];
+
+    fn document(input: &str) -> Json {
+        match parse(input) {
+            Ok(json) => json,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    /// JSON equality: numbers by value, objects ignoring member order.
+    fn same(left: &JsonValue, right: &JsonValue) -> bool {
+        match (left, right) {
+            (JsonValue::Number(left), JsonValue::Number(right)) => left.value == right.value,
+            (JsonValue::Array(left), JsonValue::Array(right)) => {
+                left.len() == right.len() && left.iter().zip(right).all(|(l, r)| same(l, r))
+            }
+            (JsonValue::Object(left), JsonValue::Object(right)) => {
+                left.len() == right.len()
+                    && left
+                        .iter()
+                        .all(|(key, value)| right.iter().any(|(k, v)| k == key && same(value, v)))
+            }
+            _ => left == right,
+        }
+    }
+
+    fn same_list(expected: &JsonValue, actual: &[JsonValue]) -> bool {
+        match expected {
+            JsonValue::Array(expected) => {
+                expected.len() == actual.len()
+                    && expected.iter().zip(actual).all(|(e, a)| same(e, a))
+            }
+            _ => false,
+        }
+    }
+
+    /// Run every case in a suite in the `cts.json` layout, except those
+    /// named in `skipped`. Returns the number of cases run.
+    fn run_suite(suite: &Json, skipped: &[&str]) -> usize {
+        let Some(ValueToken::ArrayToken { token: cases, .. }) = suite.pointer("/tests") else {
+            panic!("suite has no tests array");
+        };
+        let mut run = 0;
+        let mut seen = Vec::new();
+        for case in &cases.values {
+            let field = |name: &str| case.pointer(&format!("/{}", name));
+            let text = |name: &str| match field(name) {
+                Some(ValueToken::StringToken { token, .. }) => Some(token.as_str()),
+                _ => None,
+            };
+            let name = text("name").unwrap_or("?");
+            if skipped.contains(&name) {
+                seen.push(name);
+                continue;
+            }
+            run += 1;
+            let Some(selector) = text("selector") else {
+                panic!("{}: no selector", name);
+            };
+            let path = JsonPath::parse(selector);
+            if let Some(ValueToken::TrueToken { .. }) = field("invalid_selector") {
+                assert!(path.is_err(), "{}: {:?} should be rejected", name, selector);
+                continue;
+            }
+            let path = match path {
+                Ok(path) => path,
+                Err(e) => panic!("{}: {:?} rejected: {}", name, selector, e),
+            };
+            let Some(document) = field("document") else {
+                panic!("{}: no document", name);
+            };
+            let actual: Vec<JsonValue> = path
+                .query(document)
+                .into_iter()
+                .map(|node| JsonValue::from(node.clone()))
+                .collect();
+            let expected = |token: &ValueToken| JsonValue::from(token.clone());
+            let passed = match (field("result"), field("results")) {
+                (Some(result), _) => same_list(&expected(result), &actual),
+                (None, Some(ValueToken::ArrayToken { token, .. })) => token
+                    .values
+                    .iter()
+                    .any(|result| same_list(&expected(result), &actual)),
+                _ => panic!("{}: no expected result", name),
+            };
+            assert!(passed, "{}: {:?} selected {:?}", name, selector, actual);
+        }
+        for name in skipped {
+            assert!(
+                seen.contains(name),
+                "skipped case {:?} is not in the suite",
+                name
+            );
+        }
+        run
+    }
+
+    #[test]
+    fn hand_written_cases() {
+        assert!(run_suite(&document(CASES), &[]) > 0);
+    }
+
+    /// The JSONPath Compliance Test Suite itself, once vendored by
+    /// `scripts/fetch-test-suites.sh`.
+    #[test]
+    #[ignore = "needs testdata/jsonpath/cts from scripts/fetch-test-suites.sh"]
+    fn compliance_suite() {
+        let path = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/testdata/jsonpath/cts/cts.json"
+        );
+        let text = match std::fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) => panic!("{}: {}", path, e),
+        };
+        run_suite(&document(&text), CTS_SKIPPED);
+    }
+
+    #[test]
+    fn rfc_bookstore_examples() {
+        let json = document(
+            r#"{ "store": {
+                "book": [
+                  { "category": "reference", "author": "Nigel Rees",
+                    "title": "Sayings of the Century", "price": 8.95 },
+                  { "category": "fiction", "author": "Evelyn Waugh",
+                    "title": "Sword of Honour", "price": 12.99 },
+                  { "category": "fiction", "author": "Herman Melville",
+                    "title": "Moby Dick", "isbn": "0-553-21311-3", "price": 8.99 },
+                  { "category": "fiction", "author": "J. R. R. Tolkien",
+                    "title": "The Lord of the Rings", "isbn": "0-395-19395-8",
+                    "price": 22.99 }
+                ],
+                "bicycle": { "color": "red", "price": 399 }
+            } }"#,
+        );
+        let count = |query: &str| match JsonPath::parse(query) {
+            Ok(path) => path.query(&json.token).len(),
+            Err(e) => panic!("{}: {}", query, e),
+        };
+        assert_eq!(4, count("$.store.book[*].author"));
+        assert_eq!(4, count("$..author"));
+        assert_eq!(2, count("$.store.*"));
+        assert_eq!(5, count("$.store..price"));
+        assert_eq!(1, count("$..book[2]"));
+        assert_eq!(1, count("$..book[-1]"));
+        assert_eq!(2, count("$..book[0,1]"));
+        assert_eq!(2, count("$..book[:2]"));
+        assert_eq!(2, count("$..book[?@.isbn]"));
+        assert_eq!(2, count("$..book[?@.price<10]"));
+        assert_eq!(27, count("$..*"));
+        assert_eq!(2, count("$.store.book[?@.price < 10].title"));
+        assert_eq!(
+            1,
+            count("$.store.book[?match(@.author, 'J.*') && length(@.title) > 10]")
+        );
+    }
+
+    #[test]
+    fn numbers_compare_by_value() {
+        let json = document("[1, 1.0, 10e-1, 2, 9007199254740992, 9007199254740993]");
+        let count = |query: &str| match JsonPath::parse(query) {
+            Ok(path) => path.query(&json.token).len(),
+            Err(e) => panic!("{}: {}", query, e),
+        };
+        assert_eq!(3, count("$[?@ == 1]"));
+        assert_eq!(3, count("$[?@ < 1.5e0]"));
+        // Both round to 2^53 as f64.
+        let exact = cfg!(feature = "arbitrary_precision");
+        assert_eq!(
+            if exact { 1 } else { 2 },
+            count("$[?@ == 9007199254740993]")
+        );
+        assert_eq!(if exact { 5 } else { 4 }, count("$[?@ < 9007199254740993]"));
+    }
+
+    #[test]
+    fn errors_point_at_the_problem() {
+        for (query, offset) in [
+            ("", 0),
+            ("a", 0),
+            ("$ ", 1),
+            ("$.", 2),
+            ("$[01]", 2),
+            ("$[?@.a == @.*]", 10),
+            ("$[?length(@.*) == 1]", 10),
+            ("$[?nope(@)]", 3),
+        ] {
+            match JsonPath::parse(query) {
+                Err(e) => assert_eq!(offset, e.offset, "{:?}: {}", query, e),
+                Ok(_) => panic!("{:?} should be rejected", query),
+            }
+        }
+    }
+}
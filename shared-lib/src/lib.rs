@@ -1,15 +1,63 @@
-#[macro_use]
-extern crate lazy_static;
-extern crate regex;
-
-pub use json::parse;
-pub use types::{Json, ValueToken};
+#[cfg(feature = "serde")]
+pub use de::{from_str, from_str_with_options, from_token};
+pub use diff::{Change, diff, diff_with_options};
+#[cfg(feature = "serde")]
+pub use error::{DeserializeError, SerializeError};
+pub use error::{
+    ErrorKind, JsonPathError, ParseError, PatchError, PathSegment, PointerError, ReadError,
+    SchemaError, UnsupportedOption, ValidationError,
+};
+pub use events::JsonEvent;
+pub use json::{parse, parse_with_options};
+pub use json_value::{JsonIndex, JsonObject, JsonValue};
+pub use jsonpath::JsonPath;
+pub use lines::{JsonLinesReader, ParseMany, parse_many, parse_many_with_options};
+pub use merge::merge_patch;
+pub use number::NumberKind;
+pub use options::{
+    ArrayAlignment, Dialect, DiffOptions, DuplicateKeys, LoneSurrogates, ParseOptions,
+};
+pub use ordered_map::{Member, OrderedMap};
+pub use patch::{JsonPatch, PatchOperation};
+pub use pointer::JsonPointer;
+pub use push::JsonPushParser;
+pub use reader::JsonReader;
+pub use schema::JsonSchema;
+#[cfg(feature = "serde")]
+pub use ser::{to_string, to_string_pretty, to_value, to_writer};
+pub use serializer::{to_json_pretty, to_json_pretty_with_comments, to_json_string};
+pub use types::{
+    Array, Comment, CommentKind, CommentPlacement, Json, Number, Object, Pair, Position, RawString,
+    Span, ValueToken,
+};
 
 mod array;
+mod comment;
+mod cursor;
+#[cfg(feature = "serde")]
+mod de;
+mod diff;
+mod error;
+mod events;
+mod iregexp;
 mod json;
+mod json_value;
+mod jsonpath;
+mod lines;
+mod merge;
 mod number;
 mod object;
+mod options;
+mod ordered_map;
 mod pair;
+mod patch;
+mod pointer;
+mod push;
+mod reader;
+mod schema;
+#[cfg(feature = "serde")]
+mod ser;
+mod serializer;
 mod string;
 mod types;
 mod value;
@@ -17,7 +65,9 @@ mod value;
 pub fn pretty_print_token(token: &ValueToken, indent: usize) -> String {
     let indent_str = "  ".repeat(indent);
     match token {
-        ValueToken::ArrayToken { skip, token: array } => {
+        ValueToken::ArrayToken {
+            skip, token: array, ..
+        } => {
             let mut s = format!("ArrayToken (skip: {}) [\n", skip);
             for (i, v) in array.values.iter().enumerate() {
                 s.push_str(&indent_str);
@@ -35,9 +85,10 @@ pub fn pretty_print_token(token: &ValueToken, indent: usize) -> String {
         ValueToken::ObjectToken {
             skip,
             token: object,
+            ..
         } => {
             let mut s = format!("ObjectToken (skip: {}) {{\n", skip);
-            for (i, pair) in object.members.iter().enumerate() {
+            for (i, pair) in object.iter().enumerate() {
                 s.push_str(&indent_str);
                 s.push_str("  ");
                 s.push_str(&format!(
@@ -45,7 +96,7 @@ pub fn pretty_print_token(token: &ValueToken, indent: usize) -> String {
                     pair.key,
                     pretty_print_token(&pair.value, indent + 1)
                 ));
-                if i < object.members.len() - 1 {
+                if i < object.len() - 1 {
                     s.push(',');
                 }
                 s.push('\n');
@@ -57,15 +108,23 @@ pub fn pretty_print_token(token: &ValueToken, indent: usize) -> String {
         ValueToken::StringToken {
             skip,
             token: string,
+            ..
         } => format!("StringToken (skip: {}) \"{}\"", skip, string),
         ValueToken::NumberToken {
             skip,
             token: number,
+            ..
         } => format!("NumberToken (skip: {}) {}", skip, number.value_as_string),
-        ValueToken::TrueToken { skip, token } => format!("TrueToken (skip: {}) {}", skip, token),
-        ValueToken::FalseToken { skip, token } => format!("FalseToken (skip: {}) {}", skip, token),
-        ValueToken::NullToken { skip } => format!("NullToken (skip: {})", skip),
-        ValueToken::PairToken { skip, token: pair } => {
+        ValueToken::TrueToken { skip, token, .. } => {
+            format!("TrueToken (skip: {}) {}", skip, token)
+        }
+        ValueToken::FalseToken { skip, token, .. } => {
+            format!("FalseToken (skip: {}) {}", skip, token)
+        }
+        ValueToken::NullToken { skip, .. } => format!("NullToken (skip: {})", skip),
+        ValueToken::PairToken {
+            skip, token: pair, ..
+        } => {
             format!(
                 "PairToken (skip: {}) \"{}\": {}",
                 skip,
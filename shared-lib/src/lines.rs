@@ -0,0 +1,334 @@
+use crate::cursor::is_whitespace;
+use crate::error::{ErrorKind, ParseError, ReadError};
+use crate::json::parse_at;
+use crate::options::ParseOptions;
+use crate::types::{Json, Position, ValueToken};
+use std::io::BufRead;
+
+/// Record separator that starts each text of an RFC 7464 JSON text sequence.
+const RS: u8 = 0x1E;
+
+/// Parse a text holding any number of top-level values one after another,
+/// such as newline-delimited or concatenated JSON.
+///
+/// Each item is one value, or the error that stopped it; after an error the
+/// iterator resumes on the next line. Spans and error positions are relative
+/// to the whole input.
+///
+/// ```
+/// use shared_lib::parse_many;
+///
+/// let results: Vec<_> = parse_many("{\"a\": 1} [2]\nnope\n3").collect();
+/// assert_eq!(4, results.len());
+/// assert!(results[2].is_err());
+/// ```
+pub fn parse_many(input: &str) -> ParseMany<'_> {
+    parse_many_with_options(input, &ParseOptions::default())
+}
+
+/// [`parse_many`] with options. `allow_trailing_content` is ignored, as the
+/// content after each value is the next value.
+pub fn parse_many_with_options<'a>(input: &'a str, options: &ParseOptions) -> ParseMany<'a> {
+    ParseMany {
+        input,
+        options: ParseOptions {
+            allow_trailing_content: true,
+            ..options.clone()
+        },
+        position: Position::START,
+    }
+}
+
+/// Iterator returned by [`parse_many`].
+pub struct ParseMany<'a> {
+    input: &'a str,
+    options: ParseOptions,
+    position: Position,
+}
+
+impl Iterator for ParseMany<'_> {
+    type Item = Result<Json, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let rest = &self.input[self.position.offset..];
+        let trimmed = rest.trim_start_matches(is_whitespace);
+        self.position = advance(
+            self.position,
+            &rest.as_bytes()[..rest.len() - trimmed.len()],
+        );
+        if trimmed.is_empty() {
+            return None;
+        }
+        match parse_at(trimmed, self.position, &self.options) {
+            Ok(json) => {
+                let consumed = trimmed.chars().take(json.skip).map(char::len_utf8).sum();
+                self.position = advance(self.position, &trimmed.as_bytes()[..consumed]);
+                Some(Ok(json))
+            }
+            Err(e) => {
+                // Resume on the line after the error.
+                let local = e.offset - self.position.offset;
+                let end = trimmed[local..]
+                    .find('\n')
+                    .map_or(trimmed.len(), |newline| local + newline + 1);
+                self.position = advance(self.position, &trimmed.as_bytes()[..end]);
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+/// Reader for JSON Lines, one value per line, or for RFC 7464 JSON text
+/// sequences, where each value follows an ASCII record separator.
+///
+/// Every record is parsed on its own, so a bad record yields an error and
+/// the next one is read as usual. Blank records are skipped. Spans and error
+/// positions are relative to the whole stream.
+///
+/// ```
+/// use shared_lib::JsonLinesReader;
+///
+/// let input = "{\"id\": 1}\n{\"id\": }\n{\"id\": 3}\n";
+/// let results: Vec<_> = JsonLinesReader::new(input.as_bytes()).collect();
+/// assert_eq!(3, results.len());
+/// assert!(results[0].is_ok() && results[1].is_err() && results[2].is_ok());
+/// ```
+pub struct JsonLinesReader<R> {
+    reader: R,
+    options: ParseOptions,
+    separator: u8,
+    record: Vec<u8>,
+    position: Position,
+}
+
+impl<R: BufRead> JsonLinesReader<R> {
+    pub fn new(reader: R) -> Self {
+        JsonLinesReader::with_options(reader, ParseOptions::default())
+    }
+
+    pub fn with_options(reader: R, options: ParseOptions) -> Self {
+        JsonLinesReader {
+            reader,
+            options,
+            separator: b'\n',
+            record: Vec::new(),
+            position: Position::START,
+        }
+    }
+
+    /// Read an RFC 7464 JSON text sequence instead of lines. As the RFC
+    /// requires, a top-level number or literal that is not followed by
+    /// whitespace is reported as [`ErrorKind::Truncated`].
+    pub fn sequence(reader: R, options: ParseOptions) -> Self {
+        JsonLinesReader {
+            separator: RS,
+            ..JsonLinesReader::with_options(reader, options)
+        }
+    }
+
+    /// Source position of the start of the next record.
+    pub fn position(&self) -> Position {
+        self.position
+    }
+
+    fn parse(&self, record: &[u8], origin: Position) -> Result<Json, ParseError> {
+        let text = std::str::from_utf8(record).map_err(|e| {
+            let valid = std::str::from_utf8(&record[..e.valid_up_to()]).unwrap_or_default();
+            ParseError::at(ErrorKind::InvalidUtf8, valid.len())
+                .locate(valid)
+                .offset_by(origin)
+        })?;
+        let json = parse_at(text, origin, &self.options)?;
+        let scalar = matches!(
+            *json.token,
+            ValueToken::NumberToken { .. }
+                | ValueToken::TrueToken { .. }
+                | ValueToken::FalseToken { .. }
+                | ValueToken::NullToken { .. }
+        );
+        if self.separator == RS && scalar && json.skip == text.chars().count() {
+            return Err(ParseError::at(ErrorKind::Truncated, text.len())
+                .locate(text)
+                .offset_by(origin));
+        }
+        Ok(json)
+    }
+}
+
+impl<R: BufRead> Iterator for JsonLinesReader<R> {
+    type Item = Result<Json, ReadError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let mut record = std::mem::take(&mut self.record);
+            record.clear();
+            match self.reader.read_until(self.separator, &mut record) {
+                Ok(0) => return None,
+                Ok(_) => {}
+                Err(e) => return Some(Err(ReadError::Io(e))),
+            }
+            let origin = self.position;
+            self.position = advance(self.position, &record);
+            let text = record.strip_suffix(&[self.separator]).unwrap_or(&record);
+            let result = if text.iter().all(|&byte| is_whitespace(byte as char)) {
+                None
+            } else {
+                Some(self.parse(text, origin).map_err(ReadError::Parse))
+            };
+            self.record = record;
+            if result.is_some() {
+                return result;
+            }
+        }
+    }
+}
+
+/// The position after `bytes`, which start at `position`. Columns count
+/// characters, so UTF-8 continuation bytes do not advance them.
+fn advance(mut position: Position, bytes: &[u8]) -> Position {
+    for &byte in bytes {
+        position.offset += 1;
+        if byte == b'\n' {
+            position.line += 1;
+            position.column = 1;
+        } else if byte & 0xC0 != 0x80 {
+            position.column += 1;
+        }
+    }
+    position
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{JsonLinesReader, parse_many};
+    use crate::error::{ErrorKind, ParseError, ReadError};
+    use crate::options::ParseOptions;
+    use crate::types::{Json, ValueToken};
+
+    fn start(json: &Json) -> (usize, usize, usize) {
+        let start = json.token.span().start;
+        (start.offset, start.line, start.column)
+    }
+
+    fn parse_error(result: &Result<Json, ReadError>) -> &ParseError {
+        match result {
+            Err(ReadError::Parse(e)) => e,
+            other => panic!("Expected parse error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn concatenated_values() {
+        let input = "{\"a\":1}{\"b\":2} [3]\n\"\u{e9}\" 4";
+        let values = match parse_many(input).collect::<Result<Vec<_>, _>>() {
+            Ok(values) => values,
+            Err(e) => panic!("{}", e),
+        };
+        assert_eq!(
+            vec![(0, 1, 1), (7, 1, 8), (15, 1, 16), (19, 2, 1), (24, 2, 5)],
+            values.iter().map(start).collect::<Vec<_>>()
+        );
+        assert!(matches!(*values[3].token, ValueToken::StringToken { .. }));
+    }
+
+    #[test]
+    fn resumes_after_errors() {
+        let results: Vec<_> = parse_many("1\n[2,\n{x}] 5\n  3 ]\n4").collect();
+        assert_eq!(5, results.len());
+        match &results[1] {
+            Err(e) => assert_eq!((3, 2), (e.line, e.column)),
+            Ok(json) => panic!("Expected error, got {:?}", json),
+        }
+        assert!(results[2].is_ok());
+        match &results[3] {
+            Err(e) => assert_eq!((4, 5), (e.line, e.column)),
+            Ok(json) => panic!("Expected error, got {:?}", json),
+        }
+        match &results[4] {
+            Ok(json) => assert_eq!((19, 5, 1), start(json)),
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    #[test]
+    fn truncated_values_are_errors() {
+        let results: Vec<_> = parse_many("[1] [2").collect();
+        assert_eq!(2, results.len());
+        assert!(results[0].is_ok());
+        match &results[1] {
+            Err(e) => {
+                assert_eq!(ErrorKind::Expected("',' or ']'"), e.kind);
+                assert_eq!((1, 7, 6), (e.line, e.column, e.offset));
+            }
+            Ok(json) => panic!("Expected error, got {:?}", json),
+        }
+    }
+
+    #[test]
+    fn empty_input() {
+        assert_eq!(0, parse_many(" \n\t").count());
+    }
+
+    #[test]
+    fn lines_are_parsed_independently() {
+        let input = "{\"a\": 1}\n\n[1 x]\n{\"a\":\n2}\r\n\"\u{e9}\"\n";
+        let results: Vec<_> = JsonLinesReader::new(input.as_bytes()).collect();
+        assert_eq!(5, results.len());
+        assert!(results[0].is_ok());
+        let e = parse_error(&results[1]);
+        assert_eq!(ErrorKind::Expected("',' or ']'"), e.kind);
+        assert_eq!((3, 4, 13), (e.line, e.column, e.offset));
+        assert_eq!(4, parse_error(&results[2]).line);
+        assert_eq!(5, parse_error(&results[3]).line);
+        match &results[4] {
+            Ok(json) => assert_eq!((26, 6, 1), start(json)),
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    #[test]
+    fn truncated_lines_are_errors() {
+        let input = "{\"id\": 1}\n{\"id\": 2\n[1,\n[3]\n";
+        let results: Vec<_> = JsonLinesReader::new(input.as_bytes()).collect();
+        assert_eq!(4, results.len());
+        assert!(results[0].is_ok());
+        let e = parse_error(&results[1]);
+        assert_eq!(ErrorKind::Expected("',' or '}'"), e.kind);
+        assert_eq!((2, 9, 18), (e.line, e.column, e.offset));
+        let e = parse_error(&results[2]);
+        assert_eq!(ErrorKind::Expected("value"), e.kind);
+        assert_eq!((3, 4, 22), (e.line, e.column, e.offset));
+        assert!(results[3].is_ok());
+    }
+
+    #[test]
+    fn invalid_utf8_line() {
+        let input: &[u8] = b"1\n\"a\xffb\"\n2\n";
+        let results: Vec<_> = JsonLinesReader::new(input).collect();
+        assert_eq!(3, results.len());
+        let e = parse_error(&results[1]);
+        assert_eq!(ErrorKind::InvalidUtf8, e.kind);
+        assert_eq!((2, 3, 4), (e.line, e.column, e.offset));
+        assert!(results[2].is_ok());
+    }
+
+    #[test]
+    fn json_text_sequence() {
+        let input: &[u8] = b"\x1e{\"a\":1}\n\x1e123\x1e[oops\n\x1e\x1etrue\n\x1e-1 \n";
+        let results: Vec<_> = JsonLinesReader::sequence(input, ParseOptions::default()).collect();
+        assert_eq!(5, results.len());
+        assert!(results[0].is_ok());
+        let e = parse_error(&results[1]);
+        assert_eq!(ErrorKind::Truncated, e.kind);
+        assert_eq!((2, 13), (e.line, e.offset));
+        assert_eq!(
+            ErrorKind::UnexpectedCharacter,
+            parse_error(&results[2]).kind
+        );
+        match &results[3] {
+            Ok(json) => assert!(matches!(*json.token, ValueToken::TrueToken { .. })),
+            Err(e) => panic!("{}", e),
+        }
+        assert!(results[4].is_ok());
+    }
+}
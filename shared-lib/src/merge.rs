@@ -0,0 +1,114 @@
+use crate::types::{Object, Span, ValueToken};
+
+/// Apply an RFC 7386 JSON Merge Patch to `target`.
+///
+/// An object patch is merged member by member: `null` removes the member,
+/// and any other value is merged into the member recursively. A patch that
+/// is not an object replaces the target whole.
+///
+/// ```
+/// use shared_lib::{merge_patch, parse, to_json_string};
+///
+/// let mut json = parse(r#"{"title": "Hello!", "author": {"name": "A", "email": "a@x"}}"#)
+///     .unwrap();
+/// let patch = parse(r#"{"title": "Hi", "author": {"email": null}}"#).unwrap();
+/// merge_patch(&mut json.token, &patch.token);
+/// assert_eq!(
+///     r#"{"title":"Hi","author":{"name":"A"}}"#,
+///     to_json_string(&json.token)
+/// );
+/// ```
+pub fn merge_patch(target: &mut ValueToken, patch: &ValueToken) {
+    let ValueToken::ObjectToken { token: patch, .. } = patch else {
+        *target = patch.clone();
+        return;
+    };
+    if !matches!(target, ValueToken::ObjectToken { .. }) {
+        *target = ValueToken::ObjectToken {
+            skip: 0,
+            span: Span::default(),
+            token: Object::new(),
+        };
+    }
+    let ValueToken::ObjectToken { token: members, .. } = target else {
+        return;
+    };
+    for pair in patch {
+        if let ValueToken::NullToken { .. } = *pair.value {
+            members.remove(&pair.key);
+            continue;
+        }
+        match members.get_mut(&pair.key) {
+            Some(value) => merge_patch(value, &pair.value),
+            None => {
+                // Merging into nothing still drops the nulls nested in the
+                // patch value.
+                let mut value = ValueToken::NullToken {
+                    skip: 0,
+                    span: Span::default(),
+                };
+                merge_patch(&mut value, &pair.value);
+                members.insert(pair.key.clone(), Box::new(value));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::merge_patch;
+    use crate::json::parse;
+    use crate::serializer::to_json_string;
+    use crate::types::ValueToken;
+
+    fn token(input: &str) -> ValueToken {
+        match parse(input) {
+            Ok(json) => *json.token,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    /// The example table of RFC 7386, appendix A.
+    #[test]
+    fn rfc_examples() {
+        for (original, patch, result) in [
+            (r#"{"a":"b"}"#, r#"{"a":"c"}"#, r#"{"a":"c"}"#),
+            (r#"{"a":"b"}"#, r#"{"b":"c"}"#, r#"{"a":"b","b":"c"}"#),
+            (r#"{"a":"b"}"#, r#"{"a":null}"#, r#"{}"#),
+            (r#"{"a":"b","b":"c"}"#, r#"{"a":null}"#, r#"{"b":"c"}"#),
+            (r#"{"a":["b"]}"#, r#"{"a":"c"}"#, r#"{"a":"c"}"#),
+            (r#"{"a":"c"}"#, r#"{"a":["b"]}"#, r#"{"a":["b"]}"#),
+            (
+                r#"{"a":{"b":"c"}}"#,
+                r#"{"a":{"b":"d","c":null}}"#,
+                r#"{"a":{"b":"d"}}"#,
+            ),
+            (r#"{"a":[{"b":"c"}]}"#, r#"{"a":[1]}"#, r#"{"a":[1]}"#),
+            (r#"["a","b"]"#, r#"["c","d"]"#, r#"["c","d"]"#),
+            (r#"{"a":"b"}"#, r#"["c"]"#, r#"["c"]"#),
+            (r#"{"a":"foo"}"#, r#"null"#, r#"null"#),
+            (r#"{"a":"foo"}"#, r#""bar""#, r#""bar""#),
+            (r#"{"e":null}"#, r#"{"a":1}"#, r#"{"e":null,"a":1}"#),
+            (r#"[1,2]"#, r#"{"a":"b","c":null}"#, r#"{"a":"b"}"#),
+            (
+                r#"{}"#,
+                r#"{"a":{"bb":{"ccc":null}}}"#,
+                r#"{"a":{"bb":{}}}"#,
+            ),
+        ] {
+            let mut target = token(original);
+            merge_patch(&mut target, &token(patch));
+            assert_eq!(result, to_json_string(&target), "{} + {}", original, patch);
+        }
+    }
+
+    #[test]
+    fn keeps_member_order() {
+        let mut target = token(r#"{"x":1,"y":{"z":2},"w":3}"#);
+        merge_patch(
+            &mut target,
+            &token(r#"{"y":{"z":null,"v":4},"x":null,"u":5}"#),
+        );
+        assert_eq!(r#"{"y":{"v":4},"w":3,"u":5}"#, to_json_string(&target));
+    }
+}
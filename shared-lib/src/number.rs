@@ -1,11 +1,19 @@
-use crate::types::{Number, ValueToken};
-use regex::Regex;
+use crate::cursor::Cursor;
+use crate::error::{ErrorKind, ParseError};
+use crate::options::Dialect;
+use crate::types::{Number, Span, ValueToken};
+use std::cmp::Ordering;
+use std::fmt;
 
+/// States of the RFC 8259 number grammar:
+/// `[ minus ] int [ frac ] [ exp ]`, plus the JSON5 hexadecimal form.
 enum Mode {
     Scanning,
     Characteristic,
+    Hex,
     CharacteristicDigit,
     DecimalPoint,
+    MantissaFirstDigit,
     Mantissa,
     Exponent,
     ExponentSign,
@@ -14,52 +22,78 @@ enum Mode {
     End,
 }
 
-lazy_static! {
-    static ref DIGIT: Regex = Regex::new(r"\d").unwrap();
-    static ref NON_ZERO_DIGIT: Regex = Regex::new(r"[1-9]").unwrap();
-    static ref WHITESPACE: Regex = Regex::new(r"[ \n\r\t]").unwrap();
-}
-
-pub fn parse_number(number: &str, delimiters: &str) -> Result<ValueToken, &'static str> {
+pub fn parse_number(cursor: &mut Cursor) -> Result<ValueToken, ParseError> {
     let mut mode = Mode::Scanning;
-    let mut pos: usize = 0;
+    let start = cursor.chars();
+    let mut span_start = cursor.position();
     let mut value_as_string = String::new();
-    let is_delimiter: &Regex = &Regex::new(delimiters).unwrap();
-
-    while let Some(ch) = number.chars().nth(pos) {
-        let char = &ch.to_string()[..];
+    let json5 = cursor.dialect() == Dialect::Json5;
+    let mut hex_digits = String::new();
+    let mut leading_point = false;
 
+    while let Some(ch) = cursor.peek() {
         match mode {
             Mode::Scanning => {
-                if WHITESPACE.is_match(char) {
-                    pos += 1;
+                span_start = cursor.position();
+                if cursor.is_trivia(ch) {
+                    cursor.skip_trivia(ch)?;
                 } else if ch == '-' {
                     value_as_string.push(ch);
-                    pos += 1;
+                    cursor.advance(ch);
+                    mode = Mode::Characteristic;
+                } else if ch == '+' && json5 {
+                    cursor.advance(ch);
                     mode = Mode::Characteristic;
                 } else {
                     mode = Mode::Characteristic;
                 }
             }
             Mode::Characteristic => {
-                if ch == '0' {
+                if json5 && cursor.eat("Infinity") {
+                    value_as_string.push_str("Infinity");
+                    mode = Mode::End;
+                } else if json5 && cursor.eat("NaN") {
+                    // The sign of NaN carries no meaning.
+                    value_as_string = String::from("NaN");
+                    mode = Mode::End;
+                } else if json5 && (cursor.eat("0x") || cursor.eat("0X")) {
+                    mode = Mode::Hex;
+                } else if json5 && ch == '.' {
+                    value_as_string.push('0');
+                    leading_point = true;
+                    mode = Mode::DecimalPoint;
+                } else if ch == '0' {
                     value_as_string.push(ch);
-                    pos += 1;
+                    cursor.advance(ch);
                     mode = Mode::DecimalPoint;
-                } else if NON_ZERO_DIGIT.is_match(char) {
+                } else if matches!(ch, '1'..='9') {
                     value_as_string.push(ch);
-                    pos += 1;
+                    cursor.advance(ch);
                     mode = Mode::CharacteristicDigit;
                 } else {
-                    return Err("Expected digit");
+                    return Err(ParseError::at(
+                        ErrorKind::MissingIntegerDigits,
+                        cursor.pos(),
+                    ));
+                }
+            }
+            Mode::Hex => {
+                if ch.is_ascii_hexdigit() {
+                    hex_digits.push(ch);
+                    cursor.advance(ch);
+                } else if hex_digits.is_empty() {
+                    return Err(ParseError::at(
+                        ErrorKind::Expected("hexadecimal digit"),
+                        cursor.pos(),
+                    ));
+                } else {
+                    mode = Mode::End;
                 }
             }
             Mode::CharacteristicDigit => {
-                if DIGIT.is_match(char) {
+                if ch.is_ascii_digit() {
                     value_as_string.push(ch);
-                    pos += 1;
-                } else if !delimiters.is_empty() && is_delimiter.is_match(char) {
-                    mode = Mode::End;
+                    cursor.advance(ch);
                 } else {
                     mode = Mode::DecimalPoint;
                 }
@@ -67,76 +101,129 @@ pub fn parse_number(number: &str, delimiters: &str) -> Result<ValueToken, &'stat
             Mode::DecimalPoint => {
                 if ch == '.' {
                     value_as_string.push(ch);
-                    pos += 1;
-                    mode = Mode::Mantissa;
-                } else if !delimiters.is_empty() && is_delimiter.is_match(char) {
+                    cursor.advance(ch);
+                    mode = Mode::MantissaFirstDigit;
+                } else if ch == 'e' || ch == 'E' {
+                    mode = Mode::Exponent;
+                } else if ch.is_ascii_digit() {
+                    // Only reachable after a leading '0'.
+                    return Err(ParseError::at(ErrorKind::LeadingZero, cursor.pos()));
+                } else {
                     mode = Mode::End;
+                }
+            }
+            Mode::MantissaFirstDigit => {
+                if ch.is_ascii_digit() {
+                    value_as_string.push(ch);
+                    cursor.advance(ch);
+                    mode = Mode::Mantissa;
+                } else if json5 && !leading_point {
+                    // JSON5 allows `1.`; drop the point to keep valid JSON.
+                    value_as_string.pop();
+                    mode = Mode::Mantissa;
                 } else {
-                    mode = Mode::Exponent;
+                    return Err(ParseError::at(
+                        ErrorKind::MissingFractionDigits,
+                        cursor.pos(),
+                    ));
                 }
             }
             Mode::Mantissa => {
-                if DIGIT.is_match(char) {
+                if ch.is_ascii_digit() {
                     value_as_string.push(ch);
-                    pos += 1;
+                    cursor.advance(ch);
                 } else if ch == 'e' || ch == 'E' {
                     mode = Mode::Exponent;
-                } else if !delimiters.is_empty() && is_delimiter.is_match(char) {
-                    mode = Mode::End;
                 } else {
-                    return Err("Unexpected character");
+                    mode = Mode::End;
                 }
             }
             Mode::Exponent => {
-                if ch == 'e' || ch == 'E' {
-                    value_as_string.push('e');
-                    pos += 1;
-                    mode = Mode::ExponentSign;
-                } else {
-                    return Err("Expected 'e' or 'E'");
-                }
+                value_as_string.push('e');
+                cursor.advance(ch);
+                mode = Mode::ExponentSign;
             }
             Mode::ExponentSign => {
                 if ch == '+' || ch == '-' {
                     value_as_string.push(ch);
-                    pos += 1;
+                    cursor.advance(ch);
                     mode = Mode::ExponentFirstDigit;
                 } else {
                     mode = Mode::ExponentFirstDigit;
                 }
             }
             Mode::ExponentFirstDigit => {
-                if DIGIT.is_match(char) {
+                if ch.is_ascii_digit() {
                     value_as_string.push(ch);
-                    pos += 1;
+                    cursor.advance(ch);
                     mode = Mode::ExponentDigits;
                 } else {
-                    return Err("Expected digit");
+                    return Err(ParseError::at(
+                        ErrorKind::MissingExponentDigits,
+                        cursor.pos(),
+                    ));
                 }
             }
             Mode::ExponentDigits => {
-                if DIGIT.is_match(char) {
+                if ch.is_ascii_digit() {
                     value_as_string.push(ch);
-                    pos += 1;
-                } else if !delimiters.is_empty() && is_delimiter.is_match(char) {
-                    mode = Mode::End;
+                    cursor.advance(ch);
                 } else {
-                    return Err("Expected digit");
+                    mode = Mode::End;
+                }
+            }
+            Mode::End => {
+                // Anything that looks like the number continuing is reported
+                // here rather than as a confusing delimiter error by the caller.
+                if ch.is_alphanumeric() || ch == '.' || ch == '+' || ch == '-' {
+                    return Err(ParseError::at(ErrorKind::InvalidNumberSuffix, cursor.pos()));
                 }
+                break;
             }
-            Mode::End => break,
         }
     }
 
     match mode {
-        Mode::Characteristic | Mode::ExponentFirstDigit | Mode::ExponentSign => {
-            return Err("Incomplete expression");
+        Mode::Scanning | Mode::Characteristic => {
+            return Err(ParseError::at(
+                ErrorKind::MissingIntegerDigits,
+                cursor.pos(),
+            ));
+        }
+        Mode::Hex if hex_digits.is_empty() => {
+            return Err(ParseError::at(
+                ErrorKind::Expected("hexadecimal digit"),
+                cursor.pos(),
+            ));
+        }
+        Mode::MantissaFirstDigit if json5 && !leading_point => {
+            value_as_string.pop();
+        }
+        Mode::MantissaFirstDigit => {
+            return Err(ParseError::at(
+                ErrorKind::MissingFractionDigits,
+                cursor.pos(),
+            ));
+        }
+        Mode::ExponentSign | Mode::ExponentFirstDigit => {
+            return Err(ParseError::at(
+                ErrorKind::MissingExponentDigits,
+                cursor.pos(),
+            ));
         }
         _ => {}
     }
 
+    if !hex_digits.is_empty() {
+        value_as_string.push_str(&hex_to_decimal(&hex_digits));
+    }
+
     Ok(ValueToken::NumberToken {
-        skip: pos,
+        skip: cursor.chars() - start,
+        span: Span {
+            start: span_start,
+            end: cursor.position(),
+        },
         token: Number {
             value: value_as_string.parse::<f64>().unwrap(),
             value_as_string,
@@ -144,10 +231,285 @@ pub fn parse_number(number: &str, delimiters: &str) -> Result<ValueToken, &'stat
     })
 }
 
+/// Decimal spelling of a JSON5 hexadecimal literal. Values beyond `u128`
+/// fall back to the nearest `f64`.
+fn hex_to_decimal(digits: &str) -> String {
+    match u128::from_str_radix(digits, 16) {
+        Ok(value) => value.to_string(),
+        Err(_) => {
+            let value = digits
+                .chars()
+                .filter_map(|digit| digit.to_digit(16))
+                .fold(0.0, |value: f64, digit| value * 16.0 + f64::from(digit));
+            format!("{:e}", value)
+        }
+    }
+}
+
+/// How a number literal can be held without losing anything, from the
+/// text kept in [`Number::value_as_string`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NumberKind {
+    /// An integer that fits `i64`.
+    I64,
+    /// An integer above `i64::MAX` that fits `u64`.
+    U64,
+    /// A number with a fraction or exponent that `f64` holds as written, or
+    /// JSON5 `Infinity` or `NaN`.
+    F64,
+    /// Anything else: an integer beyond `u64`, more significant digits than
+    /// `f64` keeps, or a magnitude that overflows `f64` to infinity or
+    /// underflows it to zero. [`Number::as_decimal`] has the exact value.
+    Decimal,
+}
+
+impl Number {
+    pub fn kind(&self) -> NumberKind {
+        if self.as_i64().is_some() {
+            NumberKind::I64
+        } else if self.as_u64().is_some() {
+            NumberKind::U64
+        } else if !self.is_finite_literal() {
+            NumberKind::F64
+        } else if !self.value_as_string.contains(['.', 'e']) {
+            NumberKind::Decimal
+        } else {
+            // `{:e}` is the shortest spelling that reads back as the same
+            // `f64`, so it matches the literal exactly when nothing was lost.
+            let exact = Decimal::parse(&self.value_as_string);
+            let held = Decimal::parse(&format!("{:e}", self.value));
+            if self.value.is_finite() && exact.is_some() && exact == held {
+                NumberKind::F64
+            } else {
+                NumberKind::Decimal
+            }
+        }
+    }
+
+    /// The value of an integer literal that fits `i64`.
+    pub fn as_i64(&self) -> Option<i64> {
+        self.value_as_string.parse().ok()
+    }
+
+    /// The value of a non-negative integer literal that fits `u64`.
+    pub fn as_u64(&self) -> Option<u64> {
+        self.value_as_string.parse().ok()
+    }
+
+    /// The value as the nearest `f64`, or `None` if the literal is too large
+    /// for `f64` and became infinite.
+    pub fn as_f64(&self) -> Option<f64> {
+        (!self.is_overflow()).then_some(self.value)
+    }
+
+    /// The exact value as JSON number text, for an arbitrary-precision
+    /// decimal type. The text is normalized, so equal values give equal
+    /// text: `1`, `1.0` and `10e-1` are all `1`, and `1e400` is `1e400`.
+    /// `None` for JSON5 `Infinity` and `NaN`, or an exponent beyond `i64`.
+    pub fn as_decimal(&self) -> Option<String> {
+        Decimal::parse(&self.value_as_string).map(|decimal| decimal.to_string())
+    }
+
+    /// Whether a finite literal, such as `1e400`, overflowed `f64` to
+    /// infinity.
+    pub fn is_overflow(&self) -> bool {
+        self.value.is_infinite() && self.is_finite_literal()
+    }
+
+    /// Every JSON number ends in a digit; `Infinity` and `NaN` do not.
+    fn is_finite_literal(&self) -> bool {
+        self.value_as_string.ends_with(|c: char| c.is_ascii_digit())
+    }
+}
+
+/// Whether two numbers have the same value. With the `arbitrary_precision`
+/// feature the literals are compared exactly, so integers beyond 2^53 that
+/// round to the same `f64` are still told apart.
+pub(crate) fn same_value(left: &Number, right: &Number) -> bool {
+    compare(left, right) == Some(Ordering::Equal)
+}
+
+/// How two numbers are ordered, exactly with the `arbitrary_precision`
+/// feature and as `f64` otherwise. `None` if either is `NaN`.
+pub(crate) fn compare(left: &Number, right: &Number) -> Option<Ordering> {
+    if cfg!(feature = "arbitrary_precision")
+        && let (Some(left), Some(right)) = (
+            Decimal::parse(&left.value_as_string),
+            Decimal::parse(&right.value_as_string),
+        )
+    {
+        return Some(left.cmp(&right));
+    }
+    left.value.partial_cmp(&right.value)
+}
+
+/// Whether `value` is an integer multiple of `divisor`, computed exactly on
+/// the literals so that neither large values nor near misses slip through.
+/// A divisor that is not positive, or has more significant digits than a
+/// `u64` holds, accepts everything.
+pub(crate) fn is_multiple(value: &Number, divisor: &Number) -> bool {
+    let (Some(value), Some(divisor)) = (
+        Decimal::parse(&value.value_as_string),
+        Decimal::parse(&divisor.value_as_string),
+    ) else {
+        return false;
+    };
+    let Ok(modulus) = divisor.digits.parse::<u64>() else {
+        return true;
+    };
+    if divisor.negative || modulus == 0 {
+        return true;
+    }
+    if value.digits.is_empty() {
+        return true;
+    }
+    // value / divisor = (V × 10^a) / (D × 10^b) for the integers V and D
+    // of their significant digits.
+    let shift = (value.exponent as i128 - value.digits.len() as i128)
+        - (divisor.exponent as i128 - divisor.digits.len() as i128);
+    if shift < 0 {
+        // V has no trailing zeros, so it is not a multiple of D × 10^-shift.
+        return false;
+    }
+    let modulus = modulus as u128;
+    let remainder = value.digits.bytes().fold(0, |remainder, digit| {
+        (remainder * 10 + (digit - b'0') as u128) % modulus
+    });
+    let (mut power, mut base, mut shift) = (1 % modulus, 10 % modulus, shift as u128);
+    while shift > 0 {
+        if shift & 1 == 1 {
+            power = power * base % modulus;
+        }
+        base = base * base % modulus;
+        shift >>= 1;
+    }
+    remainder * power % modulus == 0
+}
+
+/// Whether a number is an integer by its literal, so `1.0` and `1e400` are
+/// while JSON5 `Infinity` is not.
+pub(crate) fn is_integer(number: &Number) -> bool {
+    match Decimal::parse(&number.value_as_string) {
+        Some(decimal) => decimal.exponent >= decimal.digits.len() as i64,
+        None => false,
+    }
+}
+
+/// An exact decimal `±0.digits × 10^exponent`, normalized so that equal
+/// values are equal.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct Decimal {
+    negative: bool,
+    /// Significant digits without leading or trailing zeros; empty for zero.
+    digits: String,
+    exponent: i64,
+}
+
+impl Decimal {
+    /// Read JSON number text, as kept in [`Number::value_as_string`].
+    /// `None` for `Infinity`, `NaN`, or an exponent beyond `i64`.
+    pub(crate) fn parse(text: &str) -> Option<Decimal> {
+        let (negative, text) = match text.strip_prefix('-') {
+            Some(text) => (true, text),
+            None => (false, text),
+        };
+        let (mantissa, exponent) = match text.split_once(['e', 'E']) {
+            Some((mantissa, exponent)) => (mantissa, exponent.parse::<i64>().ok()?),
+            None => (text, 0),
+        };
+        let (integer, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+        let all_digits = format!("{}{}", integer, fraction);
+        if all_digits.is_empty() || !all_digits.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        let leading = all_digits.len() - all_digits.trim_start_matches('0').len();
+        let digits = all_digits.trim_matches('0').to_string();
+        if digits.is_empty() {
+            return Some(Decimal {
+                negative: false,
+                digits,
+                exponent: 0,
+            });
+        }
+        let exponent = exponent.checked_add((integer.len() as i64) - (leading as i64))?;
+        Some(Decimal {
+            negative,
+            digits,
+            exponent,
+        })
+    }
+}
+
+impl Ord for Decimal {
+    fn cmp(&self, other: &Decimal) -> Ordering {
+        let sign = |decimal: &Decimal| match (decimal.negative, decimal.digits.is_empty()) {
+            (_, true) => 0,
+            (true, false) => -1,
+            (false, false) => 1,
+        };
+        let magnitude = || {
+            // Both nonzero with the same sign: a larger exponent is a larger
+            // magnitude, and with equal exponents the digits compare as text
+            // since they are aligned at the decimal point.
+            self.exponent
+                .cmp(&other.exponent)
+                .then_with(|| self.digits.cmp(&other.digits))
+        };
+        match sign(self).cmp(&sign(other)) {
+            Ordering::Equal if self.digits.is_empty() => Ordering::Equal,
+            Ordering::Equal if self.negative => magnitude().reverse(),
+            Ordering::Equal => magnitude(),
+            ordering => ordering,
+        }
+    }
+}
+
+impl PartialOrd for Decimal {
+    fn partial_cmp(&self, other: &Decimal) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Normalized JSON number text, laid out as JavaScript's `Number#toString`
+/// lays out a double: plain for magnitudes from 1e-6 up to 1e21, with an
+/// exponent otherwise.
+impl fmt::Display for Decimal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.digits.is_empty() {
+            return f.write_str("0");
+        }
+        if self.negative {
+            f.write_str("-")?;
+        }
+        let digits = &self.digits;
+        let point = self.exponent;
+        let zeros = |count: i64| "0".repeat(count as usize);
+        if (digits.len() as i64) <= point && point <= 21 {
+            write!(f, "{}{}", digits, zeros(point - digits.len() as i64))
+        } else if 0 < point && point <= 21 {
+            let (integer, fraction) = digits.split_at(point as usize);
+            write!(f, "{}.{}", integer, fraction)
+        } else if -6 < point && point <= 0 {
+            write!(f, "0.{}{}", zeros(-point), digits)
+        } else {
+            let (first, rest) = digits.split_at(1);
+            f.write_str(first)?;
+            if !rest.is_empty() {
+                write!(f, ".{}", rest)?;
+            }
+            write!(f, "e{}", point - 1)
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::json::parse;
-    use crate::types::{Json, ValueToken};
+    use super::{NumberKind, compare, is_integer, is_multiple, same_value};
+    use crate::error::ErrorKind;
+    use crate::json::{parse, parse_with_options};
+    use crate::options::{Dialect, ParseOptions};
+    use crate::types::{Json, Number, ValueToken};
+    use std::cmp::Ordering;
 
     #[test]
     fn test_zero() {
@@ -157,11 +519,11 @@ mod tests {
         let expected_value = 0.0;
         let expected_value_as_string = "0";
         match parse(input) {
-            Ok(Json { skip, token }) => {
+            Ok(Json { skip, token, .. }) => {
                 assert_eq!(expected_json_skip, skip);
                 let unboxed = *token;
                 match unboxed {
-                    ValueToken::NumberToken { skip, token } => {
+                    ValueToken::NumberToken { skip, token, .. } => {
                         assert_eq!(expected_token_skip, skip);
                         let epsilon = 1e-10;
                         assert!((expected_value - token.value).abs() < epsilon);
@@ -186,11 +548,11 @@ mod tests {
         let expected_value = -1.0;
         let expected_value_as_string = "-1";
         match parse(input) {
-            Ok(Json { skip, token }) => {
+            Ok(Json { skip, token, .. }) => {
                 assert_eq!(expected_json_skip, skip);
                 let unboxed = *token;
                 match unboxed {
-                    ValueToken::NumberToken { skip, token } => {
+                    ValueToken::NumberToken { skip, token, .. } => {
                         assert_eq!(expected_token_skip, skip);
                         let epsilon = 1e-10;
                         assert!((expected_value - token.value).abs() < epsilon);
@@ -215,11 +577,11 @@ mod tests {
         let expected_value = 1.0;
         let expected_value_as_string = "1";
         match parse(input) {
-            Ok(Json { skip, token }) => {
+            Ok(Json { skip, token, .. }) => {
                 assert_eq!(expected_json_skip, skip);
                 let unboxed = *token;
                 match unboxed {
-                    ValueToken::NumberToken { skip, token } => {
+                    ValueToken::NumberToken { skip, token, .. } => {
                         assert_eq!(expected_token_skip, skip);
                         let epsilon = 1e-10;
                         assert!((expected_value - token.value).abs() < epsilon);
@@ -244,11 +606,11 @@ mod tests {
         let expected_value = 1200.0;
         let expected_value_as_string = "1.2e3";
         match parse(input) {
-            Ok(Json { skip, token }) => {
+            Ok(Json { skip, token, .. }) => {
                 assert_eq!(expected_json_skip, skip);
                 let unboxed = *token;
                 match unboxed {
-                    ValueToken::NumberToken { skip, token } => {
+                    ValueToken::NumberToken { skip, token, .. } => {
                         assert_eq!(expected_token_skip, skip);
                         let epsilon = 1e-10;
                         assert!((expected_value - token.value).abs() < epsilon);
@@ -292,18 +654,12 @@ mod tests {
     #[test]
     fn test_decimal_point_no_mantissa() {
         let input = "2.";
-        let expected_value = 2.0;
-        let expected_value_as_string = "2.";
         match parse(input) {
-            Ok(Json { token, .. }) => match *token {
-                ValueToken::NumberToken { token, .. } => {
-                    let epsilon = 1e-10;
-                    assert!((expected_value - token.value).abs() < epsilon);
-                    assert_eq!(expected_value_as_string, token.value_as_string);
-                }
-                _ => panic!("Expected NumberToken"),
-            },
-            Err(e) => panic!("{}", e),
+            Ok(_) => panic!("Should not parse number without fraction digits"),
+            Err(e) => {
+                assert_eq!(ErrorKind::MissingFractionDigits, e.kind);
+                assert_eq!(2, e.offset);
+            }
         }
     }
 
@@ -378,4 +734,405 @@ mod tests {
             Err(e) => panic!("{}", e),
         }
     }
+
+    #[test]
+    fn test_span_excludes_whitespace() {
+        let input = "\n  -1.5e3 ";
+        match parse(input) {
+            Ok(Json { token, .. }) => match *token {
+                ValueToken::NumberToken { skip, span, .. } => {
+                    assert_eq!(6, skip);
+                    assert_eq!((3, 9), (span.start.offset, span.end.offset));
+                    assert_eq!((2, 3), (span.start.line, span.start.column));
+                    assert_eq!((2, 9), (span.end.line, span.end.column));
+                }
+                _ => panic!("Expected NumberToken"),
+            },
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    /// `y_number_*` cases from JSONTestSuite; all must parse.
+    const Y_NUMBER: &[&str] = &[
+        "[123e65]",
+        "[0e+1]",
+        "[0e1]",
+        "[ 4]",
+        "[-0.000000000000000000000000000000000000000000000000000000000000000000000000000001]\n",
+        "[20e1]",
+        "[-0]",
+        "[-123]",
+        "[-1]",
+        "[-0.0e+0]",
+        "[1E22]",
+        "[1E-2]",
+        "[1E+2]",
+        "[123e45]",
+        "[123.456e78]",
+        "[1e-2]",
+        "[1e+2]",
+        "[123]",
+        "[123.456789]",
+        "123",
+        "-0.0",
+        "[1e400]",
+        "[1.5e-400]",
+    ];
+
+    /// `n_number_*` cases from JSONTestSuite (except the invalid UTF-8
+    /// ones, which cannot be expressed as `&str`) with the expected error.
+    const N_NUMBER: &[(&str, ErrorKind)] = &[
+        ("[++1234]", ErrorKind::UnexpectedCharacter),
+        ("[+1]", ErrorKind::UnexpectedCharacter),
+        ("[+Inf]", ErrorKind::UnexpectedCharacter),
+        ("[-01]", ErrorKind::LeadingZero),
+        ("[-1.0.]", ErrorKind::InvalidNumberSuffix),
+        ("[-2.]", ErrorKind::MissingFractionDigits),
+        ("[-NaN]", ErrorKind::MissingIntegerDigits),
+        ("[.-1]", ErrorKind::UnexpectedCharacter),
+        ("[.2e-3]", ErrorKind::UnexpectedCharacter),
+        ("[0.1.2]", ErrorKind::InvalidNumberSuffix),
+        ("[0.3e+]", ErrorKind::MissingExponentDigits),
+        ("[0.3e]", ErrorKind::MissingExponentDigits),
+        ("[0.e1]", ErrorKind::MissingFractionDigits),
+        ("[0E+]", ErrorKind::MissingExponentDigits),
+        ("[0E]", ErrorKind::MissingExponentDigits),
+        ("[0e+]", ErrorKind::MissingExponentDigits),
+        ("[0e]", ErrorKind::MissingExponentDigits),
+        ("[1.0e+]", ErrorKind::MissingExponentDigits),
+        ("[1.0e-]", ErrorKind::MissingExponentDigits),
+        ("[1.0e]", ErrorKind::MissingExponentDigits),
+        ("[1 000.0]", ErrorKind::Expected("',' or ']'")),
+        ("[1eE2]", ErrorKind::MissingExponentDigits),
+        ("[2.e+3]", ErrorKind::MissingFractionDigits),
+        ("[2.e-3]", ErrorKind::MissingFractionDigits),
+        ("[2.e3]", ErrorKind::MissingFractionDigits),
+        ("[9.e+]", ErrorKind::MissingFractionDigits),
+        ("[Inf]", ErrorKind::UnexpectedCharacter),
+        ("[NaN]", ErrorKind::UnexpectedCharacter),
+        ("[\u{ff11}]", ErrorKind::UnexpectedCharacter),
+        ("[1+2]", ErrorKind::InvalidNumberSuffix),
+        ("[0x1]", ErrorKind::InvalidNumberSuffix),
+        ("[0x42]", ErrorKind::InvalidNumberSuffix),
+        ("[Infinity]", ErrorKind::UnexpectedCharacter),
+        ("[0e+-1]", ErrorKind::MissingExponentDigits),
+        ("[-123.123foo]", ErrorKind::InvalidNumberSuffix),
+        ("[-Infinity]", ErrorKind::MissingIntegerDigits),
+        ("[-foo]", ErrorKind::MissingIntegerDigits),
+        ("[- 1]", ErrorKind::MissingIntegerDigits),
+        ("[-012]", ErrorKind::LeadingZero),
+        ("[-.123]", ErrorKind::MissingIntegerDigits),
+        ("[-1x]", ErrorKind::InvalidNumberSuffix),
+        ("[1ea]", ErrorKind::MissingExponentDigits),
+        ("[1.]", ErrorKind::MissingFractionDigits),
+        ("[.123]", ErrorKind::UnexpectedCharacter),
+        ("[1.2a-3]", ErrorKind::InvalidNumberSuffix),
+        ("[1.8011670033376514H-308]", ErrorKind::InvalidNumberSuffix),
+        ("[012]", ErrorKind::LeadingZero),
+        ("-", ErrorKind::MissingIntegerDigits),
+        ("1e", ErrorKind::MissingExponentDigits),
+        ("12}", ErrorKind::TrailingCharacters),
+    ];
+
+    #[test]
+    fn test_json_test_suite_accepted() {
+        for input in Y_NUMBER {
+            if let Err(e) = parse(input) {
+                panic!("{:?}: {}", input, e);
+            }
+        }
+    }
+
+    #[test]
+    fn test_json_test_suite_rejected() {
+        for (input, kind) in N_NUMBER {
+            match parse(input) {
+                Ok(_) => panic!("{:?} should not parse", input),
+                Err(e) => assert_eq!(*kind, e.kind, "{:?}", input),
+            }
+        }
+    }
+
+    #[test]
+    fn test_overflow_is_infinite() {
+        match parse("-1e400") {
+            Ok(Json { token, .. }) => match *token {
+                ValueToken::NumberToken { token, .. } => {
+                    assert_eq!(f64::NEG_INFINITY, token.value);
+                    assert_eq!("-1e400", token.value_as_string);
+                }
+                _ => panic!("Expected NumberToken"),
+            },
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    fn parse_json5(input: &str) -> Result<(f64, String), ErrorKind> {
+        let options = ParseOptions {
+            dialect: Dialect::Json5,
+            ..ParseOptions::default()
+        };
+        match parse_with_options(input, &options) {
+            Ok(Json { token, .. }) => match *token {
+                ValueToken::NumberToken { token, .. } => Ok((token.value, token.value_as_string)),
+                _ => panic!("Expected NumberToken"),
+            },
+            Err(e) => Err(e.kind),
+        }
+    }
+
+    #[test]
+    fn test_json5_numbers() {
+        for (input, value, value_as_string) in [
+            ("0x1F", 31.0, "31"),
+            ("-0XdecAF", -912559.0, "-912559"),
+            ("+1", 1.0, "1"),
+            ("+.5", 0.5, "0.5"),
+            ("-.5e1", -5.0, "-0.5e1"),
+            ("5.", 5.0, "5"),
+            ("5.e2", 500.0, "5e2"),
+            ("Infinity", f64::INFINITY, "Infinity"),
+            ("+Infinity", f64::INFINITY, "Infinity"),
+            ("-Infinity", f64::NEG_INFINITY, "-Infinity"),
+            (
+                "0x100000000000000000000000000000000",
+                2f64.powi(128),
+                "3.402823669209385e38",
+            ),
+        ] {
+            assert_eq!(
+                Ok((value, value_as_string.to_string())),
+                parse_json5(input),
+                "{}",
+                input
+            );
+        }
+        match parse_json5("-NaN") {
+            Ok((value, value_as_string)) => {
+                assert!(value.is_nan());
+                assert_eq!("NaN", value_as_string);
+            }
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    #[test]
+    fn test_json5_numbers_rejected() {
+        for (input, kind) in [
+            ("0x", ErrorKind::Expected("hexadecimal digit")),
+            ("0xg", ErrorKind::Expected("hexadecimal digit")),
+            ("0x1g", ErrorKind::InvalidNumberSuffix),
+            (".", ErrorKind::MissingFractionDigits),
+            (".e1", ErrorKind::MissingFractionDigits),
+            ("++1", ErrorKind::MissingIntegerDigits),
+            ("Inf", ErrorKind::MissingIntegerDigits),
+            ("Infinityx", ErrorKind::InvalidNumberSuffix),
+            ("012", ErrorKind::LeadingZero),
+            ("1..", ErrorKind::InvalidNumberSuffix),
+        ] {
+            assert_eq!(Err(kind), parse_json5(input), "{}", input);
+        }
+    }
+
+    fn number(input: &str) -> Number {
+        match parse(input) {
+            Ok(Json { token, .. }) => match *token {
+                ValueToken::NumberToken { token, .. } => token,
+                _ => panic!("Expected NumberToken"),
+            },
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    #[test]
+    fn test_kinds() {
+        for (input, kind) in [
+            ("0", NumberKind::I64),
+            ("-9223372036854775808", NumberKind::I64),
+            ("9223372036854775808", NumberKind::U64),
+            ("18446744073709551615", NumberKind::U64),
+            ("18446744073709551616", NumberKind::Decimal),
+            ("-9223372036854775809", NumberKind::Decimal),
+            ("1.0", NumberKind::F64),
+            ("0.1", NumberKind::F64),
+            ("-2.5e-3", NumberKind::F64),
+            ("1E2", NumberKind::F64),
+            ("1.7976931348623157e308", NumberKind::F64),
+            ("3.14159265358979323846", NumberKind::Decimal),
+            ("1e400", NumberKind::Decimal),
+            ("1e-400", NumberKind::Decimal),
+        ] {
+            assert_eq!(kind, number(input).kind(), "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_accessors() {
+        let id = number("9007199254740993");
+        assert_eq!(Some(9007199254740993), id.as_i64());
+        assert_eq!(Some(9007199254740993), id.as_u64());
+        assert_eq!(Some(9007199254740992.0), id.as_f64());
+        assert_eq!(Some("9007199254740993"), id.as_decimal().as_deref());
+
+        let negative = number("-1");
+        assert_eq!((Some(-1), None), (negative.as_i64(), negative.as_u64()));
+
+        let fraction = number("2.50");
+        assert_eq!((None, None), (fraction.as_i64(), fraction.as_u64()));
+        assert_eq!(Some(2.5), fraction.as_f64());
+        assert_eq!(Some("2.5"), fraction.as_decimal().as_deref());
+
+        let huge = number("-1e400");
+        assert!(huge.is_overflow());
+        assert_eq!(None, huge.as_f64());
+        assert_eq!(Some("-1e400"), huge.as_decimal().as_deref());
+
+        let options = ParseOptions {
+            dialect: Dialect::Json5,
+            ..ParseOptions::default()
+        };
+        match parse_with_options("-Infinity", &options) {
+            Ok(Json { token, .. }) => match *token {
+                ValueToken::NumberToken { token, .. } => {
+                    assert!(!token.is_overflow());
+                    assert_eq!(Some(f64::NEG_INFINITY), token.as_f64());
+                    assert_eq!(None, token.as_decimal());
+                    assert_eq!(NumberKind::F64, token.kind());
+                }
+                _ => panic!("Expected NumberToken"),
+            },
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    #[test]
+    fn test_same_value() {
+        for (left, right) in [("1", "1.0"), ("0.5", "5e-1"), ("-0", "0"), ("100", "1e2")] {
+            assert!(
+                same_value(&number(left), &number(right)),
+                "{} {}",
+                left,
+                right
+            );
+        }
+        assert!(!same_value(&number("1"), &number("-1")));
+        // Both round to 2^53 as f64.
+        assert_eq!(
+            !cfg!(feature = "arbitrary_precision"),
+            same_value(&number("9007199254740993"), &number("9007199254740992"))
+        );
+    }
+
+    #[test]
+    fn test_compare() {
+        for (left, right) in [
+            ("-2", "-1"),
+            ("-1", "0"),
+            ("-0.5", "0"),
+            ("0.12", "0.123"),
+            ("0.123", "0.13"),
+            ("9.9", "10"),
+            ("1e2", "101"),
+        ] {
+            assert_eq!(
+                Some(Ordering::Less),
+                compare(&number(left), &number(right)),
+                "{} {}",
+                left,
+                right
+            );
+            assert_eq!(
+                Some(Ordering::Greater),
+                compare(&number(right), &number(left))
+            );
+        }
+        // Each pair is the same `f64`.
+        for (left, right) in [
+            ("9007199254740992", "9007199254740993"),
+            ("0", "1e-400"),
+            ("-1e400", "-1e399"),
+        ] {
+            assert_eq!(
+                Some(if cfg!(feature = "arbitrary_precision") {
+                    Ordering::Less
+                } else {
+                    Ordering::Equal
+                }),
+                compare(&number(left), &number(right)),
+                "{} {}",
+                left,
+                right
+            );
+        }
+    }
+
+    #[test]
+    fn test_as_decimal_is_normalized() {
+        for (text, decimal) in [
+            ("1", "1"),
+            ("1.0", "1"),
+            ("1e0", "1"),
+            ("10e-1", "1"),
+            ("-0.0", "0"),
+            ("1200", "1200"),
+            ("12e2", "1200"),
+            ("0.000001", "0.000001"),
+            ("1E-7", "1e-7"),
+            ("-1.50e1", "-15"),
+            ("123456789012345678901", "123456789012345678901"),
+            ("1e21", "1e21"),
+            ("1.5e400", "1.5e400"),
+        ] {
+            assert_eq!(
+                Some(decimal),
+                number(text).as_decimal().as_deref(),
+                "{}",
+                text
+            );
+        }
+    }
+
+    #[test]
+    fn test_is_multiple() {
+        for (value, divisor, multiple) in [
+            ("0", "1.5", true),
+            ("4.5", "1.5", true),
+            ("35", "1.5", false),
+            ("0.0075", "0.0001", true),
+            ("0.00751", "0.0001", false),
+            ("0.3", "0.1", true),
+            ("0.3000000001", "0.1", false),
+            ("9007199254740993", "2", false),
+            ("9007199254740994", "2", true),
+            ("1e300", "7", false),
+            ("7e300", "7", true),
+            ("1e308", "0.123456789", false),
+            ("-12", "4", true),
+            ("12", "0", true),
+        ] {
+            assert_eq!(
+                multiple,
+                is_multiple(&number(value), &number(divisor)),
+                "{} {}",
+                value,
+                divisor
+            );
+        }
+    }
+
+    #[test]
+    fn test_is_integer() {
+        for (text, integer) in [
+            ("1", true),
+            ("-0", true),
+            ("1.0", true),
+            ("1.5e1", true),
+            ("1e400", true),
+            ("9007199254740993", true),
+            ("1.5", false),
+            ("1e-400", false),
+        ] {
+            assert_eq!(integer, is_integer(&number(text)), "{}", text);
+        }
+    }
 }
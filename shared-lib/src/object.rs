@@ -1,6 +1,8 @@
+use crate::cursor::Cursor;
+use crate::error::{ErrorKind, ParseError, PathSegment};
+use crate::options::DuplicateKeys;
 use crate::pair::parse_pair;
-use crate::types::{Object, Pair, ValueToken};
-use regex::Regex;
+use crate::types::{Object, Span, ValueToken};
 
 enum Mode {
     Scanning,
@@ -9,83 +11,136 @@ enum Mode {
     End,
 }
 
-lazy_static! {
-    static ref WHITESPACE: Regex = Regex::new(r"[ \n\r\t]").unwrap();
-}
-
-pub fn parse_object(object: &str) -> Result<ValueToken, &'static str> {
+pub fn parse_object(cursor: &mut Cursor) -> Result<ValueToken, ParseError> {
     let mut mode = Mode::Scanning;
-    let mut pos: usize = 0;
-    let mut members: Vec<Pair> = Vec::new();
-
-    while let Some(ch) = object.chars().nth(pos) {
-        let char = &ch.to_string()[..];
+    let start = cursor.chars();
+    let mut span_start = cursor.position();
+    let mut members = Object::new();
 
+    while let Some(ch) = cursor.peek() {
         match mode {
             Mode::Scanning => {
-                if WHITESPACE.is_match(char) {
-                    pos += 1;
+                if cursor.is_trivia(ch) {
+                    cursor.skip_trivia(ch)?;
                 } else if ch == '{' {
-                    pos += 1;
+                    span_start = cursor.position();
+                    cursor.enter()?;
+                    cursor.advance(ch);
                     mode = Mode::Pair;
                 } else {
-                    return Err("Expected '{'");
+                    return Err(ParseError::at(ErrorKind::Expected("'{'"), cursor.pos()));
                 }
             }
             Mode::Pair => {
-                if WHITESPACE.is_match(char) {
-                    pos += 1;
+                if cursor.is_trivia(ch) {
+                    cursor.skip_trivia(ch)?;
                 } else if ch == '}' {
-                    if !members.is_empty() {
-                        return Err("Unexpected ','");
+                    if !members.is_empty() && !cursor.dialect().allows_trailing_commas() {
+                        return Err(ParseError::at(ErrorKind::UnexpectedComma, cursor.pos()));
                     }
-                    pos += 1;
+                    cursor.advance(ch);
                     mode = Mode::End;
                 } else {
-                    let slice: String = object.chars().skip(pos).collect();
-                    match parse_pair(&slice) {
-                        // TODO r"[\s,\}]"
-                        Ok(ValueToken::PairToken { skip, token }) => {
-                            members.push(token);
-                            pos += skip;
+                    let pos = cursor.pos();
+                    match parse_pair(cursor)? {
+                        ValueToken::PairToken { token, .. } => {
+                            if !members.contains_key(&token.key) {
+                                members.push(token);
+                            } else {
+                                match cursor.options().duplicate_keys {
+                                    DuplicateKeys::Allow => members.push(token),
+                                    DuplicateKeys::FirstWins => {}
+                                    DuplicateKeys::LastWins => {
+                                        members.insert(token.key, token.value);
+                                    }
+                                    DuplicateKeys::Error => {
+                                        return Err(ParseError::at(ErrorKind::DuplicateKey, pos)
+                                            .within(PathSegment::Key(token.key)));
+                                    }
+                                }
+                            }
                             mode = Mode::Delimiter;
                         }
-                        Ok(_) => {
-                            return Err("Expected key-value pair");
-                        }
-                        Err(e) => {
-                            return Err(e);
+                        _ => {
+                            return Err(ParseError::at(ErrorKind::Expected("key-value pair"), pos));
                         }
                     }
                 }
             }
             Mode::Delimiter => {
-                if WHITESPACE.is_match(char) {
-                    pos += 1;
+                if cursor.is_trivia(ch) {
+                    cursor.skip_trivia(ch)?;
                 } else if ch == ',' {
-                    pos += 1;
+                    cursor.advance(ch);
                     mode = Mode::Pair;
                 } else if ch == '}' {
-                    pos += 1;
+                    cursor.advance(ch);
                     mode = Mode::End;
                 } else {
-                    return Err("Expected ',' or '}'");
+                    return Err(ParseError::at(
+                        ErrorKind::Expected("',' or '}'"),
+                        cursor.pos(),
+                    ));
                 }
             }
             Mode::End => break,
         }
     }
 
+    match mode {
+        Mode::End => {}
+        Mode::Scanning => {
+            return Err(ParseError::at(ErrorKind::Expected("'{'"), cursor.pos()));
+        }
+        Mode::Pair => {
+            return Err(ParseError::at(ErrorKind::Expected("'\"'"), cursor.pos()));
+        }
+        Mode::Delimiter => {
+            return Err(ParseError::at(
+                ErrorKind::Expected("',' or '}'"),
+                cursor.pos(),
+            ));
+        }
+    }
+
+    cursor.leave();
     Ok(ValueToken::ObjectToken {
-        skip: pos,
-        token: Object { members },
+        skip: cursor.chars() - start,
+        span: Span {
+            start: span_start,
+            end: cursor.position(),
+        },
+        token: members,
     })
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::error::{ErrorKind, PathSegment};
     use crate::json;
-    use crate::types::{Json, ValueToken};
+    use crate::options::{Dialect, DuplicateKeys, ParseOptions};
+    use crate::types::{Json, Object, Pair, Span, ValueToken};
+
+    fn parse_object_with(input: &str, duplicate_keys: DuplicateKeys) -> Object {
+        let options = ParseOptions {
+            duplicate_keys,
+            ..ParseOptions::default()
+        };
+        match json::parse_with_options(input, &options) {
+            Ok(Json { token, .. }) => match *token {
+                ValueToken::ObjectToken { token, .. } => token,
+                _ => panic!("Expected ObjectToken"),
+            },
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    fn number(token: Option<&ValueToken>) -> f64 {
+        match token {
+            Some(ValueToken::NumberToken { token, .. }) => token.value,
+            _ => panic!("Expected NumberToken"),
+        }
+    }
 
     #[test]
     fn test_empty_object() {
@@ -94,14 +149,14 @@ mod tests {
         let expected_token_skip = 2;
         let expected_members_len = 0;
         match json::parse(input) {
-            Ok(Json { skip, token }) => {
+            Ok(Json { skip, token, .. }) => {
                 assert_eq!(expected_json_skip, skip);
 
                 let unboxed = *token;
                 match unboxed {
-                    ValueToken::ObjectToken { skip, token } => {
+                    ValueToken::ObjectToken { skip, token, .. } => {
                         assert_eq!(expected_token_skip, skip);
-                        assert_eq!(expected_members_len, token.members.len());
+                        assert_eq!(expected_members_len, token.len());
                     }
                     _ => {
                         panic!("Expected ObjectToken");
@@ -121,14 +176,14 @@ mod tests {
         let expected_token_skip = 3;
         let expected_members_len = 0;
         match json::parse(input) {
-            Ok(Json { skip, token }) => {
+            Ok(Json { skip, token, .. }) => {
                 assert_eq!(expected_json_skip, skip);
 
                 let unboxed = *token;
                 match unboxed {
-                    ValueToken::ObjectToken { skip, token } => {
+                    ValueToken::ObjectToken { skip, token, .. } => {
                         assert_eq!(expected_token_skip, skip);
-                        assert_eq!(expected_members_len, token.members.len());
+                        assert_eq!(expected_members_len, token.len());
                     }
                     _ => {
                         panic!("Expected ObjectToken");
@@ -148,7 +203,6 @@ mod tests {
             Ok(Json { token, .. }) => match *token {
                 ValueToken::ObjectToken { ref token, .. } => {
                     let arr_pair = token
-                        .members
                         .iter()
                         .find(|p| p.key == "arr")
                         .expect("Missing 'arr' key");
@@ -186,7 +240,6 @@ mod tests {
             Ok(Json { token, .. }) => match *token {
                 ValueToken::ObjectToken { ref token, .. } => {
                     let pair = token
-                        .members
                         .iter()
                         .find(|p| p.key == "f")
                         .expect("Missing 'f' key");
@@ -205,7 +258,6 @@ mod tests {
             Ok(Json { token, .. }) => match *token {
                 ValueToken::ObjectToken { ref token, .. } => {
                     let pair = token
-                        .members
                         .iter()
                         .find(|p| p.key == "t")
                         .expect("Missing 't' key");
@@ -224,7 +276,6 @@ mod tests {
             Ok(Json { token, .. }) => match *token {
                 ValueToken::ObjectToken { ref token, .. } => {
                     let pair = token
-                        .members
                         .iter()
                         .find(|p| p.key == "n")
                         .expect("Missing 'n' key");
@@ -243,7 +294,6 @@ mod tests {
             Ok(Json { token, .. }) => match *token {
                 ValueToken::ObjectToken { ref token, .. } => {
                     let pair = token
-                        .members
                         .iter()
                         .find(|p| p.key == "a")
                         .expect("Missing 'a' key");
@@ -262,7 +312,6 @@ mod tests {
             Ok(Json { token, .. }) => match *token {
                 ValueToken::ObjectToken { ref token, .. } => {
                     let pair = token
-                        .members
                         .iter()
                         .find(|p| p.key == "o")
                         .expect("Missing 'o' key");
@@ -281,7 +330,6 @@ mod tests {
             Ok(Json { token, .. }) => match *token {
                 ValueToken::ObjectToken { ref token, .. } => {
                     let pair = token
-                        .members
                         .iter()
                         .find(|p| p.key == "s")
                         .expect("Missing 's' key");
@@ -300,7 +348,6 @@ mod tests {
             Ok(Json { token, .. }) => match *token {
                 ValueToken::ObjectToken { ref token, .. } => {
                     let pair = token
-                        .members
                         .iter()
                         .find(|p| p.key == "n")
                         .expect("Missing 'n' key");
@@ -311,4 +358,217 @@ mod tests {
             Err(e) => panic!("{}", e),
         }
     }
+
+    #[test]
+    fn object_too_deep() {
+        let input = r#"{"a":"#.repeat(200_000);
+        match json::parse(&input) {
+            Ok(_) => panic!("Expected error"),
+            Err(e) => {
+                assert_eq!(ErrorKind::NestingTooDeep, e.kind);
+                assert_eq!(128 * 5, e.offset);
+            }
+        }
+    }
+
+    #[test]
+    fn object_and_array_too_deep() {
+        let input = r#"{"a":["#.repeat(100);
+        match json::parse(&input) {
+            Ok(_) => panic!("Expected error"),
+            Err(e) => assert_eq!(ErrorKind::NestingTooDeep, e.kind),
+        }
+    }
+
+    #[test]
+    fn object_lookup_by_key() {
+        let object = parse_object_with(r#"{"a":1,"b":2,"c":3}"#, DuplicateKeys::Allow);
+        assert_eq!(2.0, number(object.get("b")));
+        assert_eq!(Some(2), object.position("c"));
+        assert!(object.get("d").is_none());
+        let keys: Vec<&str> = object.iter().map(|pair| pair.key.as_str()).collect();
+        assert_eq!(vec!["a", "b", "c"], keys);
+    }
+
+    #[test]
+    fn object_duplicate_keys_allow() {
+        let object = parse_object_with(r#"{"a":1,"b":2,"a":3}"#, DuplicateKeys::Allow);
+        assert_eq!(3, object.len());
+        assert_eq!(3.0, number(object.get("a")));
+    }
+
+    #[test]
+    fn object_duplicate_keys_first_wins() {
+        let object = parse_object_with(r#"{"a":1,"b":2,"a":3}"#, DuplicateKeys::FirstWins);
+        assert_eq!(2, object.len());
+        assert_eq!(1.0, number(object.get("a")));
+    }
+
+    #[test]
+    fn object_duplicate_keys_last_wins() {
+        let object = parse_object_with(r#"{"a":1,"b":2,"a":3}"#, DuplicateKeys::LastWins);
+        assert_eq!(2, object.len());
+        assert_eq!("a", object.members()[0].key);
+        assert_eq!(3.0, number(object.get("a")));
+    }
+
+    #[test]
+    fn object_duplicate_keys_error() {
+        let options = ParseOptions {
+            duplicate_keys: DuplicateKeys::Error,
+            ..ParseOptions::default()
+        };
+        match json::parse_with_options(r#"{"o":{"a":1, "a":2}}"#, &options) {
+            Ok(_) => panic!("Expected error"),
+            Err(e) => {
+                assert_eq!(ErrorKind::DuplicateKey, e.kind);
+                assert_eq!(13, e.offset);
+                assert_eq!(
+                    vec![
+                        PathSegment::Key("o".to_string()),
+                        PathSegment::Key("a".to_string())
+                    ],
+                    e.path
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn object_insert_and_remove() {
+        let mut object = Object::new();
+        object.push(Pair::new(
+            "a".to_string(),
+            Box::new(ValueToken::NullToken {
+                skip: 0,
+                span: Span::default(),
+            }),
+        ));
+        assert!(
+            object
+                .insert(
+                    "b".to_string(),
+                    Box::new(ValueToken::NullToken {
+                        skip: 0,
+                        span: Span::default(),
+                    })
+                )
+                .is_none()
+        );
+        assert!(
+            object
+                .insert(
+                    "a".to_string(),
+                    Box::new(ValueToken::TrueToken {
+                        skip: 0,
+                        span: Span::default(),
+                        token: true
+                    })
+                )
+                .is_some()
+        );
+        assert_eq!(2, object.len());
+        assert!(matches!(
+            object.get("a"),
+            Some(ValueToken::TrueToken { .. })
+        ));
+
+        assert!(object.remove("a").is_some());
+        assert!(object.remove("a").is_none());
+        assert_eq!(Some(0), object.position("b"));
+    }
+
+    #[test]
+    fn object_member_spans() {
+        let input = "{\n  \"key\" : {\"x\": true}\n}";
+        match json::parse(input) {
+            Ok(Json { token, .. }) => match *token {
+                ValueToken::ObjectToken { span, token, .. } => {
+                    assert_eq!((0, input.len()), (span.start.offset, span.end.offset));
+                    let pair = &token.members()[0];
+                    assert_eq!(
+                        "\"key\"",
+                        &input[pair.key_span.start.offset..pair.key_span.end.offset]
+                    );
+                    assert_eq!(
+                        (2, 3),
+                        (pair.key_span.start.line, pair.key_span.start.column)
+                    );
+                    match &*pair.value {
+                        ValueToken::ObjectToken { span, token, .. } => {
+                            assert_eq!(
+                                r#"{"x": true}"#,
+                                &input[span.start.offset..span.end.offset]
+                            );
+                            match token.get("x") {
+                                Some(ValueToken::TrueToken { span, .. }) => {
+                                    assert_eq!("true", &input[span.start.offset..span.end.offset]);
+                                    assert_eq!(2, span.start.line);
+                                }
+                                _ => panic!("Expected TrueToken"),
+                            }
+                        }
+                        _ => panic!("Expected ObjectToken"),
+                    }
+                }
+                _ => panic!("Expected ObjectToken"),
+            },
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    #[test]
+    fn json5_keys_and_trailing_comma() {
+        let options = ParseOptions {
+            dialect: Dialect::Json5,
+            ..ParseOptions::default()
+        };
+        let input = "{ $id: 1, _x9: 2, 'q': 3, caf\u{e9}: 4, }";
+        match json::parse_with_options(input, &options) {
+            Ok(Json { token, .. }) => match *token {
+                ValueToken::ObjectToken { token, .. } => {
+                    let keys: Vec<&str> = token.iter().map(|pair| pair.key.as_str()).collect();
+                    assert_eq!(vec!["$id", "_x9", "q", "caf\u{e9}"], keys);
+                    let span = token.members()[1].key_span;
+                    assert_eq!((10, 13), (span.start.offset, span.end.offset));
+                }
+                _ => panic!("Expected ObjectToken"),
+            },
+            Err(e) => panic!("{}", e),
+        }
+        for input in ["{9a: 1}", "{a-b: 1}", "{,}"] {
+            assert!(
+                json::parse_with_options(input, &options).is_err(),
+                "{:?} should not parse",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn object_truncated() {
+        for (input, kind, offset) in [
+            ("{", ErrorKind::Expected("'\"'"), 1),
+            ("{\"a\"", ErrorKind::Expected("':'"), 4),
+            ("{\"a\":", ErrorKind::Expected("value"), 5),
+            ("{\"a\":1", ErrorKind::Expected("',' or '}'"), 6),
+            ("{\"a\":1, ", ErrorKind::Expected("'\"'"), 8),
+            ("{\"a\":{}", ErrorKind::Expected("',' or '}'"), 7),
+        ] {
+            match json::parse(input) {
+                Ok(json) => panic!("{:?} parsed as {:?}", input, json),
+                Err(e) => assert_eq!((kind, offset), (e.kind, e.offset), "{:?}", input),
+            }
+        }
+        match json::parse("{\"o\": {\"a\": ") {
+            Ok(_) => panic!("Expected error"),
+            Err(e) => assert_eq!(
+                vec![
+                    PathSegment::Key("o".to_string()),
+                    PathSegment::Key("a".to_string())
+                ],
+                e.path
+            ),
+        }
+    }
 }
@@ -0,0 +1,121 @@
+/// Settings that relax or tighten the grammar accepted by
+/// [`crate::parse_with_options`]. The default is strict RFC 8259.
+#[derive(Debug, Clone)]
+pub struct ParseOptions {
+    /// Stop after the first complete value instead of requiring that only
+    /// whitespace follows it. [`crate::Json::skip`] reports how much of the
+    /// input was consumed.
+    pub allow_trailing_content: bool,
+    /// Maximum number of nested arrays and objects. Parsing is recursive, so
+    /// very large limits can still overflow the stack on deep input.
+    pub max_depth: usize,
+    /// What to do when an object repeats a key.
+    pub duplicate_keys: DuplicateKeys,
+    /// What to do with a `\uXXXX` escape for a UTF-16 surrogate that is not
+    /// part of a high/low pair.
+    pub lone_surrogates: LoneSurrogates,
+    /// Accept raw U+0000 through U+001F inside strings, as some legacy
+    /// producers emit unescaped tabs and newlines.
+    pub allow_control_characters: bool,
+    /// Which flavour of JSON to accept.
+    pub dialect: Dialect,
+    /// Keep comments in [`crate::Json::comments`], attached to the nearest
+    /// value, so [`crate::to_json_pretty_with_comments`] can write them back.
+    /// Has no effect for [`Dialect::Json`], which has no comments.
+    pub preserve_comments: bool,
+}
+
+/// The grammar accepted by the parser.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Dialect {
+    /// Strict RFC 8259.
+    #[default]
+    Json,
+    /// JSONC as read by VS Code for `settings.json` and `tsconfig.json`:
+    /// JSON plus `//` and `/* */` comments and trailing commas.
+    Jsonc,
+    /// [JSON5](https://spec.json5.org): comments, trailing commas,
+    /// single-quoted strings, unquoted identifier keys, hexadecimal numbers,
+    /// `Infinity`, `NaN`, explicit `+` signs, leading or trailing decimal
+    /// points, extra escapes and Unicode whitespace.
+    ///
+    /// Numbers are stored with a JSON spelling where one exists, so `0x1F`
+    /// reads back as `31` and `.5` as `0.5`.
+    Json5,
+}
+
+impl Dialect {
+    pub(crate) fn allows_comments(self) -> bool {
+        matches!(self, Dialect::Jsonc | Dialect::Json5)
+    }
+
+    pub(crate) fn allows_trailing_commas(self) -> bool {
+        matches!(self, Dialect::Jsonc | Dialect::Json5)
+    }
+}
+
+/// Policy for objects that contain the same key more than once.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DuplicateKeys {
+    /// Keep every member; lookups return the last one.
+    #[default]
+    Allow,
+    /// Keep the first member and discard later ones.
+    FirstWins,
+    /// Keep the position of the first member but the value of the last.
+    LastWins,
+    /// Fail with [`crate::ErrorKind::DuplicateKey`].
+    Error,
+}
+
+/// Policy for unpaired UTF-16 surrogate escapes such as `"\uD800"`.
+///
+/// Decoded strings are Rust `String`s, so a lone surrogate cannot be kept
+/// in the string itself; it has to be rejected, replaced, or kept aside.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LoneSurrogates {
+    /// Fail with [`crate::ErrorKind::LoneSurrogate`].
+    #[default]
+    Error,
+    /// Decode it as U+FFFD REPLACEMENT CHARACTER.
+    Replace,
+    /// Decode it as U+FFFD like [`LoneSurrogates::Replace`], and also keep
+    /// the string as WTF-8, with the surrogate encoded in place, in
+    /// [`crate::Json::raw_strings`].
+    Preserve,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        ParseOptions {
+            allow_trailing_content: false,
+            max_depth: 128,
+            duplicate_keys: DuplicateKeys::Allow,
+            lone_surrogates: LoneSurrogates::Error,
+            allow_control_characters: false,
+            dialect: Dialect::Json,
+            preserve_comments: false,
+        }
+    }
+}
+
+/// Settings for [`crate::diff_with_options`].
+#[derive(Debug, Clone, Default)]
+pub struct DiffOptions {
+    /// How elements of two arrays are paired up for comparison.
+    pub arrays: ArrayAlignment,
+}
+
+/// How [`crate::diff_with_options`] pairs the elements of two arrays.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ArrayAlignment {
+    /// Compare elements at the same index; extra elements are added or
+    /// removed at the end.
+    #[default]
+    Index,
+    /// Keep the longest common subsequence of equal elements in place, so
+    /// an insertion near the start shows as one added element instead of a
+    /// change to every element after it. Takes time and memory proportional
+    /// to the product of the array lengths.
+    Lcs,
+}
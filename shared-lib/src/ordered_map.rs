@@ -0,0 +1,165 @@
+use std::collections::HashMap;
+use std::fmt;
+
+/// A key and its value, as kept by an [`OrderedMap`].
+pub trait Member {
+    /// What lookups return.
+    type Value: ?Sized;
+    /// What [`OrderedMap::insert`] takes and [`OrderedMap::remove`] gives back.
+    type Owned;
+
+    fn new(key: String, value: Self::Owned) -> Self;
+    fn key(&self) -> &str;
+    fn value(&self) -> &Self::Value;
+    fn value_mut(&mut self) -> &mut Self::Value;
+    /// Swap in a new value, returning the old one.
+    fn replace(&mut self, value: Self::Owned) -> Self::Owned;
+    fn into_value(self) -> Self::Owned;
+}
+
+/// Members in source order, with a key index for O(1) lookup. This is the
+/// storage behind both [`crate::Object`] and [`crate::JsonObject`].
+///
+/// Duplicate keys are kept when the parser allows them; lookups then
+/// resolve to the last member with that key.
+#[derive(Clone)]
+pub struct OrderedMap<M> {
+    members: Vec<M>,
+    index: HashMap<String, usize>,
+}
+
+impl<M: Member> OrderedMap<M> {
+    pub fn new() -> Self {
+        OrderedMap::default()
+    }
+
+    pub fn members(&self) -> &[M] {
+        &self.members
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, M> {
+        self.members.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.members.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.members.is_empty()
+    }
+
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.index.contains_key(key)
+    }
+
+    pub fn get(&self, key: &str) -> Option<&M::Value> {
+        self.index.get(key).map(|&i| self.members[i].value())
+    }
+
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut M::Value> {
+        match self.index.get(key) {
+            Some(&i) => Some(self.members[i].value_mut()),
+            None => None,
+        }
+    }
+
+    /// Position of the member that [`OrderedMap::get`] would return.
+    pub fn position(&self, key: &str) -> Option<usize> {
+        self.index.get(key).copied()
+    }
+
+    /// Append a member, even if its key is already present.
+    pub fn push(&mut self, member: M) {
+        self.index
+            .insert(member.key().to_string(), self.members.len());
+        self.members.push(member);
+    }
+
+    /// Replace the value of an existing key in place, or append a new member.
+    /// Returns the previous value.
+    pub fn insert(&mut self, key: String, value: M::Owned) -> Option<M::Owned> {
+        match self.index.get(&key) {
+            Some(&i) => Some(self.members[i].replace(value)),
+            None => {
+                self.push(M::new(key, value));
+                None
+            }
+        }
+    }
+
+    /// Remove the member that [`OrderedMap::get`] would return, keeping the
+    /// order of the remaining members.
+    pub fn remove(&mut self, key: &str) -> Option<M::Owned> {
+        let i = self.index.get(key).copied()?;
+        let member = self.members.remove(i);
+        self.reindex();
+        Some(member.into_value())
+    }
+
+    fn reindex(&mut self) {
+        self.index.clear();
+        for (i, member) in self.members.iter().enumerate() {
+            self.index.insert(member.key().to_string(), i);
+        }
+    }
+}
+
+impl<M> Default for OrderedMap<M> {
+    fn default() -> Self {
+        OrderedMap {
+            members: Vec::new(),
+            index: HashMap::new(),
+        }
+    }
+}
+
+impl<M: Member> From<Vec<M>> for OrderedMap<M> {
+    fn from(members: Vec<M>) -> Self {
+        let mut map = OrderedMap {
+            members,
+            index: HashMap::new(),
+        };
+        map.reindex();
+        map
+    }
+}
+
+impl<M: Member> FromIterator<M> for OrderedMap<M> {
+    fn from_iter<I: IntoIterator<Item = M>>(iter: I) -> Self {
+        OrderedMap::from(iter.into_iter().collect::<Vec<_>>())
+    }
+}
+
+impl<M> IntoIterator for OrderedMap<M> {
+    type Item = M;
+    type IntoIter = std::vec::IntoIter<M>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.members.into_iter()
+    }
+}
+
+impl<'a, M> IntoIterator for &'a OrderedMap<M> {
+    type Item = &'a M;
+    type IntoIter = std::slice::Iter<'a, M>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.members.iter()
+    }
+}
+
+/// The index is derived from `members`, so only the members are compared.
+impl<M: PartialEq> PartialEq for OrderedMap<M> {
+    fn eq(&self, other: &Self) -> bool {
+        self.members == other.members
+    }
+}
+
+impl<M: fmt::Debug> fmt::Debug for OrderedMap<M> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OrderedMap")
+            .field("members", &self.members)
+            .finish()
+    }
+}
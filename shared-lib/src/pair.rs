@@ -1,7 +1,9 @@
+use crate::cursor::{Cursor, OBJECT_DELIMITERS};
+use crate::error::{ErrorKind, ParseError, PathSegment};
+use crate::options::Dialect;
 use crate::string::parse_string;
-use crate::types::{Pair, ValueToken};
+use crate::types::{Pair, Span, ValueToken};
 use crate::value::parse_value;
-use regex::Regex;
 
 enum Mode {
     Scanning,
@@ -11,93 +13,62 @@ enum Mode {
     End,
 }
 
-lazy_static! {
-    static ref WHITESPACE: Regex = Regex::new(r"[ \n\r\t]").unwrap();
-}
-
-pub fn parse_pair(pair: &str) -> Result<ValueToken, &'static str> {
+pub fn parse_pair(cursor: &mut Cursor) -> Result<ValueToken, ParseError> {
     let mut mode = Mode::Scanning;
-    let mut pos: usize = 0;
+    let start = cursor.chars();
     let mut key = String::new();
+    let mut key_span = Span::default();
     let mut value: Option<ValueToken> = None;
 
-    while let Some(ch) = pair.chars().nth(pos) {
-        let char = &ch.to_string()[..];
-
+    while let Some(ch) = cursor.peek() {
         match mode {
             Mode::Scanning => {
-                if WHITESPACE.is_match(char) {
-                    pos += 1;
+                if cursor.is_trivia(ch) {
+                    cursor.skip_trivia(ch)?;
                 } else {
                     mode = Mode::StringValue;
                 }
             }
             Mode::StringValue => {
-                let slice: String = pair.chars().skip(pos).collect();
-                match parse_string(&slice) {
-                    Ok(ValueToken::StringToken { skip, token }) => {
-                        key = token;
-                        pos += skip;
-                        mode = Mode::Delimiter;
-                    }
-                    Ok(_) => {
-                        return Err("Expected string");
-                    }
-                    Err(e) => {
-                        return Err(e);
+                if cursor.dialect() == Dialect::Json5 && is_identifier_start(ch) {
+                    (key, key_span) = parse_identifier(cursor);
+                    mode = Mode::Delimiter;
+                } else {
+                    let pos = cursor.pos();
+                    match parse_string(cursor)? {
+                        ValueToken::StringToken { span, token, .. } => {
+                            key = token;
+                            key_span = span;
+                            mode = Mode::Delimiter;
+                        }
+                        _ => {
+                            return Err(ParseError::at(ErrorKind::Expected("string"), pos));
+                        }
                     }
                 }
             }
             Mode::Delimiter => {
-                if WHITESPACE.is_match(char) {
-                    pos += 1;
+                if cursor.is_trivia(ch) {
+                    cursor.skip_trivia(ch)?;
                 } else if ch == ':' {
-                    pos += 1;
+                    cursor.advance(ch);
                     mode = Mode::Value;
                 } else {
-                    return Err("Expected ':'");
+                    return Err(ParseError::at(ErrorKind::Expected("':'"), cursor.pos()));
                 }
             }
             Mode::Value => {
-                let slice: String = pair.chars().skip(pos).collect();
-                match parse_value(&slice, r"[ \n\r\t\},]") {
-                    Ok(ValueToken::ArrayToken { skip, token }) => {
-                        value = Some(ValueToken::ArrayToken { skip, token });
-                        pos += skip;
-                        mode = Mode::End;
-                    }
-                    Ok(ValueToken::FalseToken { skip, token }) => {
-                        value = Some(ValueToken::FalseToken { skip, token });
-                        pos += skip;
-                        mode = Mode::End;
-                    }
-                    Ok(ValueToken::NullToken { skip }) => {
-                        value = Some(ValueToken::NullToken { skip });
-                        pos += skip;
-                        mode = Mode::End;
-                    }
-                    Ok(ValueToken::NumberToken { skip, token }) => {
-                        value = Some(ValueToken::NumberToken { skip, token });
-                        pos += skip;
-                        mode = Mode::End;
-                    }
-                    Ok(ValueToken::ObjectToken { skip, token }) => {
-                        value = Some(ValueToken::ObjectToken { skip, token });
-                        pos += skip;
-                        mode = Mode::End;
+                let pos = cursor.pos();
+                match parse_value(cursor, OBJECT_DELIMITERS) {
+                    Ok(ValueToken::PairToken { .. }) => {
+                        return Err(ParseError::at(ErrorKind::UnexpectedToken, pos));
                     }
-                    Ok(ValueToken::StringToken { skip, token }) => {
-                        value = Some(ValueToken::StringToken { skip, token });
-                        pos += skip;
+                    Ok(token) => {
+                        value = Some(token);
                         mode = Mode::End;
                     }
-                    Ok(ValueToken::TrueToken { skip, token }) => {
-                        value = Some(ValueToken::TrueToken { skip, token });
-                        pos += skip;
-                        mode = Mode::End;
-                    }
-                    _ => {
-                        return Err("Unexpected token");
+                    Err(e) => {
+                        return Err(e.within(PathSegment::Key(key)));
                     }
                 }
             }
@@ -107,12 +78,55 @@ pub fn parse_pair(pair: &str) -> Result<ValueToken, &'static str> {
 
     match value {
         Some(value) => Ok(ValueToken::PairToken {
-            skip: pos,
+            skip: cursor.chars() - start,
+            span: Span {
+                start: key_span.start,
+                end: cursor.position(),
+            },
             token: Pair {
                 key,
+                key_span,
                 value: Box::new(value),
             },
         }),
-        _ => Err("Expected pair token"),
+        None => {
+            let expected = match mode {
+                Mode::Scanning | Mode::StringValue => "'\"'",
+                Mode::Delimiter => "':'",
+                Mode::Value | Mode::End => {
+                    return Err(ParseError::at(ErrorKind::Expected("value"), cursor.pos())
+                        .within(PathSegment::Key(key)));
+                }
+            };
+            Err(ParseError::at(ErrorKind::Expected(expected), cursor.pos()))
+        }
     }
 }
+
+/// Read an unquoted JSON5 key, which must be an ECMAScript identifier name.
+fn parse_identifier(cursor: &mut Cursor) -> (String, Span) {
+    let start = cursor.position();
+    let mut key = String::new();
+    while let Some(ch) = cursor.peek() {
+        if !is_identifier_part(ch) {
+            break;
+        }
+        key.push(ch);
+        cursor.advance(ch);
+    }
+    (
+        key,
+        Span {
+            start,
+            end: cursor.position(),
+        },
+    )
+}
+
+fn is_identifier_start(ch: char) -> bool {
+    ch.is_alphabetic() || ch == '$' || ch == '_'
+}
+
+fn is_identifier_part(ch: char) -> bool {
+    is_identifier_start(ch) || ch.is_alphanumeric() || ch == '\u{200c}' || ch == '\u{200d}'
+}
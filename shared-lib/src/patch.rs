@@ -0,0 +1,532 @@
+use crate::error::PatchError;
+use crate::json::parse;
+use crate::number::same_value;
+use crate::pointer::{JsonPointer, array_index};
+use crate::types::{Array, Object, Pair, Span, ValueToken};
+
+/// One operation of an RFC 6902 JSON Patch.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PatchOperation {
+    Add {
+        path: JsonPointer,
+        value: ValueToken,
+    },
+    Remove {
+        path: JsonPointer,
+    },
+    Replace {
+        path: JsonPointer,
+        value: ValueToken,
+    },
+    Move {
+        from: JsonPointer,
+        path: JsonPointer,
+    },
+    Copy {
+        from: JsonPointer,
+        path: JsonPointer,
+    },
+    Test {
+        path: JsonPointer,
+        value: ValueToken,
+    },
+}
+
+/// An RFC 6902 JSON Patch: a list of operations applied in order.
+///
+/// ```
+/// use shared_lib::{JsonPatch, parse, to_json_string};
+///
+/// let mut json = parse(r#"{"a": 1, "b": [1, 2]}"#).unwrap();
+/// let patch = JsonPatch::parse(
+///     r#"[{"op": "remove", "path": "/a"}, {"op": "add", "path": "/b/-", "value": 3}]"#,
+/// )
+/// .unwrap();
+/// patch.apply(&mut json.token).unwrap();
+/// assert_eq!(r#"{"b":[1,2,3]}"#, to_json_string(&json.token));
+/// ```
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct JsonPatch {
+    pub operations: Vec<PatchOperation>,
+}
+
+impl JsonPatch {
+    /// Parse a patch document.
+    pub fn parse(patch: &str) -> Result<Self, PatchError> {
+        JsonPatch::from_token(&parse(patch)?.token)
+    }
+
+    /// Read a patch from a parsed array of operation objects. Members other
+    /// than the ones the operation uses are ignored.
+    pub fn from_token(patch: &ValueToken) -> Result<Self, PatchError> {
+        let ValueToken::ArrayToken { token, .. } = patch else {
+            return Err(PatchError::Invalid {
+                index: 0,
+                message: "patch must be an array".to_string(),
+            });
+        };
+        let operations = token
+            .values
+            .iter()
+            .enumerate()
+            .map(|(index, operation)| read_operation(index, operation))
+            .collect::<Result<_, _>>()?;
+        Ok(JsonPatch { operations })
+    }
+
+    /// Apply every operation to `target`. If any operation fails, including
+    /// a `test`, `target` is left as it was.
+    pub fn apply(&self, target: &mut ValueToken) -> Result<(), PatchError> {
+        let mut patched = target.clone();
+        for (index, operation) in self.operations.iter().enumerate() {
+            apply_operation(&mut patched, index, operation)?;
+        }
+        *target = patched;
+        Ok(())
+    }
+
+    /// A patch that turns `from` into `to`. Object members are compared by
+    /// key and array elements by index; changed scalars and values of a
+    /// different type are replaced whole.
+    pub fn diff(from: &ValueToken, to: &ValueToken) -> Self {
+        JsonPatch::from_changes(&crate::diff::diff(from, to))
+    }
+
+    /// The patch as a JSON array of operation objects, ready for
+    /// [`crate::to_json_string`].
+    pub fn to_token(&self) -> ValueToken {
+        let values = self
+            .operations
+            .iter()
+            .map(|operation| Box::new(operation_token(operation)))
+            .collect();
+        ValueToken::ArrayToken {
+            skip: 0,
+            span: Span::default(),
+            token: Array { values },
+        }
+    }
+}
+
+/// JSON equality: numbers by value, objects regardless of member order.
+pub(crate) fn equal(left: &ValueToken, right: &ValueToken) -> bool {
+    match (left, right) {
+        (ValueToken::NullToken { .. }, ValueToken::NullToken { .. })
+        | (ValueToken::TrueToken { .. }, ValueToken::TrueToken { .. })
+        | (ValueToken::FalseToken { .. }, ValueToken::FalseToken { .. }) => true,
+        (
+            ValueToken::NumberToken { token: left, .. },
+            ValueToken::NumberToken { token: right, .. },
+        ) => same_value(left, right),
+        (
+            ValueToken::StringToken { token: left, .. },
+            ValueToken::StringToken { token: right, .. },
+        ) => left == right,
+        (
+            ValueToken::ArrayToken { token: left, .. },
+            ValueToken::ArrayToken { token: right, .. },
+        ) => {
+            left.values.len() == right.values.len()
+                && left
+                    .values
+                    .iter()
+                    .zip(&right.values)
+                    .all(|(left, right)| equal(left, right))
+        }
+        (
+            ValueToken::ObjectToken { token: left, .. },
+            ValueToken::ObjectToken { token: right, .. },
+        ) => {
+            let left_keys = keys(left);
+            left_keys.len() == keys(right).len()
+                && left_keys.into_iter().all(|key| {
+                    matches!((left.get(key), right.get(key)), (Some(l), Some(r)) if equal(l, r))
+                })
+        }
+        _ => false,
+    }
+}
+
+/// Keys of an object in order, each once: the members that
+/// [`Object::get`] returns.
+pub(crate) fn keys(object: &Object) -> Vec<&str> {
+    object
+        .iter()
+        .enumerate()
+        .filter(|(i, pair)| object.position(&pair.key) == Some(*i))
+        .map(|(_, pair)| pair.key.as_str())
+        .collect()
+}
+
+fn read_operation(index: usize, operation: &ValueToken) -> Result<PatchOperation, PatchError> {
+    let invalid = |message: String| PatchError::Invalid { index, message };
+    let ValueToken::ObjectToken { token: members, .. } = operation else {
+        return Err(invalid("operation must be an object".to_string()));
+    };
+    let pointer = |name: &str| match members.get(name) {
+        Some(ValueToken::StringToken { token, .. }) => JsonPointer::parse(token)
+            .map_err(|e| invalid(format!("\"{}\" is not a JSON Pointer: {}", name, e))),
+        Some(_) => Err(invalid(format!("\"{}\" must be a string", name))),
+        None => Err(invalid(format!("missing \"{}\"", name))),
+    };
+    let value = || match members.get("value") {
+        Some(value) => Ok(value.clone()),
+        None => Err(invalid("missing \"value\"".to_string())),
+    };
+    let op = match members.get("op") {
+        Some(ValueToken::StringToken { token, .. }) => token.as_str(),
+        Some(_) => return Err(invalid("\"op\" must be a string".to_string())),
+        None => return Err(invalid("missing \"op\"".to_string())),
+    };
+    Ok(match op {
+        "add" => PatchOperation::Add {
+            path: pointer("path")?,
+            value: value()?,
+        },
+        "remove" => PatchOperation::Remove {
+            path: pointer("path")?,
+        },
+        "replace" => PatchOperation::Replace {
+            path: pointer("path")?,
+            value: value()?,
+        },
+        "move" => PatchOperation::Move {
+            from: pointer("from")?,
+            path: pointer("path")?,
+        },
+        "copy" => PatchOperation::Copy {
+            from: pointer("from")?,
+            path: pointer("path")?,
+        },
+        "test" => PatchOperation::Test {
+            path: pointer("path")?,
+            value: value()?,
+        },
+        other => return Err(invalid(format!("unknown op \"{}\"", other))),
+    })
+}
+
+fn apply_operation(
+    target: &mut ValueToken,
+    index: usize,
+    operation: &PatchOperation,
+) -> Result<(), PatchError> {
+    let no_value = |path: &JsonPointer| PatchError::NoValue {
+        index,
+        path: path.clone(),
+    };
+    match operation {
+        PatchOperation::Add { path, value } => {
+            add(target, path, value.clone()).ok_or_else(|| no_value(path))
+        }
+        PatchOperation::Remove { path } => {
+            remove(target, path).map(drop).ok_or_else(|| no_value(path))
+        }
+        PatchOperation::Replace { path, value } => {
+            let old = path.resolve_mut(target).ok_or_else(|| no_value(path))?;
+            *old = value.clone();
+            Ok(())
+        }
+        PatchOperation::Move { from, path } => {
+            if path == from {
+                return from.resolve(target).map(drop).ok_or_else(|| no_value(from));
+            }
+            if path.starts_with(from) {
+                return Err(PatchError::Invalid {
+                    index,
+                    message: format!("cannot move \"{}\" into itself", from),
+                });
+            }
+            let value = remove(target, from).ok_or_else(|| no_value(from))?;
+            add(target, path, value).ok_or_else(|| no_value(path))
+        }
+        PatchOperation::Copy { from, path } => {
+            let value = from.resolve(target).ok_or_else(|| no_value(from))?.clone();
+            add(target, path, value).ok_or_else(|| no_value(path))
+        }
+        PatchOperation::Test { path, value } => {
+            let actual = path.resolve(target).ok_or_else(|| no_value(path))?;
+            if equal(actual, value) {
+                Ok(())
+            } else {
+                Err(PatchError::TestFailed {
+                    index,
+                    path: path.clone(),
+                })
+            }
+        }
+    }
+}
+
+/// Add `value` at `path`: replace the root or an existing member, or insert
+/// into an array, where `-` appends.
+fn add(target: &mut ValueToken, path: &JsonPointer, value: ValueToken) -> Option<()> {
+    let Some((parent, last)) = path.split_last() else {
+        *target = value;
+        return Some(());
+    };
+    match parent.resolve_mut(target)? {
+        ValueToken::ObjectToken { token, .. } => {
+            token.insert(last.to_string(), Box::new(value));
+        }
+        ValueToken::ArrayToken { token, .. } => {
+            let index = if last == "-" {
+                token.values.len()
+            } else {
+                array_index(last).filter(|&index| index <= token.values.len())?
+            };
+            token.values.insert(index, Box::new(value));
+        }
+        _ => return None,
+    }
+    Some(())
+}
+
+/// Remove and return the value at `path`, which must not be the root.
+fn remove(target: &mut ValueToken, path: &JsonPointer) -> Option<ValueToken> {
+    let (parent, last) = path.split_last()?;
+    match parent.resolve_mut(target)? {
+        ValueToken::ObjectToken { token, .. } => token.remove(last).map(|value| *value),
+        ValueToken::ArrayToken { token, .. } => {
+            let index = array_index(last).filter(|&index| index < token.values.len())?;
+            Some(*token.values.remove(index))
+        }
+        _ => None,
+    }
+}
+
+fn operation_token(operation: &PatchOperation) -> ValueToken {
+    let (op, from, path, value) = match operation {
+        PatchOperation::Add { path, value } => ("add", None, path, Some(value)),
+        PatchOperation::Remove { path } => ("remove", None, path, None),
+        PatchOperation::Replace { path, value } => ("replace", None, path, Some(value)),
+        PatchOperation::Move { from, path } => ("move", Some(from), path, None),
+        PatchOperation::Copy { from, path } => ("copy", Some(from), path, None),
+        PatchOperation::Test { path, value } => ("test", None, path, Some(value)),
+    };
+    let mut members = Object::new();
+    members.push(Pair::new("op".to_string(), string(op)));
+    if let Some(from) = from {
+        members.push(Pair::new("from".to_string(), string(&from.to_string())));
+    }
+    members.push(Pair::new("path".to_string(), string(&path.to_string())));
+    if let Some(value) = value {
+        members.push(Pair::new("value".to_string(), Box::new(value.clone())));
+    }
+    ValueToken::ObjectToken {
+        skip: 0,
+        span: Span::default(),
+        token: members,
+    }
+}
+
+fn string(value: &str) -> Box<ValueToken> {
+    Box::new(ValueToken::StringToken {
+        skip: 0,
+        span: Span::default(),
+        token: value.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{JsonPatch, equal};
+    use crate::error::PatchError;
+    use crate::json::parse;
+    use crate::serializer::to_json_string;
+    use crate::types::ValueToken;
+
+    fn token(input: &str) -> ValueToken {
+        match parse(input) {
+            Ok(json) => *json.token,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    fn patched(document: &str, patch: &str) -> Result<ValueToken, PatchError> {
+        let mut target = token(document);
+        JsonPatch::parse(patch)?.apply(&mut target)?;
+        Ok(target)
+    }
+
+    fn assert_patched(document: &str, patch: &str, expected: &str) {
+        match patched(document, patch) {
+            Ok(actual) => assert!(
+                equal(&actual, &token(expected)),
+                "{} gave {}",
+                patch,
+                to_json_string(&actual)
+            ),
+            Err(e) => panic!("{}: {}", patch, e),
+        }
+    }
+
+    /// The examples of RFC 6902, appendix A.
+    #[test]
+    fn rfc_examples() {
+        assert_patched(
+            r#"{"foo": "bar"}"#,
+            r#"[{"op": "add", "path": "/baz", "value": "qux"}]"#,
+            r#"{"baz": "qux", "foo": "bar"}"#,
+        );
+        assert_patched(
+            r#"{"foo": ["bar", "baz"]}"#,
+            r#"[{"op": "add", "path": "/foo/1", "value": "qux"}]"#,
+            r#"{"foo": ["bar", "qux", "baz"]}"#,
+        );
+        assert_patched(
+            r#"{"baz": "qux", "foo": "bar"}"#,
+            r#"[{"op": "remove", "path": "/baz"}]"#,
+            r#"{"foo": "bar"}"#,
+        );
+        assert_patched(
+            r#"{"foo": ["bar", "qux", "baz"]}"#,
+            r#"[{"op": "remove", "path": "/foo/1"}]"#,
+            r#"{"foo": ["bar", "baz"]}"#,
+        );
+        assert_patched(
+            r#"{"baz": "qux", "foo": "bar"}"#,
+            r#"[{"op": "replace", "path": "/baz", "value": "boo"}]"#,
+            r#"{"baz": "boo", "foo": "bar"}"#,
+        );
+        assert_patched(
+            r#"{"foo": {"bar": "baz", "waldo": "fred"}, "qux": {"corge": "grault"}}"#,
+            r#"[{"op": "move", "from": "/foo/waldo", "path": "/qux/thud"}]"#,
+            r#"{"foo": {"bar": "baz"}, "qux": {"corge": "grault", "thud": "fred"}}"#,
+        );
+        assert_patched(
+            r#"{"foo": ["all", "grass", "cows", "eat"]}"#,
+            r#"[{"op": "move", "from": "/foo/1", "path": "/foo/3"}]"#,
+            r#"{"foo": ["all", "cows", "eat", "grass"]}"#,
+        );
+        assert_patched(
+            r#"{"baz": "qux", "foo": ["a", 2, "c"]}"#,
+            r#"[{"op": "test", "path": "/baz", "value": "qux"},
+                {"op": "test", "path": "/foo/1", "value": 2}]"#,
+            r#"{"baz": "qux", "foo": ["a", 2, "c"]}"#,
+        );
+        assert_patched(
+            r#"{"foo": "bar"}"#,
+            r#"[{"op": "add", "path": "/child", "value": {"grandchild": {}}}]"#,
+            r#"{"foo": "bar", "child": {"grandchild": {}}}"#,
+        );
+        assert_patched(
+            r#"{"foo": "bar"}"#,
+            r#"[{"op": "add", "path": "/baz", "value": "qux", "xyz": 123}]"#,
+            r#"{"foo": "bar", "baz": "qux"}"#,
+        );
+        assert_patched(
+            r#"{"foo": ["bar"]}"#,
+            r#"[{"op": "add", "path": "/foo/-", "value": ["abc", "def"]}]"#,
+            r#"{"foo": ["bar", ["abc", "def"]]}"#,
+        );
+        assert_patched(
+            r#"{"/": 9, "~1": 10}"#,
+            r#"[{"op": "test", "path": "/~01", "value": 10}]"#,
+            r#"{"/": 9, "~1": 10}"#,
+        );
+        assert_patched(
+            r#"{"foo": 1}"#,
+            r#"[{"op": "copy", "from": "/foo", "path": "/bar"}]"#,
+            r#"{"foo": 1, "bar": 1}"#,
+        );
+        assert_patched(
+            r#"{"foo": 1}"#,
+            r#"[{"op": "replace", "path": "", "value": [1]}]"#,
+            r#"[1]"#,
+        );
+    }
+
+    #[test]
+    fn rfc_errors() {
+        for (document, patch) in [
+            (
+                r#"{"foo": "bar"}"#,
+                r#"[{"op": "add", "path": "/baz/bat", "value": "qux"}]"#,
+            ),
+            (
+                r#"{"baz": "qux"}"#,
+                r#"[{"op": "test", "path": "/baz", "value": "bar"}]"#,
+            ),
+            (
+                r#"{"/": 9, "~1": 10}"#,
+                r#"[{"op": "test", "path": "/~01", "value": "10"}]"#,
+            ),
+            (
+                r#"{"foo": [1]}"#,
+                r#"[{"op": "add", "path": "/foo/2", "value": 2}]"#,
+            ),
+            (r#"{"foo": [1]}"#, r#"[{"op": "remove", "path": "/foo/-"}]"#),
+            (
+                r#"{"foo": {}}"#,
+                r#"[{"op": "move", "from": "/foo", "path": "/foo/bar"}]"#,
+            ),
+            (r#"{"foo": 1}"#, r#"[{"op": "remove", "path": ""}]"#),
+            (r#"{}"#, r#"[{"op": "add", "path": "/a"}]"#),
+            (r#"{}"#, r#"[{"op": "nope", "path": "/a"}]"#),
+            (r#"{}"#, r#"{"op": "add", "path": "/a", "value": 1}"#),
+        ] {
+            assert!(patched(document, patch).is_err(), "{}", patch);
+        }
+        assert_eq!(
+            Some("Patch operation 0: test failed at \"/baz\"".to_string()),
+            patched(
+                r#"{"baz": 1}"#,
+                r#"[{"op": "test", "path": "/baz", "value": 2}]"#
+            )
+            .err()
+            .map(|e| e.to_string())
+        );
+    }
+
+    #[test]
+    fn failed_patch_leaves_target_unchanged() {
+        let mut target = token(r#"{"a": [1, 2], "b": true}"#);
+        let before = target.clone();
+        let patch = match JsonPatch::parse(
+            r#"[{"op": "remove", "path": "/a/0"},
+                {"op": "add", "path": "/c", "value": null},
+                {"op": "test", "path": "/b", "value": false}]"#,
+        ) {
+            Ok(patch) => patch,
+            Err(e) => panic!("{}", e),
+        };
+        assert_eq!(
+            Err(PatchError::TestFailed {
+                index: 2,
+                path: crate::JsonPointer::parse("/b").unwrap_or_default(),
+            }),
+            patch.apply(&mut target)
+        );
+        assert_eq!(before, target);
+    }
+
+    #[test]
+    fn diff_round_trips() {
+        for (from, to) in [
+            (
+                r#"{"a": 1, "b": [1, 2, 3], "c": {"d": null}}"#,
+                r#"{"a": 2, "b": [1], "c": {"e": null}, "f": "g"}"#,
+            ),
+            (r#"[1, 2]"#, r#"[1, 2, {"x": []}, 4]"#),
+            (
+                r#"{"a": 1.0, "b": {"c": 1, "d": 2}}"#,
+                r#"{"b": {"d": 2, "c": 1}, "a": 1}"#,
+            ),
+            (r#"{"a": [1]}"#, r#"{"a": {"0": 1}}"#),
+            (r#"1"#, r#""one""#),
+        ] {
+            let (from, to) = (token(from), token(to));
+            let patch = JsonPatch::diff(&from, &to);
+            let mut patched = from.clone();
+            let serialized = to_json_string(&patch.to_token());
+            match JsonPatch::parse(&serialized).and_then(|patch| patch.apply(&mut patched)) {
+                Ok(()) => assert!(equal(&patched, &to), "{}", serialized),
+                Err(e) => panic!("{}: {}", serialized, e),
+            }
+        }
+        let same = token(r#"{"a": 1.0, "b": {"c": 1, "d": 2}}"#);
+        let reordered = token(r#"{"b": {"d": 2, "c": 1}, "a": 1}"#);
+        assert!(JsonPatch::diff(&same, &reordered).operations.is_empty());
+    }
+}
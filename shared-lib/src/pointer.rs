@@ -0,0 +1,295 @@
+use crate::error::PointerError;
+use crate::types::{Json, ValueToken};
+use std::fmt;
+use std::str::FromStr;
+
+/// An RFC 6901 JSON Pointer such as `/data/items/3/name`.
+///
+/// Each reference token selects an object member by key or an array element
+/// by index. In the text form `~` is written `~0` and `/` is written `~1`.
+///
+/// ```
+/// use shared_lib::JsonPointer;
+///
+/// let pointer = JsonPointer::parse("/a~1b/0").unwrap();
+/// assert_eq!(vec!["a/b", "0"], pointer.tokens());
+/// assert_eq!("/a~1b/0", pointer.to_string());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct JsonPointer {
+    tokens: Vec<String>,
+}
+
+impl JsonPointer {
+    /// The empty pointer, which refers to the whole document.
+    pub fn root() -> Self {
+        JsonPointer::default()
+    }
+
+    pub fn parse(pointer: &str) -> Result<Self, PointerError> {
+        if pointer.is_empty() {
+            return Ok(JsonPointer::root());
+        }
+        let Some(rest) = pointer.strip_prefix('/') else {
+            return Err(PointerError::MissingSlash);
+        };
+        let mut tokens = Vec::new();
+        let mut offset = 1;
+        for raw in rest.split('/') {
+            tokens.push(unescape(raw, offset)?);
+            offset += raw.len() + 1;
+        }
+        Ok(JsonPointer { tokens })
+    }
+
+    /// The unescaped reference tokens, outermost first.
+    pub fn tokens(&self) -> Vec<&str> {
+        self.tokens.iter().map(String::as_str).collect()
+    }
+
+    pub fn is_root(&self) -> bool {
+        self.tokens.is_empty()
+    }
+
+    /// Add a reference token at the end.
+    pub fn push(&mut self, token: impl Into<String>) {
+        self.tokens.push(token.into());
+    }
+
+    /// The pointer to the value that contains this one, and the token that
+    /// selects this one within it. `None` for the root.
+    pub fn split_last(&self) -> Option<(JsonPointer, &str)> {
+        let (last, parent) = self.tokens.split_last()?;
+        Some((
+            JsonPointer {
+                tokens: parent.to_vec(),
+            },
+            last,
+        ))
+    }
+
+    /// Whether `self` is `other` or a pointer into it.
+    pub fn starts_with(&self, other: &JsonPointer) -> bool {
+        self.tokens.starts_with(&other.tokens)
+    }
+
+    pub fn resolve<'a>(&self, root: &'a ValueToken) -> Option<&'a ValueToken> {
+        self.tokens
+            .iter()
+            .try_fold(root, |token, reference| child(token, reference))
+    }
+
+    pub fn resolve_mut<'a>(&self, root: &'a mut ValueToken) -> Option<&'a mut ValueToken> {
+        self.tokens
+            .iter()
+            .try_fold(root, |token, reference| child_mut(token, reference))
+    }
+}
+
+impl FromStr for JsonPointer {
+    type Err = PointerError;
+
+    fn from_str(pointer: &str) -> Result<Self, Self::Err> {
+        JsonPointer::parse(pointer)
+    }
+}
+
+impl fmt::Display for JsonPointer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for token in &self.tokens {
+            write!(f, "/{}", token.replace('~', "~0").replace('/', "~1"))?;
+        }
+        Ok(())
+    }
+}
+
+/// Decode `~0` and `~1` in one reference token that starts at byte
+/// `offset` of the pointer.
+fn unescape(raw: &str, offset: usize) -> Result<String, PointerError> {
+    let mut token = String::with_capacity(raw.len());
+    let mut chars = raw.char_indices();
+    while let Some((i, ch)) = chars.next() {
+        if ch != '~' {
+            token.push(ch);
+            continue;
+        }
+        match chars.next() {
+            Some((_, '0')) => token.push('~'),
+            Some((_, '1')) => token.push('/'),
+            _ => return Err(PointerError::InvalidEscape(offset + i)),
+        }
+    }
+    Ok(token)
+}
+
+/// Array index for a reference token: digits without a leading zero.
+/// `-`, the element after the last one, never refers to a value.
+pub(crate) fn array_index(reference: &str) -> Option<usize> {
+    let digits = reference.bytes().all(|byte| byte.is_ascii_digit());
+    if reference.is_empty() || !digits || (reference.len() > 1 && reference.starts_with('0')) {
+        return None;
+    }
+    reference.parse().ok()
+}
+
+fn child<'a>(token: &'a ValueToken, reference: &str) -> Option<&'a ValueToken> {
+    match token {
+        ValueToken::ArrayToken { token, .. } => token
+            .values
+            .get(array_index(reference)?)
+            .map(|value| &**value),
+        ValueToken::ObjectToken { token, .. } => token.get(reference),
+        _ => None,
+    }
+}
+
+fn child_mut<'a>(token: &'a mut ValueToken, reference: &str) -> Option<&'a mut ValueToken> {
+    match token {
+        ValueToken::ArrayToken { token, .. } => token
+            .values
+            .get_mut(array_index(reference)?)
+            .map(|value| &mut **value),
+        ValueToken::ObjectToken { token, .. } => token.get_mut(reference),
+        _ => None,
+    }
+}
+
+impl ValueToken {
+    /// Look up a value by RFC 6901 JSON Pointer. Returns `None` if the
+    /// pointer is malformed or nothing is there. With duplicate keys the
+    /// last member wins, as with [`crate::Object::get`].
+    ///
+    /// ```
+    /// use shared_lib::{ValueToken, parse};
+    ///
+    /// let json = parse(r#"{"data": {"items": [{"name": "a"}, {"name": "b"}]}}"#).unwrap();
+    /// match json.token.pointer("/data/items/1/name") {
+    ///     Some(ValueToken::StringToken { token, .. }) => assert_eq!("b", token),
+    ///     other => panic!("unexpected {:?}", other),
+    /// }
+    /// ```
+    pub fn pointer(&self, pointer: &str) -> Option<&ValueToken> {
+        JsonPointer::parse(pointer).ok()?.resolve(self)
+    }
+
+    pub fn pointer_mut(&mut self, pointer: &str) -> Option<&mut ValueToken> {
+        JsonPointer::parse(pointer).ok()?.resolve_mut(self)
+    }
+}
+
+impl Json {
+    /// [`ValueToken::pointer`] on the root value.
+    pub fn pointer(&self, pointer: &str) -> Option<&ValueToken> {
+        self.token.pointer(pointer)
+    }
+
+    pub fn pointer_mut(&mut self, pointer: &str) -> Option<&mut ValueToken> {
+        self.token.pointer_mut(pointer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::JsonPointer;
+    use crate::error::PointerError;
+    use crate::json::parse;
+    use crate::types::{Json, ValueToken};
+
+    /// The example document from RFC 6901, section 5.
+    const RFC_DOCUMENT: &str = r#"{
+        "foo": ["bar", "baz"],
+        "": 0,
+        "a/b": 1,
+        "c%d": 2,
+        "e^f": 3,
+        "g|h": 4,
+        "i\\j": 5,
+        "k\"l": 6,
+        " ": 7,
+        "m~n": 8
+    }"#;
+
+    fn document(input: &str) -> Json {
+        match parse(input) {
+            Ok(json) => json,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    fn number(token: Option<&ValueToken>) -> Option<f64> {
+        match token {
+            Some(ValueToken::NumberToken { token, .. }) => Some(token.value),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn rfc_examples() {
+        let json = document(RFC_DOCUMENT);
+        assert_eq!(Some(&*json.token), json.pointer(""));
+        match json.pointer("/foo") {
+            Some(ValueToken::ArrayToken { token, .. }) => assert_eq!(2, token.values.len()),
+            other => panic!("Expected ArrayToken, got {:?}", other),
+        }
+        match json.pointer("/foo/0") {
+            Some(ValueToken::StringToken { token, .. }) => assert_eq!("bar", token),
+            other => panic!("Expected StringToken, got {:?}", other),
+        }
+        for (pointer, expected) in [
+            ("/", 0.0),
+            ("/a~1b", 1.0),
+            ("/c%d", 2.0),
+            ("/e^f", 3.0),
+            ("/g|h", 4.0),
+            ("/i\\j", 5.0),
+            ("/k\"l", 6.0),
+            ("/ ", 7.0),
+            ("/m~0n", 8.0),
+        ] {
+            assert_eq!(Some(expected), number(json.pointer(pointer)), "{}", pointer);
+        }
+    }
+
+    #[test]
+    fn missing_values() {
+        let json = document(r#"{"a": [10, 20], "b": {"c": null}}"#);
+        for pointer in [
+            "/x", "/a/2", "/a/-", "/a/01", "/a/+1", "/a/1e0", "/a/", "/b/c/d", "a", "/a~2",
+        ] {
+            assert_eq!(None, json.pointer(pointer), "{}", pointer);
+        }
+        assert_eq!(Some(20.0), number(json.pointer("/a/1")));
+    }
+
+    #[test]
+    fn mutation() {
+        let mut json = document(r#"{"data": {"items": [1, 2]}}"#);
+        match json.pointer_mut("/data/items/1") {
+            Some(ValueToken::NumberToken { token, .. }) => token.value = 5.0,
+            other => panic!("Expected NumberToken, got {:?}", other),
+        }
+        assert_eq!(Some(5.0), number(json.pointer("/data/items/1")));
+        assert!(json.pointer_mut("/data/nothing").is_none());
+    }
+
+    #[test]
+    fn parse_and_display() {
+        assert_eq!(Err(PointerError::MissingSlash), JsonPointer::parse("a/b"));
+        assert_eq!(
+            Err(PointerError::InvalidEscape(3)),
+            JsonPointer::parse("/a/~x")
+        );
+        assert_eq!(
+            Err(PointerError::InvalidEscape(2)),
+            JsonPointer::parse("/a~")
+        );
+        let pointer = match JsonPointer::parse("/~01/~10//") {
+            Ok(pointer) => pointer,
+            Err(e) => panic!("{}", e),
+        };
+        assert_eq!(vec!["~1", "/0", "", ""], pointer.tokens());
+        assert_eq!("/~01/~10//", pointer.to_string());
+        assert!(JsonPointer::root().is_root());
+        assert_eq!("", JsonPointer::root().to_string());
+    }
+}
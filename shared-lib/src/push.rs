@@ -0,0 +1,395 @@
+use crate::error::{ErrorKind, ParseError, UnsupportedOption};
+use crate::events::{EventParser, Input, JsonEvent, Step};
+use crate::options::{DuplicateKeys, ParseOptions};
+use crate::types::{Array, Json, Object, Pair, Position, Span, ValueToken};
+
+/// An array or object whose closing bracket has not arrived yet.
+enum Partial {
+    /// Boxed to match `Array::values`.
+    #[allow(clippy::vec_box)]
+    Array(Vec<Box<ValueToken>>),
+    Object {
+        members: Object,
+        /// Key of the member whose value is being read.
+        key: Option<(String, Span)>,
+    },
+}
+
+/// Push parser for input that arrives in chunks, such as a request body
+/// read from the network.
+///
+/// Chunks are handed over with [`JsonPushParser::feed`] and may split
+/// tokens and UTF-8 sequences anywhere. [`JsonPushParser::next_event`]
+/// reports each [`JsonEvent`] as soon as its bytes are in, while
+/// [`JsonPushParser::next_value`] builds the same [`Json`] tree as
+/// [`crate::parse_with_options`], with the same skips, spans and errors.
+/// Use one or the other on a given parser, not both.
+///
+/// Like [`crate::JsonReader`], the parser accepts strict JSON only, and
+/// [`JsonPushParser::with_options`] fails for a non-JSON `dialect` or
+/// [`crate::LoneSurrogates::Preserve`]. Every `duplicate_keys` policy is
+/// applied to the tree; [`JsonPushParser::next_event`] reports repeats under
+/// [`DuplicateKeys::LastWins`] as they are read.
+///
+/// ```
+/// use shared_lib::{JsonPushParser, ValueToken};
+///
+/// let mut parser = JsonPushParser::new();
+/// parser.feed(br#"{"greeting": "h"#);
+/// assert!(parser.next_value().unwrap().is_none());
+/// parser.feed("\u{e9}llo\"}".as_bytes());
+/// let json = parser.finish().unwrap();
+/// assert!(matches!(*json.token, ValueToken::ObjectToken { .. }));
+/// ```
+pub struct JsonPushParser {
+    parser: EventParser,
+    buffer: Vec<u8>,
+    start: usize,
+    eof: bool,
+    ended: bool,
+    stack: Vec<Partial>,
+    value: Option<Json>,
+}
+
+impl JsonPushParser {
+    pub fn new() -> Self {
+        match JsonPushParser::with_options(ParseOptions::default()) {
+            Ok(parser) => parser,
+            Err(e) => unreachable!("default options are supported: {}", e),
+        }
+    }
+
+    pub fn with_options(options: ParseOptions) -> Result<Self, UnsupportedOption> {
+        Ok(JsonPushParser {
+            parser: EventParser::new(options)?,
+            buffer: Vec::new(),
+            start: 0,
+            eof: false,
+            ended: false,
+            stack: Vec::new(),
+            value: None,
+        })
+    }
+
+    /// Hand over the next chunk of input.
+    pub fn feed(&mut self, chunk: &[u8]) {
+        self.buffer.drain(..self.start);
+        self.start = 0;
+        self.buffer.extend_from_slice(chunk);
+    }
+
+    /// Record that no more input follows, so that a value cut off at the
+    /// end is an error rather than a wait for more.
+    pub fn end(&mut self) {
+        self.eof = true;
+    }
+
+    /// Source position of the next unparsed byte.
+    pub fn position(&self) -> Position {
+        self.parser.position()
+    }
+
+    /// The next event, or `None` if more input is needed or the document
+    /// is over.
+    pub fn next_event(&mut self) -> Result<Option<JsonEvent>, ParseError> {
+        let mut input = Input::new(&self.buffer[self.start..], self.eof);
+        let step = self.parser.step(&mut input);
+        self.start += input.consumed();
+        match step? {
+            Step::Event(event) => Ok(Some(event)),
+            Step::NeedMore => Ok(None),
+            Step::End => {
+                self.ended = true;
+                Ok(None)
+            }
+        }
+    }
+
+    /// Parse as much of the input as has arrived and return the document
+    /// once it is complete: after [`JsonPushParser::end`], or as soon as
+    /// the top-level value closes if trailing content is allowed.
+    pub fn next_value(&mut self) -> Result<Option<Json>, ParseError> {
+        while let Some(event) = self.next_event()? {
+            self.build(event)?;
+        }
+        Ok(if self.ended { self.value.take() } else { None })
+    }
+
+    /// End the input and return the document.
+    pub fn finish(mut self) -> Result<Json, ParseError> {
+        self.end();
+        match self.next_value()? {
+            Some(json) => Ok(json),
+            None => Err(self
+                .parser
+                .error_at(ErrorKind::Expected("value"), self.position(), true)),
+        }
+    }
+
+    fn build(&mut self, event: JsonEvent) -> Result<(), ParseError> {
+        let skip = self.parser.skip();
+        let span = self.parser.span();
+        let token = match event {
+            JsonEvent::StartArray => {
+                self.stack.push(Partial::Array(Vec::new()));
+                return Ok(());
+            }
+            JsonEvent::StartObject => {
+                self.stack.push(Partial::Object {
+                    members: Object::new(),
+                    key: None,
+                });
+                return Ok(());
+            }
+            JsonEvent::Key(key) => {
+                if let Some(Partial::Object { key: current, .. }) = self.stack.last_mut() {
+                    *current = Some((key, span));
+                }
+                return Ok(());
+            }
+            JsonEvent::EndArray | JsonEvent::EndObject => match self.stack.pop() {
+                Some(Partial::Array(values)) => ValueToken::ArrayToken {
+                    skip,
+                    span,
+                    token: Array { values },
+                },
+                Some(Partial::Object { members, .. }) => ValueToken::ObjectToken {
+                    skip,
+                    span,
+                    token: members,
+                },
+                None => return Ok(()),
+            },
+            JsonEvent::String(token) => ValueToken::StringToken { skip, span, token },
+            JsonEvent::Number(token) => ValueToken::NumberToken { skip, span, token },
+            JsonEvent::Bool(true) => ValueToken::TrueToken {
+                skip,
+                span,
+                token: true,
+            },
+            JsonEvent::Bool(false) => ValueToken::FalseToken {
+                skip,
+                span,
+                token: false,
+            },
+            JsonEvent::Null => ValueToken::NullToken { skip, span },
+        };
+        self.add(token)
+    }
+
+    /// Add a completed value to the enclosing array or object, or make it
+    /// the document.
+    fn add(&mut self, token: ValueToken) -> Result<(), ParseError> {
+        let value = Box::new(token);
+        match self.stack.last_mut() {
+            Some(Partial::Array(values)) => values.push(value),
+            Some(Partial::Object { members, key }) => {
+                let Some((key, key_span)) = key.take() else {
+                    return Ok(());
+                };
+                if !members.contains_key(&key) {
+                    members.push(Pair {
+                        key,
+                        key_span,
+                        value,
+                    });
+                } else {
+                    match self.parser.options().duplicate_keys {
+                        DuplicateKeys::Allow => members.push(Pair {
+                            key,
+                            key_span,
+                            value,
+                        }),
+                        DuplicateKeys::FirstWins => {}
+                        DuplicateKeys::LastWins => {
+                            members.insert(key, value);
+                        }
+                        DuplicateKeys::Error => {
+                            // Reported once the member is complete, as by
+                            // the tree parser.
+                            return Err(self.parser.error_at(
+                                ErrorKind::DuplicateKey,
+                                key_span.start,
+                                true,
+                            ));
+                        }
+                    }
+                }
+            }
+            None => {
+                self.value = Some(Json {
+                    skip: self.parser.chars(),
+                    token: value,
+                    comments: Vec::new(),
+                    raw_strings: Vec::new(),
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Default for JsonPushParser {
+    fn default() -> Self {
+        JsonPushParser::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::JsonPushParser;
+    use crate::error::ErrorKind;
+    use crate::events::JsonEvent;
+    use crate::json::parse_with_options;
+    use crate::options::{DuplicateKeys, ParseOptions};
+
+    /// Feed `input` in two chunks split at every byte offset and check the
+    /// result against the tree parser.
+    fn assert_matches_tree_parser(input: &str, options: &ParseOptions) {
+        let expected = parse_with_options(input, options);
+        for split in 0..=input.len() {
+            let mut parser = JsonPushParser::with_options(options.clone()).unwrap();
+            parser.feed(&input.as_bytes()[..split]);
+            let early = parser.next_value();
+            parser.feed(&input.as_bytes()[split..]);
+            let actual = match early {
+                Err(e) => Err(e),
+                Ok(_) => parser.finish(),
+            };
+            assert_eq!(expected, actual, "{:?} split at {}", input, split);
+        }
+    }
+
+    #[test]
+    fn values_match_tree_parser() {
+        let options = ParseOptions::default();
+        for input in [
+            "0",
+            " -12.5e3 ",
+            "\"h\u{e9}llo \u{1F600} \\uD83D\\uDE00\"",
+            "true",
+            "[]",
+            "{}",
+            " [ 1 , [ null , false ] , {\"a\" : \"b\"} ] ",
+            "{\n  \"k\u{e9}y\": [true],\n  \"n\":\t{\"x\": -0.5}\n}\n",
+        ] {
+            assert_matches_tree_parser(input, &options);
+        }
+    }
+
+    #[test]
+    fn errors_match_tree_parser() {
+        let options = ParseOptions {
+            max_depth: 2,
+            duplicate_keys: DuplicateKeys::Error,
+            ..ParseOptions::default()
+        };
+        for input in [
+            "",
+            "  ",
+            "[1 2]",
+            "{\"a\" 1}",
+            "{\"a\":}",
+            "[1,]",
+            "[\"a\\q\"]",
+            "{\"k\":[1,{\"x\":tru}]}",
+            "[1] x",
+            "[01]",
+            "\n [\"\\uD800\"]",
+            "[[[1]]]",
+            "{\"a\":1,\"a\":[2]}",
+            "{\"a\":1,\"a\":[x]}",
+            "{\"a\":{\"b\":1,\"b\":2},\"a\":3}",
+            "?",
+            "[",
+            "[1",
+            " [ 1 , ",
+            "[[",
+            "{",
+            "{\"a\" ",
+            "{\"a\":",
+            "{\"a\":1",
+            "{\"a\":1, ",
+            "[{\"a\":[1",
+            "[\"a",
+            "[tr",
+            "[1.",
+        ] {
+            assert_matches_tree_parser(input, &options);
+        }
+    }
+
+    #[test]
+    fn duplicate_keys_follow_options() {
+        for duplicate_keys in [
+            DuplicateKeys::Allow,
+            DuplicateKeys::FirstWins,
+            DuplicateKeys::LastWins,
+        ] {
+            let options = ParseOptions {
+                duplicate_keys,
+                ..ParseOptions::default()
+            };
+            assert_matches_tree_parser("{\"a\": 1, \"b\": 2, \"a\": [3]}", &options);
+            assert_matches_tree_parser(
+                "{\"a\": {\"x\": 1, \"x\": 2}, \"b\": 2, \"a\": [3, {\"a\": 4}]}",
+                &options,
+            );
+        }
+    }
+
+    #[test]
+    fn events_arrive_with_their_bytes() {
+        let mut parser = JsonPushParser::new();
+        parser.feed(b"[1, \"a");
+        assert_eq!(Ok(Some(JsonEvent::StartArray)), parser.next_event());
+        assert!(matches!(
+            parser.next_event(),
+            Ok(Some(JsonEvent::Number(_)))
+        ));
+        assert_eq!(Ok(None), parser.next_event());
+        parser.feed(b"b\"]");
+        assert_eq!(
+            Ok(Some(JsonEvent::String("ab".to_string()))),
+            parser.next_event()
+        );
+        assert_eq!(Ok(Some(JsonEvent::EndArray)), parser.next_event());
+        assert_eq!(Ok(None), parser.next_event());
+    }
+
+    #[test]
+    fn value_returned_once_input_ends() {
+        let mut parser = JsonPushParser::new();
+        parser.feed(b"{\"a\": 1}");
+        assert_eq!(Ok(None), parser.next_value());
+        parser.end();
+        assert!(matches!(parser.next_value(), Ok(Some(_))));
+        assert_eq!(Ok(None), parser.next_value());
+    }
+
+    #[test]
+    fn value_returned_early_when_trailing_content_allowed() {
+        let options = ParseOptions {
+            allow_trailing_content: true,
+            ..ParseOptions::default()
+        };
+        let mut parser = JsonPushParser::with_options(options).unwrap();
+        parser.feed(b"{\"a\": 1} {");
+        assert!(matches!(parser.next_value(), Ok(Some(_))));
+    }
+
+    #[test]
+    fn errors_reported_before_input_ends() {
+        let mut parser = JsonPushParser::new();
+        parser.feed(b"{\"a\": [1, 2");
+        assert_eq!(Ok(None), parser.next_value());
+        parser.feed(b"}");
+        match parser.next_value() {
+            Err(e) => {
+                assert_eq!(ErrorKind::Expected("',' or ']'"), e.kind);
+                assert_eq!(11, e.offset);
+            }
+            other => panic!("Expected error, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,360 @@
+use crate::error::{ReadError, UnsupportedOption};
+use crate::events::{EventParser, Input, JsonEvent, Step};
+use crate::options::{DuplicateKeys, ParseOptions};
+use crate::types::Position;
+use std::io::{self, Read};
+
+const BUFFER_SIZE: usize = 8 * 1024;
+
+/// Pull parser that reads a document from any [`Read`] and yields
+/// [`JsonEvent`]s as it goes, without building a tree.
+///
+/// Memory use is bounded by an 8 KiB read buffer, the nesting depth and the
+/// largest single string or number. Strings and numbers are decoded by the
+/// same rules as [`crate::parse`], and errors carry the same positions and
+/// paths.
+///
+/// The reader accepts strict JSON only. [`JsonReader::with_options`] fails
+/// for options it cannot honour: a non-JSON `dialect`,
+/// [`crate::LoneSurrogates::Preserve`], and [`DuplicateKeys::LastWins`],
+/// since the first member's events are out before the repeat is seen.
+/// Under [`DuplicateKeys::FirstWins`] a repeated member yields no events.
+///
+/// ```
+/// use shared_lib::{JsonEvent, JsonReader};
+///
+/// let events: Vec<JsonEvent> = JsonReader::new(&br#"{"a":[1,null]}"#[..])
+///     .collect::<Result<_, _>>()
+///     .unwrap();
+/// assert_eq!(JsonEvent::StartObject, events[0]);
+/// assert_eq!(JsonEvent::Key("a".to_string()), events[1]);
+/// assert_eq!(JsonEvent::Null, events[4]);
+/// ```
+pub struct JsonReader<R> {
+    reader: R,
+    parser: EventParser,
+    buffer: Box<[u8]>,
+    start: usize,
+    end: usize,
+    eof: bool,
+    failed: bool,
+}
+
+impl<R: Read> JsonReader<R> {
+    pub fn new(reader: R) -> Self {
+        match JsonReader::with_options(reader, ParseOptions::default()) {
+            Ok(reader) => reader,
+            Err(e) => unreachable!("default options are supported: {}", e),
+        }
+    }
+
+    pub fn with_options(reader: R, options: ParseOptions) -> Result<Self, UnsupportedOption> {
+        if options.duplicate_keys == DuplicateKeys::LastWins {
+            return Err(UnsupportedOption {
+                option: "duplicate_keys",
+                reason: "events cannot be taken back once a key repeats",
+            });
+        }
+        Ok(JsonReader {
+            reader,
+            parser: EventParser::new(options)?,
+            buffer: vec![0; BUFFER_SIZE].into_boxed_slice(),
+            start: 0,
+            end: 0,
+            eof: false,
+            failed: false,
+        })
+    }
+
+    /// Source position of the next unread byte.
+    pub fn position(&self) -> Position {
+        self.parser.position()
+    }
+
+    fn next_event(&mut self) -> Result<Option<JsonEvent>, ReadError> {
+        loop {
+            let mut input = Input::new(&self.buffer[self.start..self.end], self.eof);
+            let step = self.parser.step(&mut input);
+            self.start += input.consumed();
+            match step? {
+                Step::Event(event) => return Ok(Some(event)),
+                Step::End => return Ok(None),
+                Step::NeedMore => self.fill()?,
+            }
+        }
+    }
+
+    /// Refill the buffer once the parser has taken all of it.
+    fn fill(&mut self) -> io::Result<()> {
+        loop {
+            match self.reader.read(&mut self.buffer) {
+                Ok(n) => {
+                    self.start = 0;
+                    self.end = n;
+                    self.eof = n == 0;
+                    return Ok(());
+                }
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+impl<R: Read> Iterator for JsonReader<R> {
+    type Item = Result<JsonEvent, ReadError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        match self.next_event() {
+            Ok(event) => event.map(Ok),
+            Err(e) => {
+                self.failed = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::JsonReader;
+    use crate::error::{ErrorKind, ReadError};
+    use crate::events::JsonEvent;
+    use crate::json::parse_with_options;
+    use crate::options::{Dialect, DuplicateKeys, LoneSurrogates, ParseOptions};
+    use crate::types::Number;
+    use std::io::{self, Read};
+
+    /// Hands out one byte per `read` call to exercise buffer refills.
+    struct Trickle<'a>(&'a [u8]);
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            match self.0.split_first() {
+                Some((first, rest)) if !buf.is_empty() => {
+                    buf[0] = *first;
+                    self.0 = rest;
+                    Ok(1)
+                }
+                _ => Ok(0),
+            }
+        }
+    }
+
+    fn events<R: Read>(reader: JsonReader<R>) -> Vec<JsonEvent> {
+        match reader.collect::<Result<Vec<_>, _>>() {
+            Ok(events) => events,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    fn number_token(text: &str) -> Number {
+        Number {
+            value: text.parse().unwrap(),
+            value_as_string: text.to_string(),
+        }
+    }
+
+    fn number(events: &[JsonEvent], index: usize) -> f64 {
+        match &events[index] {
+            JsonEvent::Number(number) => number.value,
+            event => panic!("Expected Number, got {:?}", event),
+        }
+    }
+
+    #[test]
+    fn yields_events_in_order() {
+        let input = r#" {"a": [1, -2.5e1, "x\n", true, false, null, {}], "b": {"c": []}} "#;
+        let events = events(JsonReader::new(input.as_bytes()));
+        assert_eq!(
+            vec![
+                JsonEvent::StartObject,
+                JsonEvent::Key("a".to_string()),
+                JsonEvent::StartArray,
+            ],
+            events[..3]
+        );
+        assert_eq!(1.0, number(&events, 3));
+        assert_eq!(-25.0, number(&events, 4));
+        assert_eq!(
+            vec![
+                JsonEvent::String("x\n".to_string()),
+                JsonEvent::Bool(true),
+                JsonEvent::Bool(false),
+                JsonEvent::Null,
+                JsonEvent::StartObject,
+                JsonEvent::EndObject,
+                JsonEvent::EndArray,
+                JsonEvent::Key("b".to_string()),
+                JsonEvent::StartObject,
+                JsonEvent::Key("c".to_string()),
+                JsonEvent::StartArray,
+                JsonEvent::EndArray,
+                JsonEvent::EndObject,
+                JsonEvent::EndObject,
+            ],
+            events[5..]
+        );
+    }
+
+    #[test]
+    fn tokens_split_across_reads() {
+        let input = "[\"h\u{e9}llo \u{1F600}\", \"\\uD83D\\uDE00\\\"\", 12345.678, true]";
+        assert_eq!(
+            events(JsonReader::new(input.as_bytes())),
+            events(JsonReader::new(Trickle(input.as_bytes())))
+        );
+    }
+
+    #[test]
+    fn streams_past_the_buffer() {
+        let input = format!("[{}0]", "\"abcdefgh\",".repeat(5_000));
+        let mut count = 0;
+        for event in JsonReader::new(Trickle(input.as_bytes())) {
+            if let Err(e) = event {
+                panic!("{}", e);
+            }
+            count += 1;
+        }
+        assert_eq!(5_003, count);
+    }
+
+    #[test]
+    fn errors_match_tree_parser() {
+        let options = ParseOptions {
+            max_depth: 2,
+            duplicate_keys: DuplicateKeys::Error,
+            ..ParseOptions::default()
+        };
+        for input in [
+            "",
+            "[1 2]",
+            "{\"a\" 1}",
+            "{\"a\":}",
+            "{1:2}",
+            "[1,]",
+            "{\"a\":1,}",
+            "[\"a\\q\"]",
+            "{\"k\":[1,{\"x\":tru}]}",
+            "[1] x",
+            "[-]",
+            "[01]",
+            "[1.5e]",
+            "\n [\"\\uD800\"]",
+            "[\"ab\u{1}\"]",
+            "\"\u{e9}\" 1",
+            "[[[1]]]",
+            "{\"a\":{\"b\":[]}}",
+            "{\"a\":1,\"b\":2,\"a\":[3]}",
+            "{\"a\":1,\"a\":[x]}",
+            "{\"a\":{\"b\":1,\"b\":2},\"a\":3}",
+            "?",
+        ] {
+            let expected = parse_with_options(input, &options).unwrap_err();
+            let actual = JsonReader::with_options(input.as_bytes(), options.clone())
+                .unwrap()
+                .find_map(Result::err);
+            match actual {
+                Some(ReadError::Parse(e)) => assert_eq!(expected, e, "{:?}", input),
+                other => panic!("{:?}: expected {:?}, got {:?}", input, expected, other),
+            }
+        }
+    }
+
+    #[test]
+    fn invalid_utf8() {
+        let input: &[u8] = b"[\"ok\", \"a\xffb\"]";
+        match JsonReader::new(input).find_map(Result::err) {
+            Some(ReadError::Parse(e)) => {
+                assert_eq!(ErrorKind::InvalidUtf8, e.kind);
+                assert_eq!(9, e.offset);
+                assert_eq!(10, e.column);
+            }
+            other => panic!("Expected parse error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn io_errors_are_reported_once() {
+        struct Broken;
+        impl Read for Broken {
+            fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+                Err(io::Error::other("disk on fire"))
+            }
+        }
+        let mut reader = JsonReader::new(Broken);
+        assert!(matches!(reader.next(), Some(Err(ReadError::Io(_)))));
+        assert!(reader.next().is_none());
+    }
+
+    #[test]
+    fn trailing_content() {
+        let options = ParseOptions {
+            allow_trailing_content: true,
+            ..ParseOptions::default()
+        };
+        let mut reader = JsonReader::with_options("1 2".as_bytes(), options).unwrap();
+        assert_eq!(1.0, number(&[reader.next().unwrap().unwrap()], 0));
+        assert!(reader.next().is_none());
+        assert_eq!(2, reader.position().offset);
+    }
+
+    #[test]
+    fn first_key_wins() {
+        let options = ParseOptions {
+            duplicate_keys: DuplicateKeys::FirstWins,
+            ..ParseOptions::default()
+        };
+        let input = r#"{"a": 1, "b": {"a": 2, "a": []}, "a": {"c": [3]}}"#;
+        let reader = JsonReader::with_options(input.as_bytes(), options).unwrap();
+        assert_eq!(
+            vec![
+                JsonEvent::StartObject,
+                JsonEvent::Key("a".to_string()),
+                JsonEvent::Number(number_token("1")),
+                JsonEvent::Key("b".to_string()),
+                JsonEvent::StartObject,
+                JsonEvent::Key("a".to_string()),
+                JsonEvent::Number(number_token("2")),
+                JsonEvent::EndObject,
+                JsonEvent::EndObject,
+            ],
+            events(reader)
+        );
+    }
+
+    #[test]
+    fn unsupported_options_rejected() {
+        for (options, option) in [
+            (
+                ParseOptions {
+                    dialect: Dialect::Json5,
+                    ..ParseOptions::default()
+                },
+                "dialect",
+            ),
+            (
+                ParseOptions {
+                    lone_surrogates: LoneSurrogates::Preserve,
+                    ..ParseOptions::default()
+                },
+                "lone_surrogates",
+            ),
+            (
+                ParseOptions {
+                    duplicate_keys: DuplicateKeys::LastWins,
+                    ..ParseOptions::default()
+                },
+                "duplicate_keys",
+            ),
+        ] {
+            match JsonReader::with_options(&b"{}"[..], options) {
+                Ok(_) => panic!("{} should be rejected", option),
+                Err(e) => assert_eq!(option, e.option),
+            }
+        }
+    }
+}
@@ -0,0 +1,1422 @@
+use crate::error::{SchemaError, ValidationError};
+use crate::json::parse;
+use crate::number::{compare, is_integer, is_multiple};
+use crate::patch::{equal, keys};
+use crate::pointer::JsonPointer;
+use crate::serializer::to_json_string;
+use crate::types::{Number, ValueToken};
+use regex::Regex;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+/// How many `$ref`s may be followed without reaching another value of the
+/// instance, so that a schema that refers to itself cannot loop forever.
+const MAX_REF_DEPTH: usize = 64;
+
+/// A JSON Schema (draft 2020-12) ready to validate instances.
+///
+/// Supported keywords: `type`, `enum`, `const`, the numeric, string, array
+/// and object constraints, `properties`, `patternProperties`,
+/// `additionalProperties`, `propertyNames`, `required`, `prefixItems`,
+/// `items`, `contains`, `uniqueItems`, `allOf`, `anyOf`, `oneOf`, `not`,
+/// `if`/`then`/`else`, `$ref` with `$defs`, `$id` and `$anchor` within the
+/// same document, and `format` as an assertion for `date-time`, `date`,
+/// `time`, `email`, `hostname`, `ipv4`, `ipv6`, `uri`, `uuid`,
+/// `json-pointer` and `regex`. Other keywords and formats are ignored.
+///
+/// ```
+/// use shared_lib::{JsonSchema, parse};
+///
+/// let schema = JsonSchema::parse(
+///     r#"{"type": "object", "required": ["id"], "properties": {"id": {"type": "integer"}}}"#,
+/// )
+/// .unwrap();
+/// assert!(schema.is_valid(&parse(r#"{"id": 7}"#).unwrap().token));
+/// let errors = schema.validate(&parse(r#"{"id": "7"}"#).unwrap().token).unwrap_err();
+/// assert_eq!("/id", errors[0].instance_path.to_string());
+/// assert_eq!("/properties/id/type", errors[0].schema_path.to_string());
+/// ```
+#[derive(Debug, Clone)]
+pub struct JsonSchema {
+    schema: ValueToken,
+    /// Schema resources by absolute `$id`, the document itself under its
+    /// own `$id` or the empty string.
+    resources: HashMap<String, JsonPointer>,
+    /// `$anchor`s by `<resource id>#<anchor>`.
+    anchors: HashMap<String, JsonPointer>,
+    /// Compiled `pattern` and `patternProperties` expressions by source.
+    patterns: HashMap<String, Regex>,
+}
+
+impl JsonSchema {
+    pub fn parse(schema: &str) -> Result<Self, SchemaError> {
+        JsonSchema::new(&parse(schema)?.token)
+    }
+
+    /// Prepare `schema` for validation, compiling its patterns and checking
+    /// that every `$ref` resolves.
+    pub fn new(schema: &ValueToken) -> Result<Self, SchemaError> {
+        let mut compiled = JsonSchema {
+            schema: schema.clone(),
+            resources: HashMap::new(),
+            anchors: HashMap::new(),
+            patterns: HashMap::new(),
+        };
+        let mut refs = Vec::new();
+        compiled.index(schema, &mut JsonPointer::root(), "", &mut refs)?;
+        compiled.resources.entry(String::new()).or_default();
+        for (path, base, reference) in refs {
+            if compiled.resolve(&base, &reference).is_none() {
+                return Err(SchemaError::Invalid {
+                    path,
+                    message: format!("cannot resolve $ref \"{}\"", reference),
+                });
+            }
+        }
+        Ok(compiled)
+    }
+
+    /// Every way `instance` fails the schema, or `Ok` if it is valid.
+    pub fn validate(&self, instance: &ValueToken) -> Result<(), Vec<ValidationError>> {
+        let mut validation = Validation {
+            schema: self,
+            errors: Vec::new(),
+        };
+        let scope = Scope {
+            base: self.root_id(),
+            ref_depth: 0,
+        };
+        validation.check(
+            &self.schema,
+            &mut JsonPointer::root(),
+            instance,
+            &mut JsonPointer::root(),
+            &scope,
+        );
+        if validation.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(validation.errors)
+        }
+    }
+
+    pub fn is_valid(&self, instance: &ValueToken) -> bool {
+        self.validate(instance).is_ok()
+    }
+
+    fn root_id(&self) -> String {
+        match member(&self.schema, "$id") {
+            Some(ValueToken::StringToken { token, .. }) => without_fragment(token).to_string(),
+            _ => String::new(),
+        }
+    }
+
+    /// Record resources, anchors and patterns, and collect every `$ref` with
+    /// the base it is resolved against.
+    fn index(
+        &mut self,
+        schema: &ValueToken,
+        path: &mut JsonPointer,
+        base: &str,
+        refs: &mut Vec<(JsonPointer, String, String)>,
+    ) -> Result<(), SchemaError> {
+        let ValueToken::ObjectToken { token: members, .. } = schema else {
+            if let ValueToken::ArrayToken { token, .. } = schema {
+                for (i, value) in token.values.iter().enumerate() {
+                    path.push(i.to_string());
+                    self.index(value, path, base, refs)?;
+                    pop(path);
+                }
+            }
+            return Ok(());
+        };
+        let mut base = base.to_string();
+        if let Some(ValueToken::StringToken { token: id, .. }) = members.get("$id") {
+            base = without_fragment(&join(&base, id)).to_string();
+            self.resources.insert(base.clone(), path.clone());
+        }
+        if let Some(ValueToken::StringToken { token: anchor, .. }) = members.get("$anchor") {
+            self.anchors
+                .insert(format!("{}#{}", base, anchor), path.clone());
+        }
+        if let Some(ValueToken::StringToken {
+            token: reference, ..
+        }) = members.get("$ref")
+        {
+            refs.push((child(path, "$ref"), base.clone(), reference.clone()));
+        }
+        if let Some(ValueToken::StringToken { token: pattern, .. }) = members.get("pattern") {
+            self.compile(pattern, &child(path, "pattern"))?;
+        }
+        if let Some(ValueToken::ObjectToken { token, .. }) = members.get("patternProperties") {
+            for pattern in keys(token) {
+                self.compile(pattern, &child(&child(path, "patternProperties"), pattern))?;
+            }
+        }
+        for pair in members {
+            // Literal values are data, not schemas.
+            if matches!(pair.key.as_str(), "enum" | "const" | "examples" | "default") {
+                continue;
+            }
+            path.push(pair.key.as_str());
+            self.index(&pair.value, path, &base, refs)?;
+            pop(path);
+        }
+        Ok(())
+    }
+
+    fn compile(&mut self, pattern: &str, path: &JsonPointer) -> Result<(), SchemaError> {
+        if self.patterns.contains_key(pattern) {
+            return Ok(());
+        }
+        let regex = Regex::new(pattern).map_err(|e| SchemaError::Invalid {
+            path: path.clone(),
+            message: format!("invalid pattern: {}", e),
+        })?;
+        self.patterns.insert(pattern.to_string(), regex);
+        Ok(())
+    }
+
+    /// The schema a `$ref` refers to, its location, and the base URI that
+    /// applies within it.
+    fn resolve(&self, base: &str, reference: &str) -> Option<(&ValueToken, JsonPointer, String)> {
+        let target = join(base, reference);
+        let (resource, fragment) = match target.split_once('#') {
+            Some((resource, fragment)) => (resource, fragment),
+            None => (target.as_str(), ""),
+        };
+        let path = if fragment.is_empty() || fragment.starts_with('/') {
+            let mut path = self.resources.get(resource)?.clone();
+            for token in JsonPointer::parse(&percent_decode(fragment)?)
+                .ok()?
+                .tokens()
+            {
+                path.push(token);
+            }
+            path
+        } else {
+            self.anchors.get(&target)?.clone()
+        };
+        let schema = path.resolve(&self.schema)?;
+        Some((schema, path, resource.to_string()))
+    }
+}
+
+fn member<'a>(schema: &'a ValueToken, key: &str) -> Option<&'a ValueToken> {
+    match schema {
+        ValueToken::ObjectToken { token, .. } => token.get(key),
+        _ => None,
+    }
+}
+
+fn child(path: &JsonPointer, token: &str) -> JsonPointer {
+    let mut child = path.clone();
+    child.push(token);
+    child
+}
+
+fn pop(path: &mut JsonPointer) {
+    if let Some((parent, _)) = path.split_last() {
+        *path = parent;
+    }
+}
+
+fn without_fragment(uri: &str) -> &str {
+    uri.split_once('#').map_or(uri, |(uri, _)| uri)
+}
+
+/// Resolve a URI reference against a base URI, enough for `$id` and `$ref`
+/// within one document: fragments, absolute URIs, and absolute and relative
+/// paths.
+fn join(base: &str, reference: &str) -> String {
+    let base = without_fragment(base);
+    if reference.starts_with('#') {
+        return format!("{}{}", base, reference);
+    }
+    let scheme_end = reference.find(':');
+    let has_scheme = scheme_end.is_some_and(|end| {
+        let scheme = &reference[..end];
+        scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+            && scheme
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+    });
+    if has_scheme || base.is_empty() {
+        return reference.to_string();
+    }
+    let authority_start = base.find("://").map_or(0, |i| i + 3);
+    if let Some(rest) = reference.strip_prefix("//") {
+        let scheme = &base[..authority_start.saturating_sub(2)];
+        return format!("{}//{}", scheme, rest);
+    }
+    if reference.starts_with('/') {
+        let path_start = base[authority_start..]
+            .find('/')
+            .map_or(base.len(), |i| authority_start + i);
+        return format!("{}{}", &base[..path_start], reference);
+    }
+    match base.rfind('/') {
+        Some(i) if i >= authority_start => format!("{}{}", &base[..=i], reference),
+        _ => reference.to_string(),
+    }
+}
+
+fn percent_decode(text: &str) -> Option<String> {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = text.get(i + 1..i + 3)?;
+            decoded.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
+
+/// Where validation is within the schema document.
+struct Scope {
+    /// Base URI for `$ref`s.
+    base: String,
+    /// `$ref`s followed since the instance location last changed.
+    ref_depth: usize,
+}
+
+struct Validation<'a> {
+    schema: &'a JsonSchema,
+    errors: Vec<ValidationError>,
+}
+
+impl<'a> Validation<'a> {
+    fn fail(
+        &mut self,
+        schema_path: &JsonPointer,
+        keyword: &str,
+        instance_path: &JsonPointer,
+        message: String,
+    ) {
+        self.errors.push(ValidationError {
+            instance_path: instance_path.clone(),
+            schema_path: child(schema_path, keyword),
+            message,
+        });
+    }
+
+    /// Whether `instance` is valid against `schema`, without reporting why.
+    fn passes(
+        &self,
+        schema: &ValueToken,
+        schema_path: &mut JsonPointer,
+        instance: &ValueToken,
+        instance_path: &mut JsonPointer,
+        scope: &Scope,
+    ) -> bool {
+        let mut nested = Validation {
+            schema: self.schema,
+            errors: Vec::new(),
+        };
+        nested.check(schema, schema_path, instance, instance_path, scope);
+        nested.errors.is_empty()
+    }
+
+    /// Validate `instance` against the subschema found under `keyword`
+    /// (and then `token`, if given) of the schema at `schema_path`.
+    #[allow(clippy::too_many_arguments)]
+    fn check_at(
+        &mut self,
+        schema: &ValueToken,
+        schema_path: &mut JsonPointer,
+        keyword: &str,
+        token: Option<&str>,
+        instance: &ValueToken,
+        instance_path: &mut JsonPointer,
+        scope: &Scope,
+    ) {
+        schema_path.push(keyword);
+        if let Some(token) = token {
+            schema_path.push(token);
+        }
+        self.check(schema, schema_path, instance, instance_path, scope);
+        pop(schema_path);
+        if token.is_some() {
+            pop(schema_path);
+        }
+    }
+
+    fn check(
+        &mut self,
+        schema: &ValueToken,
+        schema_path: &mut JsonPointer,
+        instance: &ValueToken,
+        instance_path: &mut JsonPointer,
+        scope: &Scope,
+    ) {
+        let members = match schema {
+            ValueToken::TrueToken { .. } => return,
+            ValueToken::FalseToken { .. } => {
+                self.errors.push(ValidationError {
+                    instance_path: instance_path.clone(),
+                    schema_path: schema_path.clone(),
+                    message: "No value is allowed here".to_string(),
+                });
+                return;
+            }
+            ValueToken::ObjectToken { token, .. } => token,
+            _ => return,
+        };
+        let mut base = scope.base.clone();
+        if let Some(ValueToken::StringToken { token: id, .. }) = members.get("$id") {
+            base = without_fragment(&join(&base, id)).to_string();
+        }
+        let scope = Scope {
+            base,
+            ref_depth: scope.ref_depth,
+        };
+
+        if let Some(ValueToken::StringToken {
+            token: reference, ..
+        }) = members.get("$ref")
+        {
+            self.reference(reference, schema_path, instance, instance_path, &scope);
+        }
+        self.generic(members, schema_path, instance, instance_path);
+        self.combinators(schema, schema_path, instance, instance_path, &scope);
+        match instance {
+            ValueToken::NumberToken { token, .. } => {
+                self.number(schema, schema_path, token, instance_path)
+            }
+            ValueToken::StringToken { token, .. } => {
+                self.string(schema, schema_path, token, instance_path)
+            }
+            ValueToken::ArrayToken { token, .. } => {
+                self.array(schema, schema_path, &token.values, instance_path, &scope)
+            }
+            ValueToken::ObjectToken { .. } => {
+                self.object(schema, schema_path, instance, instance_path, &scope)
+            }
+            _ => {}
+        }
+    }
+
+    fn reference(
+        &mut self,
+        reference: &str,
+        schema_path: &JsonPointer,
+        instance: &ValueToken,
+        instance_path: &mut JsonPointer,
+        scope: &Scope,
+    ) {
+        let Some((target, mut target_path, base)) = self.schema.resolve(&scope.base, reference)
+        else {
+            self.fail(
+                schema_path,
+                "$ref",
+                instance_path,
+                format!("Cannot resolve \"{}\"", reference),
+            );
+            return;
+        };
+        if scope.ref_depth >= MAX_REF_DEPTH {
+            self.fail(
+                schema_path,
+                "$ref",
+                instance_path,
+                "Too many nested $refs".to_string(),
+            );
+            return;
+        }
+        let scope = Scope {
+            base,
+            ref_depth: scope.ref_depth + 1,
+        };
+        self.check(target, &mut target_path, instance, instance_path, &scope);
+    }
+
+    /// `type`, `enum` and `const`, which apply to any instance.
+    fn generic(
+        &mut self,
+        members: &crate::types::Object,
+        schema_path: &JsonPointer,
+        instance: &ValueToken,
+        instance_path: &JsonPointer,
+    ) {
+        match members.get("type") {
+            Some(ValueToken::StringToken { token, .. }) if !has_type(instance, token) => {
+                self.fail(
+                    schema_path,
+                    "type",
+                    instance_path,
+                    format!("Expected {}, found {}", token, type_name(instance)),
+                );
+            }
+            Some(ValueToken::ArrayToken { token, .. }) => {
+                let names: Vec<&str> = token
+                    .values
+                    .iter()
+                    .filter_map(|value| match &**value {
+                        ValueToken::StringToken { token, .. } => Some(token.as_str()),
+                        _ => None,
+                    })
+                    .collect();
+                if !names.iter().any(|name| has_type(instance, name)) {
+                    self.fail(
+                        schema_path,
+                        "type",
+                        instance_path,
+                        format!(
+                            "Expected {}, found {}",
+                            names.join(" or "),
+                            type_name(instance)
+                        ),
+                    );
+                }
+            }
+            _ => {}
+        }
+        if let Some(ValueToken::ArrayToken { token, .. }) = members.get("enum")
+            && !token.values.iter().any(|value| equal(value, instance))
+        {
+            self.fail(
+                schema_path,
+                "enum",
+                instance_path,
+                format!(
+                    "{} is not one of the allowed values",
+                    to_json_string(instance)
+                ),
+            );
+        }
+        if let Some(value) = members.get("const")
+            && !equal(value, instance)
+        {
+            self.fail(
+                schema_path,
+                "const",
+                instance_path,
+                format!("Expected {}", to_json_string(value)),
+            );
+        }
+    }
+
+    fn combinators(
+        &mut self,
+        schema: &ValueToken,
+        schema_path: &mut JsonPointer,
+        instance: &ValueToken,
+        instance_path: &mut JsonPointer,
+        scope: &Scope,
+    ) {
+        if let Some(ValueToken::ArrayToken { token, .. }) = member(schema, "allOf") {
+            for (i, subschema) in token.values.iter().enumerate() {
+                let token = i.to_string();
+                self.check_at(
+                    subschema,
+                    schema_path,
+                    "allOf",
+                    Some(&token),
+                    instance,
+                    instance_path,
+                    scope,
+                );
+            }
+        }
+        if let Some(ValueToken::ArrayToken { token, .. }) = member(schema, "anyOf") {
+            let passed = token.values.iter().enumerate().any(|(i, subschema)| {
+                let mut path = child(&child(schema_path, "anyOf"), &i.to_string());
+                self.passes(subschema, &mut path, instance, instance_path, scope)
+            });
+            if !passed {
+                self.fail(
+                    schema_path,
+                    "anyOf",
+                    instance_path,
+                    "Matches none of the schemas".to_string(),
+                );
+            }
+        }
+        if let Some(ValueToken::ArrayToken { token, .. }) = member(schema, "oneOf") {
+            let matches = token
+                .values
+                .iter()
+                .enumerate()
+                .filter(|(i, subschema)| {
+                    let mut path = child(&child(schema_path, "oneOf"), &i.to_string());
+                    self.passes(subschema, &mut path, instance, instance_path, scope)
+                })
+                .count();
+            if matches != 1 {
+                self.fail(
+                    schema_path,
+                    "oneOf",
+                    instance_path,
+                    format!("Matches {} of the schemas instead of exactly one", matches),
+                );
+            }
+        }
+        if let Some(subschema) = member(schema, "not") {
+            let mut path = child(schema_path, "not");
+            if self.passes(subschema, &mut path, instance, instance_path, scope) {
+                self.fail(
+                    schema_path,
+                    "not",
+                    instance_path,
+                    "Matches a schema it must not".to_string(),
+                );
+            }
+        }
+        if let Some(condition) = member(schema, "if") {
+            let mut path = child(schema_path, "if");
+            let branch = if self.passes(condition, &mut path, instance, instance_path, scope) {
+                "then"
+            } else {
+                "else"
+            };
+            if let Some(subschema) = member(schema, branch) {
+                self.check_at(
+                    subschema,
+                    schema_path,
+                    branch,
+                    None,
+                    instance,
+                    instance_path,
+                    scope,
+                );
+            }
+        }
+    }
+
+    fn number(
+        &mut self,
+        schema: &ValueToken,
+        schema_path: &JsonPointer,
+        number: &Number,
+        instance_path: &JsonPointer,
+    ) {
+        let limit = |keyword: &str| match member(schema, keyword) {
+            Some(ValueToken::NumberToken { token, .. }) => Some(token),
+            _ => None,
+        };
+        for (keyword, description) in [
+            ("minimum", "at least"),
+            ("maximum", "at most"),
+            ("exclusiveMinimum", "greater than"),
+            ("exclusiveMaximum", "less than"),
+        ] {
+            let Some(limit) = limit(keyword) else {
+                continue;
+            };
+            let holds = match (keyword, compare(number, limit)) {
+                ("minimum", Some(ordering)) => ordering != Ordering::Less,
+                ("maximum", Some(ordering)) => ordering != Ordering::Greater,
+                ("exclusiveMinimum", Some(ordering)) => ordering == Ordering::Greater,
+                (_, Some(ordering)) => ordering == Ordering::Less,
+                (_, None) => false,
+            };
+            if !holds {
+                self.fail(
+                    schema_path,
+                    keyword,
+                    instance_path,
+                    format!(
+                        "Expected a number {} {}, found {}",
+                        description, limit.value_as_string, number.value_as_string
+                    ),
+                );
+            }
+        }
+        if let Some(ValueToken::NumberToken { token: divisor, .. }) = member(schema, "multipleOf")
+            && !is_multiple(number, divisor)
+        {
+            self.fail(
+                schema_path,
+                "multipleOf",
+                instance_path,
+                format!(
+                    "{} is not a multiple of {}",
+                    number.value_as_string, divisor.value_as_string
+                ),
+            );
+        }
+    }
+
+    fn string(
+        &mut self,
+        schema: &ValueToken,
+        schema_path: &JsonPointer,
+        value: &str,
+        instance_path: &JsonPointer,
+    ) {
+        let length = value.chars().count();
+        if let Some(min) = count(schema, "minLength")
+            && length < min
+        {
+            self.fail(
+                schema_path,
+                "minLength",
+                instance_path,
+                format!("Expected at least {} characters, found {}", min, length),
+            );
+        }
+        if let Some(max) = count(schema, "maxLength")
+            && length > max
+        {
+            self.fail(
+                schema_path,
+                "maxLength",
+                instance_path,
+                format!("Expected at most {} characters, found {}", max, length),
+            );
+        }
+        if let Some(ValueToken::StringToken { token: pattern, .. }) = member(schema, "pattern")
+            && let Some(regex) = self.schema.patterns.get(pattern)
+            && !regex.is_match(value)
+        {
+            self.fail(
+                schema_path,
+                "pattern",
+                instance_path,
+                format!("Does not match the pattern \"{}\"", pattern),
+            );
+        }
+        if let Some(ValueToken::StringToken { token: format, .. }) = member(schema, "format")
+            && !is_format(format, value)
+        {
+            self.fail(
+                schema_path,
+                "format",
+                instance_path,
+                format!("Not a valid {}", format),
+            );
+        }
+    }
+
+    #[allow(clippy::vec_box)]
+    fn array(
+        &mut self,
+        schema: &ValueToken,
+        schema_path: &mut JsonPointer,
+        values: &Vec<Box<ValueToken>>,
+        instance_path: &mut JsonPointer,
+        scope: &Scope,
+    ) {
+        let scope = Scope {
+            base: scope.base.clone(),
+            ref_depth: 0,
+        };
+        if let Some(min) = count(schema, "minItems")
+            && values.len() < min
+        {
+            self.fail(
+                schema_path,
+                "minItems",
+                instance_path,
+                format!("Expected at least {} items, found {}", min, values.len()),
+            );
+        }
+        if let Some(max) = count(schema, "maxItems")
+            && values.len() > max
+        {
+            self.fail(
+                schema_path,
+                "maxItems",
+                instance_path,
+                format!("Expected at most {} items, found {}", max, values.len()),
+            );
+        }
+        if let Some(ValueToken::TrueToken { .. }) = member(schema, "uniqueItems") {
+            let duplicate = (0..values.len())
+                .any(|i| values[i + 1..].iter().any(|other| equal(&values[i], other)));
+            if duplicate {
+                self.fail(
+                    schema_path,
+                    "uniqueItems",
+                    instance_path,
+                    "Items are not unique".to_string(),
+                );
+            }
+        }
+        let mut prefix = 0;
+        if let Some(ValueToken::ArrayToken { token, .. }) = member(schema, "prefixItems") {
+            prefix = token.values.len();
+            for (i, (subschema, value)) in token.values.iter().zip(values).enumerate() {
+                let token = i.to_string();
+                instance_path.push(token.as_str());
+                self.check_at(
+                    subschema,
+                    schema_path,
+                    "prefixItems",
+                    Some(&token),
+                    value,
+                    instance_path,
+                    &scope,
+                );
+                pop(instance_path);
+            }
+        }
+        if let Some(subschema) = member(schema, "items") {
+            for (i, value) in values.iter().enumerate().skip(prefix) {
+                instance_path.push(i.to_string());
+                self.check_at(
+                    subschema,
+                    schema_path,
+                    "items",
+                    None,
+                    value,
+                    instance_path,
+                    &scope,
+                );
+                pop(instance_path);
+            }
+        }
+        if let Some(subschema) = member(schema, "contains") {
+            let mut path = child(schema_path, "contains");
+            let matches = values
+                .iter()
+                .enumerate()
+                .filter(|(i, value)| {
+                    let mut item_path = child(instance_path, &i.to_string());
+                    self.passes(subschema, &mut path, value, &mut item_path, &scope)
+                })
+                .count();
+            let min = count(schema, "minContains").unwrap_or(1);
+            if matches < min {
+                self.fail(
+                    schema_path,
+                    "contains",
+                    instance_path,
+                    format!(
+                        "Expected at least {} matching items, found {}",
+                        min, matches
+                    ),
+                );
+            }
+            if let Some(max) = count(schema, "maxContains")
+                && matches > max
+            {
+                self.fail(
+                    schema_path,
+                    "maxContains",
+                    instance_path,
+                    format!("Expected at most {} matching items, found {}", max, matches),
+                );
+            }
+        }
+    }
+
+    fn object(
+        &mut self,
+        schema: &ValueToken,
+        schema_path: &mut JsonPointer,
+        instance: &ValueToken,
+        instance_path: &mut JsonPointer,
+        scope: &Scope,
+    ) {
+        let ValueToken::ObjectToken { token: object, .. } = instance else {
+            return;
+        };
+        let scope = Scope {
+            base: scope.base.clone(),
+            ref_depth: 0,
+        };
+        let names = keys(object);
+        if let Some(min) = count(schema, "minProperties")
+            && names.len() < min
+        {
+            self.fail(
+                schema_path,
+                "minProperties",
+                instance_path,
+                format!(
+                    "Expected at least {} properties, found {}",
+                    min,
+                    names.len()
+                ),
+            );
+        }
+        if let Some(max) = count(schema, "maxProperties")
+            && names.len() > max
+        {
+            self.fail(
+                schema_path,
+                "maxProperties",
+                instance_path,
+                format!("Expected at most {} properties, found {}", max, names.len()),
+            );
+        }
+        if let Some(ValueToken::ArrayToken { token, .. }) = member(schema, "required") {
+            for name in &token.values {
+                if let ValueToken::StringToken { token: name, .. } = &**name
+                    && !object.contains_key(name)
+                {
+                    self.fail(
+                        schema_path,
+                        "required",
+                        instance_path,
+                        format!("Missing required property \"{}\"", name),
+                    );
+                }
+            }
+        }
+        if let Some(subschema) = member(schema, "propertyNames") {
+            for name in &names {
+                let name_token = ValueToken::StringToken {
+                    skip: 0,
+                    span: Default::default(),
+                    token: name.to_string(),
+                };
+                instance_path.push(*name);
+                self.check_at(
+                    subschema,
+                    schema_path,
+                    "propertyNames",
+                    None,
+                    &name_token,
+                    instance_path,
+                    &scope,
+                );
+                pop(instance_path);
+            }
+        }
+
+        let properties = match member(schema, "properties") {
+            Some(ValueToken::ObjectToken { token, .. }) => Some(token),
+            _ => None,
+        };
+        let patterns = match member(schema, "patternProperties") {
+            Some(ValueToken::ObjectToken { token, .. }) => Some(token),
+            _ => None,
+        };
+        let additional = member(schema, "additionalProperties");
+        for name in names {
+            let Some(value) = object.get(name) else {
+                continue;
+            };
+            instance_path.push(name);
+            let mut matched = false;
+            if let Some(subschema) = properties.and_then(|properties| properties.get(name)) {
+                matched = true;
+                self.check_at(
+                    subschema,
+                    schema_path,
+                    "properties",
+                    Some(name),
+                    value,
+                    instance_path,
+                    &scope,
+                );
+            }
+            for pattern in patterns.map(keys).unwrap_or_default() {
+                let Some(regex) = self.schema.patterns.get(pattern) else {
+                    continue;
+                };
+                if regex.is_match(name) {
+                    matched = true;
+                    if let Some(subschema) = patterns.and_then(|patterns| patterns.get(pattern)) {
+                        self.check_at(
+                            subschema,
+                            schema_path,
+                            "patternProperties",
+                            Some(pattern),
+                            value,
+                            instance_path,
+                            &scope,
+                        );
+                    }
+                }
+            }
+            if let (false, Some(subschema)) = (matched, additional) {
+                self.check_at(
+                    subschema,
+                    schema_path,
+                    "additionalProperties",
+                    None,
+                    value,
+                    instance_path,
+                    &scope,
+                );
+            }
+            pop(instance_path);
+        }
+    }
+}
+
+/// A non-negative integer keyword such as `minLength`. Limits beyond
+/// `usize` saturate, as no length can reach them.
+fn count(schema: &ValueToken, keyword: &str) -> Option<usize> {
+    match member(schema, keyword) {
+        Some(ValueToken::NumberToken { token, .. }) if is_integer(token) && token.value >= 0.0 => {
+            let exact = token.as_decimal().and_then(|text| text.parse().ok());
+            Some(exact.unwrap_or(usize::MAX))
+        }
+        _ => None,
+    }
+}
+
+fn has_type(instance: &ValueToken, name: &str) -> bool {
+    match instance {
+        ValueToken::NumberToken { token, .. } if name == "integer" => is_integer(token),
+        _ => type_name(instance) == name,
+    }
+}
+
+fn type_name(instance: &ValueToken) -> &'static str {
+    match instance {
+        ValueToken::NullToken { .. } => "null",
+        ValueToken::TrueToken { .. } | ValueToken::FalseToken { .. } => "boolean",
+        ValueToken::NumberToken { .. } => "number",
+        ValueToken::StringToken { .. } => "string",
+        ValueToken::ArrayToken { .. } => "array",
+        ValueToken::ObjectToken { .. } | ValueToken::PairToken { .. } => "object",
+    }
+}
+
+fn is_format(format: &str, value: &str) -> bool {
+    match format {
+        "date" => is_date(value),
+        "time" => is_time(value),
+        "date-time" => match value.find(['T', 't']) {
+            Some(i) => is_date(&value[..i]) && is_time(&value[i + 1..]),
+            None => false,
+        },
+        "email" => is_email(value),
+        "hostname" => is_hostname(value),
+        "ipv4" => value.parse::<Ipv4Addr>().is_ok(),
+        "ipv6" => value.parse::<Ipv6Addr>().is_ok(),
+        "uri" => is_uri(value),
+        "uuid" => is_uuid(value),
+        "json-pointer" => JsonPointer::parse(value).is_ok(),
+        "regex" => Regex::new(value).is_ok(),
+        _ => true,
+    }
+}
+
+/// The digits of `text`, which must all be ASCII digits, as a number.
+fn digits(text: &str) -> Option<u32> {
+    if text.is_empty() || !text.bytes().all(|byte| byte.is_ascii_digit()) {
+        return None;
+    }
+    text.parse().ok()
+}
+
+/// RFC 3339 `full-date`.
+fn is_date(value: &str) -> bool {
+    let [year, month, day] = match value.split('-').collect::<Vec<_>>()[..] {
+        [year, month, day] if year.len() == 4 && month.len() == 2 && day.len() == 2 => {
+            [year, month, day]
+        }
+        _ => return false,
+    };
+    let (Some(year), Some(month), Some(day)) = (digits(year), digits(month), digits(day)) else {
+        return false;
+    };
+    let leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+    let days = match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if leap => 29,
+        2 => 28,
+        _ => return false,
+    };
+    (1..=days).contains(&day)
+}
+
+/// RFC 3339 `full-time`: a time of day with a required offset. A leap
+/// second is allowed only at 23:59:60 UTC.
+fn is_time(value: &str) -> bool {
+    let (time, offset) = if let Some(time) = value.strip_suffix(['Z', 'z']) {
+        (time, 0)
+    } else {
+        let Some(i) = value.rfind(['+', '-']) else {
+            return false;
+        };
+        let (time, offset) = value.split_at(i);
+        let sign = if offset.starts_with('-') { -1 } else { 1 };
+        let minutes = match offset[1..].split(':').collect::<Vec<_>>()[..] {
+            [hours, minutes] if hours.len() == 2 && minutes.len() == 2 => {
+                match (digits(hours), digits(minutes)) {
+                    (Some(hours), Some(minutes)) if hours < 24 && minutes < 60 => {
+                        (hours * 60 + minutes) as i32
+                    }
+                    _ => return false,
+                }
+            }
+            _ => return false,
+        };
+        (time, sign * minutes)
+    };
+    let (time, fraction) = match time.split_once('.') {
+        Some((time, fraction)) => (time, Some(fraction)),
+        None => (time, None),
+    };
+    if fraction.is_some_and(|fraction| {
+        fraction.is_empty() || !fraction.bytes().all(|b| b.is_ascii_digit())
+    }) {
+        return false;
+    }
+    let [hours, minutes, seconds] = match time.split(':').collect::<Vec<_>>()[..] {
+        [h, m, s] if h.len() == 2 && m.len() == 2 && s.len() == 2 => [h, m, s],
+        _ => return false,
+    };
+    let (Some(hours), Some(minutes), Some(seconds)) =
+        (digits(hours), digits(minutes), digits(seconds))
+    else {
+        return false;
+    };
+    if hours > 23 || minutes > 59 || seconds > 60 {
+        return false;
+    }
+    if seconds == 60 {
+        let utc = (hours * 60 + minutes) as i32 - offset;
+        return utc.rem_euclid(24 * 60) == 23 * 60 + 59;
+    }
+    true
+}
+
+/// An RFC 5321 mailbox with a dot-atom local part and a hostname or
+/// bracketed IP address as the domain.
+fn is_email(value: &str) -> bool {
+    let Some((local, domain)) = value.rsplit_once('@') else {
+        return false;
+    };
+    let atom = |c: char| c.is_ascii_alphanumeric() || "!#$%&'*+-/=?^_`{|}~".contains(c);
+    let local_ok = !local.is_empty()
+        && local
+            .split('.')
+            .all(|part| !part.is_empty() && part.chars().all(atom));
+    let domain_ok = match domain.strip_prefix('[').and_then(|d| d.strip_suffix(']')) {
+        Some(address) => match address.strip_prefix("IPv6:") {
+            Some(address) => address.parse::<Ipv6Addr>().is_ok(),
+            None => address.parse::<Ipv4Addr>().is_ok(),
+        },
+        None => is_hostname(domain),
+    };
+    local_ok && domain_ok
+}
+
+/// An RFC 1123 host name: dot-separated labels of letters, digits and
+/// inner hyphens, each at most 63 characters.
+fn is_hostname(value: &str) -> bool {
+    let value = value.strip_suffix('.').unwrap_or(value);
+    !value.is_empty()
+        && value.len() <= 253
+        && value.split('.').all(|label| {
+            !label.is_empty()
+                && label.len() <= 63
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        })
+}
+
+/// An absolute RFC 3986 URI: a scheme and then characters allowed in a URI,
+/// with well-formed percent escapes.
+fn is_uri(value: &str) -> bool {
+    let Some((scheme, rest)) = value.split_once(':') else {
+        return false;
+    };
+    let scheme_ok = scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+        && scheme
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'));
+    let allowed = |c: char| c.is_ascii_alphanumeric() || "-._~:/?#[]@!$&'()*+,;=%".contains(c);
+    scheme_ok && rest.chars().all(allowed) && percent_decode(rest).is_some()
+}
+
+fn is_uuid(value: &str) -> bool {
+    let groups: Vec<&str> = value.split('-').collect();
+    groups.len() == 5
+        && groups
+            .iter()
+            .zip([8, 4, 4, 4, 12])
+            .all(|(group, len)| group.len() == len && group.bytes().all(|b| b.is_ascii_hexdigit()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::JsonSchema;
+    use crate::json::parse;
+    use crate::types::{Json, ValueToken};
+
+    /// Hand-written cases laid out like the JSON-Schema-Test-Suite: each
+    /// file is an array of groups with a `description`, a `schema` and
+    /// `tests`, each test having a `description`, `data` and whether it is
+    /// `valid`.
+    const CASES: &[(&str, &str)] = &[
+        (
+            "boolean_schema",
+            include_str!("../testdata/json-schema/cases/boolean_schema.json"),
+        ),
+        (
+            "type",
+            include_str!("../testdata/json-schema/cases/type.json"),
+        ),
+        (
+            "enum",
+            include_str!("../testdata/json-schema/cases/enum.json"),
+        ),
+        (
+            "const",
+            include_str!("../testdata/json-schema/cases/const.json"),
+        ),
+        (
+            "numeric",
+            include_str!("../testdata/json-schema/cases/numeric.json"),
+        ),
+        (
+            "string",
+            include_str!("../testdata/json-schema/cases/string.json"),
+        ),
+        (
+            "properties",
+            include_str!("../testdata/json-schema/cases/properties.json"),
+        ),
+        (
+            "required",
+            include_str!("../testdata/json-schema/cases/required.json"),
+        ),
+        (
+            "additionalProperties",
+            include_str!("../testdata/json-schema/cases/additionalProperties.json"),
+        ),
+        (
+            "items",
+            include_str!("../testdata/json-schema/cases/items.json"),
+        ),
+        (
+            "prefixItems",
+            include_str!("../testdata/json-schema/cases/prefixItems.json"),
+        ),
+        (
+            "arrays",
+            include_str!("../testdata/json-schema/cases/arrays.json"),
+        ),
+        (
+            "combinators",
+            include_str!("../testdata/json-schema/cases/combinators.json"),
+        ),
+        (
+            "ref",
+            include_str!("../testdata/json-schema/cases/ref.json"),
+        ),
+        (
+            "format",
+            include_str!("../testdata/json-schema/cases/format.json"),
+        ),
+    ];
+
+    fn document(input: &str) -> Json {
+        match parse(input) {
+            Ok(json) => json,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    fn text<'a>(token: &'a ValueToken, pointer: &str) -> &'a str {
+        match token.pointer(pointer) {
+            Some(ValueToken::StringToken { token, .. }) => token,
+            _ => "?",
+        }
+    }
+
+    /// Upstream draft 2020-12 files left out, with the reason.
+    const SUITE_UNSUPPORTED: &[(&str, &str)] = &[
+        (
+            "defs.json",
+            "validates against the meta-schema, which is not bundled",
+        ),
+        (
+            "dependentRequired.json",
+            "dependentRequired is not implemented",
+        ),
+        (
+            "dependentSchemas.json",
+            "dependentSchemas is not implemented",
+        ),
+        ("dynamicRef.json", "$dynamicRef is not implemented"),
+        (
+            "format.json",
+            "formats are asserted; the suite expects annotations",
+        ),
+        ("refRemote.json", "remote references are not fetched"),
+        (
+            "unevaluatedItems.json",
+            "unevaluatedItems is not implemented",
+        ),
+        (
+            "unevaluatedProperties.json",
+            "unevaluatedProperties is not implemented",
+        ),
+        ("vocabulary.json", "$vocabulary is not implemented"),
+    ];
+
+    /// Upstream cases that fail in otherwise supported files, as
+    /// `(file, group description, test description)`. Every case not
+    /// listed here must pass.
+    const SUITE_SKIPPED: &[(&str, &str, &str)] = &[];
+
+    /// Check every test of every group in one suite file, except those in
+    /// `skipped`, and describe each that fails.
+    fn run_file(file: &str, contents: &str, skipped: &[(&str, &str, &str)]) -> Vec<String> {
+        let mut failures = Vec::new();
+        let groups = document(contents);
+        let ValueToken::ArrayToken { token: groups, .. } = &*groups.token else {
+            panic!("{}: expected an array of groups", file);
+        };
+        for group in &groups.values {
+            let name = text(group, "/description");
+            let Some(schema) = group.pointer("/schema") else {
+                panic!("{}: {}: no schema", file, name);
+            };
+            let Some(ValueToken::ArrayToken { token: tests, .. }) = group.pointer("/tests") else {
+                panic!("{}: {}: no tests", file, name);
+            };
+            let schema = JsonSchema::new(schema);
+            for test in &tests.values {
+                let description = text(test, "/description");
+                if skipped.contains(&(file, name, description)) {
+                    continue;
+                }
+                let (Some(data), Some(valid)) = (test.pointer("/data"), test.pointer("/valid"))
+                else {
+                    panic!("{}: {}: malformed test", file, name);
+                };
+                let valid = matches!(valid, ValueToken::TrueToken { .. });
+                match &schema {
+                    Ok(schema) if schema.is_valid(data) == valid => {}
+                    Ok(_) => failures.push(format!("{}: {}: {}", file, name, description)),
+                    Err(e) => failures.push(format!("{}: {}: {}: {}", file, name, description, e)),
+                }
+            }
+        }
+        failures
+    }
+
+    #[test]
+    fn hand_written_cases() {
+        let failures: Vec<String> = CASES
+            .iter()
+            .flat_map(|(file, contents)| run_file(file, contents, &[]))
+            .collect();
+        assert!(failures.is_empty(), "{:#?}", failures);
+    }
+
+    /// The JSON-Schema-Test-Suite's draft 2020-12 files, once vendored by
+    /// `scripts/fetch-test-suites.sh`.
+    #[test]
+    #[ignore = "needs testdata/json-schema/suite from scripts/fetch-test-suites.sh"]
+    fn official_suite() {
+        let dir = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/testdata/json-schema/suite/draft2020-12"
+        );
+        let entries = match std::fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(e) => panic!("{}: {}", dir, e),
+        };
+        let mut files: Vec<String> = entries
+            .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+            .filter(|file| file.ends_with(".json"))
+            .collect();
+        files.sort();
+        for (file, _) in SUITE_UNSUPPORTED {
+            assert!(
+                files.iter().any(|f| f == file),
+                "{} is not in the suite",
+                file
+            );
+        }
+        let mut failures = Vec::new();
+        for file in &files {
+            if SUITE_UNSUPPORTED.iter().any(|(f, _)| f == file) {
+                continue;
+            }
+            let contents = match std::fs::read_to_string(format!("{}/{}", dir, file)) {
+                Ok(contents) => contents,
+                Err(e) => panic!("{}: {}", file, e),
+            };
+            failures.extend(run_file(file, &contents, SUITE_SKIPPED));
+        }
+        assert!(failures.is_empty(), "{:#?}", failures);
+    }
+
+    #[test]
+    fn reports_every_failure() {
+        let schema = match JsonSchema::parse(
+            r##"{
+                "$defs": {"port": {"type": "integer", "minimum": 1, "maximum": 65535}},
+                "type": "object",
+                "required": ["name", "ports"],
+                "properties": {
+                    "name": {"type": "string", "minLength": 1},
+                    "ports": {"type": "array", "items": {"$ref": "#/$defs/port"}}
+                },
+                "additionalProperties": false
+            }"##,
+        ) {
+            Ok(schema) => schema,
+            Err(e) => panic!("{}", e),
+        };
+        let instance = document(r#"{"name": "", "ports": [80, 0, "443"], "extra": 1}"#);
+        let errors = match schema.validate(&instance.token) {
+            Err(errors) => errors,
+            Ok(()) => panic!("expected errors"),
+        };
+        let locations: Vec<(String, String)> = errors
+            .iter()
+            .map(|e| (e.instance_path.to_string(), e.schema_path.to_string()))
+            .collect();
+        assert_eq!(
+            vec![
+                (
+                    "/name".to_string(),
+                    "/properties/name/minLength".to_string()
+                ),
+                ("/ports/1".to_string(), "/$defs/port/minimum".to_string()),
+                ("/ports/2".to_string(), "/$defs/port/type".to_string()),
+                ("/extra".to_string(), "/additionalProperties".to_string()),
+            ],
+            locations
+        );
+    }
+
+    #[test]
+    fn numeric_keywords_keep_precision() {
+        let valid = |schema: &str, instance: &str| match JsonSchema::parse(schema) {
+            Ok(schema) => schema.validate(&document(instance).token).is_ok(),
+            Err(e) => panic!("{}", e),
+        };
+        // 2^53 + 1 is 2^53 as f64.
+        let exact = cfg!(feature = "arbitrary_precision");
+        assert_eq!(
+            !exact,
+            valid(r#"{"maximum": 9007199254740992}"#, "9007199254740993")
+        );
+        assert_eq!(
+            exact,
+            valid(
+                r#"{"exclusiveMaximum": 9007199254740993}"#,
+                "9007199254740992"
+            )
+        );
+        assert!(valid(r#"{"minimum": 1e400}"#, "1e400"));
+        assert!(!valid(r#"{"maxLength": 2.0}"#, r#""abc""#));
+        assert!(valid(r#"{"maxItems": 1e400}"#, "[1, 2]"));
+    }
+
+    #[test]
+    fn rejects_unusable_schemas() {
+        for schema in [
+            r##"{"$ref": "#/$defs/missing"}"##,
+            r#"{"$ref": "other.json"}"#,
+            r#"{"pattern": "("}"#,
+            r#"{"patternProperties": {"[": {}}}"#,
+        ] {
+            assert!(JsonSchema::parse(schema).is_err(), "{}", schema);
+        }
+    }
+}
@@ -0,0 +1,753 @@
+use crate::error::SerializeError;
+use crate::serializer::{to_json_pretty, to_json_string, write_json};
+use crate::types::{Array, Number, Object, Span, ValueToken};
+use serde::Serialize;
+use serde::ser::{self, Impossible};
+use std::io;
+
+/// Serialize a value into the crate's own tree, as if it had been parsed.
+///
+/// Structs and maps become objects in field or iteration order, sequences
+/// and tuples become arrays, and `None` and `()` become `null`. Enums are
+/// written the way [`crate::from_str`] reads them: a unit variant as its
+/// name, any other variant as an object with the name as its only key.
+/// Map keys may be strings, numbers or booleans; numbers and booleans are
+/// written as their text.
+///
+/// ```
+/// use serde::Serialize;
+/// use shared_lib::{to_json_string, to_value};
+///
+/// #[derive(Serialize)]
+/// struct Reply {
+///     code: u16,
+///     message: String,
+///     retry: Option<u32>,
+/// }
+///
+/// let reply = Reply { code: 404, message: "Not Found".to_string(), retry: None };
+/// let token = to_value(&reply).unwrap();
+/// assert!(token.pointer("/retry").is_some());
+/// assert_eq!(r#"{"code":404,"message":"Not Found","retry":null}"#, to_json_string(&token));
+/// ```
+pub fn to_value<T: Serialize + ?Sized>(value: &T) -> Result<ValueToken, SerializeError> {
+    value.serialize(ValueSerializer)
+}
+
+/// Serialize a value as compact JSON text.
+///
+/// Floats that are not finite have no JSON spelling and are written as
+/// `null`, as [`to_json_string`] does for JSON5 `Infinity` and `NaN`.
+pub fn to_string<T: Serialize + ?Sized>(value: &T) -> Result<String, SerializeError> {
+    Ok(to_json_string(&to_value(value)?))
+}
+
+/// Serialize a value as JSON text indented by two spaces per level.
+pub fn to_string_pretty<T: Serialize + ?Sized>(value: &T) -> Result<String, SerializeError> {
+    Ok(to_json_pretty(&to_value(value)?, 2))
+}
+
+/// Write a value as compact JSON text.
+///
+/// The value is converted with [`to_value`] first, so a serialization
+/// error leaves `writer` untouched. The text then goes straight to `writer`
+/// without being collected in a `String`. Writes are not buffered; wrap a
+/// file or socket in an [`io::BufWriter`].
+pub fn to_writer<W: io::Write, T: Serialize + ?Sized>(
+    writer: W,
+    value: &T,
+) -> Result<(), SerializeError> {
+    write_json(writer, &to_value(value)?)?;
+    Ok(())
+}
+
+impl ser::Error for SerializeError {
+    fn custom<T: std::fmt::Display>(message: T) -> Self {
+        SerializeError::Invalid(message.to_string())
+    }
+}
+
+fn null() -> ValueToken {
+    ValueToken::NullToken {
+        skip: 0,
+        span: Span::default(),
+    }
+}
+
+fn boolean(value: bool) -> ValueToken {
+    if value {
+        ValueToken::TrueToken {
+            skip: 0,
+            span: Span::default(),
+            token: true,
+        }
+    } else {
+        ValueToken::FalseToken {
+            skip: 0,
+            span: Span::default(),
+            token: false,
+        }
+    }
+}
+
+fn number(value: f64, value_as_string: String) -> ValueToken {
+    ValueToken::NumberToken {
+        skip: 0,
+        span: Span::default(),
+        token: Number {
+            value,
+            value_as_string,
+        },
+    }
+}
+
+/// A float spelled as its shortest round-tripping decimal, or as JSON5 for
+/// the values JSON cannot spell.
+fn float(value: f64, text: String) -> ValueToken {
+    let text = if value.is_nan() {
+        "NaN".to_string()
+    } else if value.is_infinite() {
+        if value > 0.0 { "Infinity" } else { "-Infinity" }.to_string()
+    } else {
+        text
+    };
+    number(value, text)
+}
+
+fn string(value: &str) -> ValueToken {
+    ValueToken::StringToken {
+        skip: 0,
+        span: Span::default(),
+        token: value.to_string(),
+    }
+}
+
+#[allow(clippy::vec_box)]
+fn array(values: Vec<Box<ValueToken>>) -> ValueToken {
+    ValueToken::ArrayToken {
+        skip: 0,
+        span: Span::default(),
+        token: Array { values },
+    }
+}
+
+fn object(object: Object) -> ValueToken {
+    ValueToken::ObjectToken {
+        skip: 0,
+        span: Span::default(),
+        token: object,
+    }
+}
+
+/// `{"variant": value}`, the form of an enum variant with content.
+fn variant(name: &str, value: ValueToken) -> ValueToken {
+    let mut members = Object::new();
+    members.insert(name.to_string(), Box::new(value));
+    object(members)
+}
+
+macro_rules! serialize_integers {
+    ($($method:ident: $type:ty,)*) => {
+        $(
+            fn $method(self, value: $type) -> Result<ValueToken, SerializeError> {
+                Ok(number(value as f64, value.to_string()))
+            }
+        )*
+    };
+}
+
+struct ValueSerializer;
+
+impl ser::Serializer for ValueSerializer {
+    type Ok = ValueToken;
+    type Error = SerializeError;
+    type SerializeSeq = SeqSerializer;
+    type SerializeTuple = SeqSerializer;
+    type SerializeTupleStruct = SeqSerializer;
+    type SerializeTupleVariant = SeqSerializer;
+    type SerializeMap = MapSerializer;
+    type SerializeStruct = MapSerializer;
+    type SerializeStructVariant = MapSerializer;
+
+    fn serialize_bool(self, value: bool) -> Result<ValueToken, SerializeError> {
+        Ok(boolean(value))
+    }
+
+    serialize_integers! {
+        serialize_i8: i8,
+        serialize_i16: i16,
+        serialize_i32: i32,
+        serialize_i64: i64,
+        serialize_i128: i128,
+        serialize_u8: u8,
+        serialize_u16: u16,
+        serialize_u32: u32,
+        serialize_u64: u64,
+        serialize_u128: u128,
+    }
+
+    fn serialize_f32(self, value: f32) -> Result<ValueToken, SerializeError> {
+        Ok(float(value as f64, format!("{:?}", value)))
+    }
+
+    fn serialize_f64(self, value: f64) -> Result<ValueToken, SerializeError> {
+        Ok(float(value, format!("{:?}", value)))
+    }
+
+    fn serialize_char(self, value: char) -> Result<ValueToken, SerializeError> {
+        Ok(string(value.encode_utf8(&mut [0; 4])))
+    }
+
+    fn serialize_str(self, value: &str) -> Result<ValueToken, SerializeError> {
+        Ok(string(value))
+    }
+
+    fn serialize_bytes(self, value: &[u8]) -> Result<ValueToken, SerializeError> {
+        let values = value
+            .iter()
+            .map(|&byte| Box::new(number(byte as f64, byte.to_string())))
+            .collect();
+        Ok(array(values))
+    }
+
+    fn serialize_none(self) -> Result<ValueToken, SerializeError> {
+        Ok(null())
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(
+        self,
+        value: &T,
+    ) -> Result<ValueToken, SerializeError> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<ValueToken, SerializeError> {
+        Ok(null())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<ValueToken, SerializeError> {
+        Ok(null())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<ValueToken, SerializeError> {
+        Ok(string(variant))
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<ValueToken, SerializeError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        name: &'static str,
+        value: &T,
+    ) -> Result<ValueToken, SerializeError> {
+        Ok(variant(name, value.serialize(self)?))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<SeqSerializer, SerializeError> {
+        Ok(SeqSerializer {
+            values: Vec::with_capacity(len.unwrap_or(0)),
+            variant: None,
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SeqSerializer, SerializeError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<SeqSerializer, SerializeError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        name: &'static str,
+        len: usize,
+    ) -> Result<SeqSerializer, SerializeError> {
+        Ok(SeqSerializer {
+            values: Vec::with_capacity(len),
+            variant: Some(name),
+        })
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<MapSerializer, SerializeError> {
+        Ok(MapSerializer {
+            object: Object::new(),
+            key: None,
+            variant: None,
+        })
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<MapSerializer, SerializeError> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        name: &'static str,
+        _len: usize,
+    ) -> Result<MapSerializer, SerializeError> {
+        Ok(MapSerializer {
+            object: Object::new(),
+            key: None,
+            variant: Some(name),
+        })
+    }
+}
+
+/// Collects the elements of a sequence, tuple or tuple variant.
+struct SeqSerializer {
+    #[allow(clippy::vec_box)]
+    values: Vec<Box<ValueToken>>,
+    /// Name of the tuple variant being written, if any.
+    variant: Option<&'static str>,
+}
+
+impl SeqSerializer {
+    fn push<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerializeError> {
+        self.values.push(Box::new(to_value(value)?));
+        Ok(())
+    }
+
+    fn finish(self) -> ValueToken {
+        let value = array(self.values);
+        match self.variant {
+            Some(name) => variant(name, value),
+            None => value,
+        }
+    }
+}
+
+impl ser::SerializeSeq for SeqSerializer {
+    type Ok = ValueToken;
+    type Error = SerializeError;
+
+    fn serialize_element<T: Serialize + ?Sized>(
+        &mut self,
+        value: &T,
+    ) -> Result<(), SerializeError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<ValueToken, SerializeError> {
+        Ok(self.finish())
+    }
+}
+
+impl ser::SerializeTuple for SeqSerializer {
+    type Ok = ValueToken;
+    type Error = SerializeError;
+
+    fn serialize_element<T: Serialize + ?Sized>(
+        &mut self,
+        value: &T,
+    ) -> Result<(), SerializeError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<ValueToken, SerializeError> {
+        Ok(self.finish())
+    }
+}
+
+impl ser::SerializeTupleStruct for SeqSerializer {
+    type Ok = ValueToken;
+    type Error = SerializeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerializeError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<ValueToken, SerializeError> {
+        Ok(self.finish())
+    }
+}
+
+impl ser::SerializeTupleVariant for SeqSerializer {
+    type Ok = ValueToken;
+    type Error = SerializeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerializeError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<ValueToken, SerializeError> {
+        Ok(self.finish())
+    }
+}
+
+/// Collects the members of a map, struct or struct variant.
+struct MapSerializer {
+    object: Object,
+    /// Key of the map entry whose value comes next.
+    key: Option<String>,
+    /// Name of the struct variant being written, if any.
+    variant: Option<&'static str>,
+}
+
+impl MapSerializer {
+    fn insert<T: Serialize + ?Sized>(
+        &mut self,
+        key: String,
+        value: &T,
+    ) -> Result<(), SerializeError> {
+        self.object.insert(key, Box::new(to_value(value)?));
+        Ok(())
+    }
+
+    fn finish(self) -> ValueToken {
+        let value = object(self.object);
+        match self.variant {
+            Some(name) => variant(name, value),
+            None => value,
+        }
+    }
+}
+
+impl ser::SerializeMap for MapSerializer {
+    type Ok = ValueToken;
+    type Error = SerializeError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), SerializeError> {
+        self.key = Some(key.serialize(KeySerializer)?);
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerializeError> {
+        match self.key.take() {
+            Some(key) => self.insert(key, value),
+            None => Err(ser::Error::custom("map value written before its key")),
+        }
+    }
+
+    fn end(self) -> Result<ValueToken, SerializeError> {
+        Ok(self.finish())
+    }
+}
+
+impl ser::SerializeStruct for MapSerializer {
+    type Ok = ValueToken;
+    type Error = SerializeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), SerializeError> {
+        self.insert(key.to_string(), value)
+    }
+
+    fn end(self) -> Result<ValueToken, SerializeError> {
+        Ok(self.finish())
+    }
+}
+
+impl ser::SerializeStructVariant for MapSerializer {
+    type Ok = ValueToken;
+    type Error = SerializeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), SerializeError> {
+        self.insert(key.to_string(), value)
+    }
+
+    fn end(self) -> Result<ValueToken, SerializeError> {
+        Ok(self.finish())
+    }
+}
+
+fn key_error() -> SerializeError {
+    ser::Error::custom("map key must be a string, number or boolean")
+}
+
+macro_rules! serialize_key_as_text {
+    ($($method:ident: $type:ty,)*) => {
+        $(
+            fn $method(self, value: $type) -> Result<String, SerializeError> {
+                Ok(value.to_string())
+            }
+        )*
+    };
+}
+
+macro_rules! reject_key {
+    ($($method:ident($($arg:ty),*) -> $result:ty,)*) => {
+        $(
+            fn $method(self, $(_: $arg),*) -> Result<$result, SerializeError> {
+                Err(key_error())
+            }
+        )*
+    };
+}
+
+/// Writes a map key as the text of an object key.
+struct KeySerializer;
+
+impl ser::Serializer for KeySerializer {
+    type Ok = String;
+    type Error = SerializeError;
+    type SerializeSeq = Impossible<String, SerializeError>;
+    type SerializeTuple = Impossible<String, SerializeError>;
+    type SerializeTupleStruct = Impossible<String, SerializeError>;
+    type SerializeTupleVariant = Impossible<String, SerializeError>;
+    type SerializeMap = Impossible<String, SerializeError>;
+    type SerializeStruct = Impossible<String, SerializeError>;
+    type SerializeStructVariant = Impossible<String, SerializeError>;
+
+    serialize_key_as_text! {
+        serialize_bool: bool,
+        serialize_i8: i8,
+        serialize_i16: i16,
+        serialize_i32: i32,
+        serialize_i64: i64,
+        serialize_i128: i128,
+        serialize_u8: u8,
+        serialize_u16: u16,
+        serialize_u32: u32,
+        serialize_u64: u64,
+        serialize_u128: u128,
+        serialize_char: char,
+        serialize_str: &str,
+    }
+
+    reject_key! {
+        serialize_f32(f32) -> String,
+        serialize_f64(f64) -> String,
+        serialize_bytes(&[u8]) -> String,
+        serialize_none() -> String,
+        serialize_unit() -> String,
+        serialize_unit_struct(&'static str) -> String,
+        serialize_seq(Option<usize>) -> Self::SerializeSeq,
+        serialize_tuple(usize) -> Self::SerializeTuple,
+        serialize_tuple_struct(&'static str, usize) -> Self::SerializeTupleStruct,
+        serialize_tuple_variant(&'static str, u32, &'static str, usize) -> Self::SerializeTupleVariant,
+        serialize_map(Option<usize>) -> Self::SerializeMap,
+        serialize_struct(&'static str, usize) -> Self::SerializeStruct,
+        serialize_struct_variant(&'static str, u32, &'static str, usize) -> Self::SerializeStructVariant,
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<String, SerializeError> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<String, SerializeError> {
+        Ok(variant.to_string())
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<String, SerializeError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<String, SerializeError> {
+        Err(key_error())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{to_string, to_string_pretty, to_value, to_writer};
+    use crate::de::from_token;
+    use crate::error::SerializeError;
+    use serde::{Deserialize, Serialize};
+    use std::collections::{BTreeMap, HashMap};
+    use std::io;
+
+    fn text<T: Serialize + ?Sized>(value: &T) -> String {
+        match to_string(value) {
+            Ok(text) => text,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    #[serde(rename_all = "lowercase")]
+    enum Status {
+        Active,
+        Suspended { until: String },
+        Moved(u32),
+        Span(u32, u32),
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Account {
+        id: u64,
+        name: String,
+        email: Option<String>,
+        tags: Vec<String>,
+        limits: BTreeMap<String, f64>,
+        status: Vec<Status>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        note: Option<String>,
+    }
+
+    fn account() -> Account {
+        Account {
+            id: u64::MAX,
+            name: "Ada \"A\"\nL.".to_string(),
+            email: None,
+            tags: vec!["admin".to_string()],
+            limits: BTreeMap::from([("cpu".to_string(), 1.5), ("memory".to_string(), 2048.0)]),
+            status: vec![
+                Status::Active,
+                Status::Suspended {
+                    until: "2027".to_string(),
+                },
+                Status::Moved(7),
+                Status::Span(1, 2),
+            ],
+            note: None,
+        }
+    }
+
+    #[test]
+    fn writes_json_text() {
+        assert_eq!(
+            concat!(
+                r#"{"id":18446744073709551615,"name":"Ada \"A\"\nL.","email":null,"#,
+                r#""tags":["admin"],"limits":{"cpu":1.5,"memory":2048.0},"#,
+                r#""status":["active",{"suspended":{"until":"2027"}},{"moved":7},{"span":[1,2]}]}"#
+            ),
+            text(&account())
+        );
+        match to_string_pretty(&BTreeMap::from([("a", vec![1, 2]), ("b", Vec::new())])) {
+            Ok(text) => assert_eq!("{\n  \"a\": [\n    1,\n    2\n  ],\n  \"b\": []\n}", text),
+            Err(e) => panic!("{}", e),
+        }
+        assert_eq!(
+            r#"[0.1,1e300,-0.0,null,null,"x",[1,2]]"#,
+            text(&(
+                0.1f32,
+                1e300,
+                -0.0,
+                f64::NAN,
+                f64::INFINITY,
+                'x',
+                serde_bytes_like(&[1, 2])
+            ))
+        );
+        let mut written = Vec::new();
+        match to_writer(&mut written, &HashMap::from([(1u8, true)])) {
+            Ok(()) => assert_eq!(br#"{"1":true}"#.to_vec(), written),
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    #[test]
+    fn to_writer_streams() {
+        /// Accepts up to `limit` bytes, counting the `write` calls.
+        struct Limited {
+            written: Vec<u8>,
+            writes: usize,
+            limit: usize,
+        }
+        impl io::Write for Limited {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                if self.written.len() + buf.len() > self.limit {
+                    return Err(io::Error::other("disk full"));
+                }
+                self.writes += 1;
+                self.written.extend_from_slice(buf);
+                Ok(buf.len())
+            }
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+        let value = account();
+        let mut writer = Limited {
+            written: Vec::new(),
+            writes: 0,
+            limit: usize::MAX,
+        };
+        if let Err(e) = to_writer(&mut writer, &value) {
+            panic!("{}", e);
+        }
+        assert_eq!(text(&value).into_bytes(), writer.written);
+        assert!(writer.writes > 1);
+
+        let mut writer = Limited {
+            written: Vec::new(),
+            writes: 0,
+            limit: 8,
+        };
+        match to_writer(&mut writer, &value) {
+            Err(SerializeError::Io(e)) => assert_eq!("disk full", e.to_string()),
+            other => panic!("Expected I/O error, got {:?}", other),
+        }
+        assert!(writer.written.len() <= 8);
+    }
+
+    /// Bytes as serde's `serialize_bytes` sees them.
+    fn serde_bytes_like(bytes: &[u8]) -> impl Serialize + '_ {
+        struct Bytes<'a>(&'a [u8]);
+        impl Serialize for Bytes<'_> {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.serialize_bytes(self.0)
+            }
+        }
+        Bytes(bytes)
+    }
+
+    #[test]
+    fn round_trips_through_the_tree() {
+        let token = match to_value(&account()) {
+            Ok(token) => token,
+            Err(e) => panic!("{}", e),
+        };
+        match from_token::<Account>(&token) {
+            Ok(account_back) => assert_eq!(account(), account_back),
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    #[test]
+    fn rejects_keys_json_cannot_spell() {
+        let by_pair = HashMap::from([((1, 2), "x")]);
+        match to_string(&by_pair) {
+            Ok(text) => panic!("expected an error, got {}", text),
+            Err(e) => assert_eq!("map key must be a string, number or boolean", e.to_string()),
+        }
+    }
+}
@@ -0,0 +1,586 @@
+use crate::error::PathSegment;
+use crate::types::{Comment, CommentKind, CommentPlacement, Json, ValueToken};
+use std::io;
+
+/// Serialize a token tree as compact RFC 8259 JSON text.
+///
+/// Numbers are written from `value_as_string`, so they round-trip exactly.
+/// JSON5 `Infinity` and `NaN` have no JSON spelling and are written as `null`.
+/// A bare `PairToken` is written as a single-member object.
+pub fn to_json_string(token: &ValueToken) -> String {
+    let mut writer = Writer::new(String::new(), None, &[]);
+    writer.value(token, 0);
+    writer.out
+}
+
+/// Write a token tree as compact JSON text like [`to_json_string`], straight
+/// to `writer` rather than into a `String` first.
+#[cfg_attr(not(feature = "serde"), allow(dead_code))]
+pub(crate) fn write_json<W: io::Write>(writer: W, token: &ValueToken) -> io::Result<()> {
+    let mut writer = Writer::new(
+        IoOutput {
+            writer,
+            error: None,
+        },
+        None,
+        &[],
+    );
+    writer.value(token, 0);
+    match writer.out.error {
+        Some(e) => Err(e),
+        None => Ok(()),
+    }
+}
+
+/// Serialize a token tree as JSON text with each nested value on its own
+/// line, indented by `indent` spaces per level.
+pub fn to_json_pretty(token: &ValueToken, indent: usize) -> String {
+    let mut writer = Writer::new(String::new(), Some(indent), &[]);
+    writer.value(token, 0);
+    writer.out
+}
+
+/// Serialize a parsed document like [`to_json_pretty`], writing back the
+/// comments kept by [`crate::ParseOptions::preserve_comments`] next to the
+/// values they are attached to. With any comments the output is JSONC.
+///
+/// Comments follow their value by path, so they survive edits to the tree
+/// as long as the member or element they belong to is still there.
+pub fn to_json_pretty_with_comments(json: &Json, indent: usize) -> String {
+    let mut writer = Writer::new(String::new(), Some(indent), &json.comments);
+    writer.leading(0);
+    writer.value(&json.token, 0);
+    writer.trailing();
+    writer.after();
+    writer.out
+}
+
+/// Where a [`Writer`] puts its text.
+trait Output {
+    fn push(&mut self, ch: char);
+    fn push_str(&mut self, text: &str);
+}
+
+impl Output for String {
+    fn push(&mut self, ch: char) {
+        String::push(self, ch);
+    }
+
+    fn push_str(&mut self, text: &str) {
+        String::push_str(self, text);
+    }
+}
+
+/// Output to an [`io::Write`]. The first error is kept and later text is
+/// dropped, so the writer need not check after every piece.
+struct IoOutput<W> {
+    writer: W,
+    error: Option<io::Error>,
+}
+
+impl<W: io::Write> Output for IoOutput<W> {
+    fn push(&mut self, ch: char) {
+        self.push_str(ch.encode_utf8(&mut [0; 4]));
+    }
+
+    fn push_str(&mut self, text: &str) {
+        if self.error.is_none()
+            && let Err(e) = self.writer.write_all(text.as_bytes())
+        {
+            self.error = Some(e);
+        }
+    }
+}
+
+struct Writer<'a, O> {
+    out: O,
+    indent: Option<usize>,
+    comments: &'a [Comment],
+    /// Path to the value being written, tracked only when there are comments.
+    path: Vec<PathSegment>,
+}
+
+impl<'a, O: Output> Writer<'a, O> {
+    fn new(out: O, indent: Option<usize>, comments: &'a [Comment]) -> Self {
+        Writer {
+            out,
+            indent,
+            comments,
+            path: Vec::new(),
+        }
+    }
+
+    fn value(&mut self, token: &ValueToken, level: usize) {
+        match token {
+            ValueToken::ArrayToken { token: array, .. } => {
+                if array.values.is_empty() && !self.has_inner_comments() {
+                    self.out.push_str("[]");
+                    return;
+                }
+                self.out.push('[');
+                for (i, value) in array.values.iter().enumerate() {
+                    self.enter(|| PathSegment::Index(i));
+                    self.newline(level + 1);
+                    self.leading(level + 1);
+                    self.value(value, level + 1);
+                    if i + 1 < array.values.len() {
+                        self.out.push(',');
+                    }
+                    self.trailing();
+                    self.leave();
+                }
+                self.inner(level + 1);
+                self.newline(level);
+                self.out.push(']');
+            }
+            ValueToken::ObjectToken { token: object, .. } => {
+                if object.is_empty() && !self.has_inner_comments() {
+                    self.out.push_str("{}");
+                    return;
+                }
+                self.out.push('{');
+                for (i, pair) in object.iter().enumerate() {
+                    self.enter(|| PathSegment::Key(pair.key.clone()));
+                    self.newline(level + 1);
+                    self.leading(level + 1);
+                    self.member(&pair.key, &pair.value, level + 1);
+                    if i + 1 < object.len() {
+                        self.out.push(',');
+                    }
+                    self.trailing();
+                    self.leave();
+                }
+                self.inner(level + 1);
+                self.newline(level);
+                self.out.push('}');
+            }
+            ValueToken::PairToken { token: pair, .. } => {
+                self.out.push('{');
+                self.newline(level + 1);
+                self.member(&pair.key, &pair.value, level + 1);
+                self.newline(level);
+                self.out.push('}');
+            }
+            ValueToken::StringToken { token: string, .. } => write_string(&mut self.out, string),
+            ValueToken::NumberToken { token: number, .. } => {
+                // Every JSON number ends in a digit; `Infinity` and `NaN` do not.
+                if number
+                    .value_as_string
+                    .ends_with(|c: char| c.is_ascii_digit())
+                {
+                    self.out.push_str(&number.value_as_string);
+                } else {
+                    self.out.push_str("null");
+                }
+            }
+            ValueToken::TrueToken { .. } => self.out.push_str("true"),
+            ValueToken::FalseToken { .. } => self.out.push_str("false"),
+            ValueToken::NullToken { .. } => self.out.push_str("null"),
+        }
+    }
+
+    fn member(&mut self, key: &str, value: &ValueToken, level: usize) {
+        write_string(&mut self.out, key);
+        self.out.push(':');
+        let mut space = self.indent.is_some();
+        for comment in self.attached(CommentPlacement::AfterKey) {
+            self.out.push(' ');
+            write_comment(&mut self.out, comment);
+            // A line comment runs to the end of the line.
+            space = comment.kind == CommentKind::Block;
+            if !space {
+                self.newline(level);
+            }
+        }
+        if space {
+            self.out.push(' ');
+        }
+        self.value(value, level);
+    }
+
+    fn newline(&mut self, level: usize) {
+        if let Some(indent) = self.indent {
+            self.out.push('\n');
+            for _ in 0..indent * level {
+                self.out.push(' ');
+            }
+        }
+    }
+
+    fn enter(&mut self, segment: impl FnOnce() -> PathSegment) {
+        if !self.comments.is_empty() {
+            self.path.push(segment());
+        }
+    }
+
+    fn leave(&mut self) {
+        self.path.pop();
+    }
+
+    fn attached(&self, placement: CommentPlacement) -> Vec<&'a Comment> {
+        self.comments
+            .iter()
+            .filter(|comment| comment.placement == placement && comment.path == self.path)
+            .collect()
+    }
+
+    fn has_inner_comments(&self) -> bool {
+        !self.attached(CommentPlacement::Inner).is_empty()
+    }
+
+    /// Comments on their own lines before the current value.
+    fn leading(&mut self, level: usize) {
+        for comment in self.attached(CommentPlacement::Leading) {
+            write_comment(&mut self.out, comment);
+            self.newline(level);
+        }
+    }
+
+    /// Comments after the current value on the same line.
+    fn trailing(&mut self) {
+        for comment in self.attached(CommentPlacement::Trailing) {
+            self.out.push(' ');
+            write_comment(&mut self.out, comment);
+        }
+    }
+
+    /// Comments on their own lines after the root value.
+    fn after(&mut self) {
+        for comment in self.attached(CommentPlacement::After) {
+            self.newline(0);
+            write_comment(&mut self.out, comment);
+        }
+    }
+
+    /// Comments on their own lines before the closing bracket.
+    fn inner(&mut self, level: usize) {
+        for comment in self.attached(CommentPlacement::Inner) {
+            self.newline(level);
+            write_comment(&mut self.out, comment);
+        }
+    }
+}
+
+fn write_comment(out: &mut impl Output, comment: &Comment) {
+    match comment.kind {
+        CommentKind::Line => {
+            out.push_str("//");
+            out.push_str(&comment.text);
+        }
+        CommentKind::Block => {
+            out.push_str("/*");
+            out.push_str(&comment.text);
+            out.push_str("*/");
+        }
+    }
+}
+
+fn write_string(out: &mut impl Output, string: &str) {
+    out.push('"');
+    for ch in string.chars() {
+        match ch {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\u{8}' => out.push_str("\\b"),
+            '\u{c}' => out.push_str("\\f"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            '\u{0}'..='\u{1f}' => out.push_str(&format!("\\u{:04x}", ch as u32)),
+            _ => out.push(ch),
+        }
+    }
+    out.push('"');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{to_json_pretty, to_json_pretty_with_comments, to_json_string};
+    use crate::json::{parse, parse_with_options};
+    use crate::options::{Dialect, ParseOptions};
+    use crate::types::{Array, Number, Object, Pair, Span, ValueToken};
+    use proptest::prelude::*;
+
+    fn roundtrip(input: &str) -> String {
+        match parse(input) {
+            Ok(json) => to_json_string(&json.token),
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    #[test]
+    fn compact_all_types() {
+        let input = r#" { "s" : "" , "n" : 0 , "b" : true , "f" : false , "l" : null , "o" : { } , "a" : [ 1 , [ ] ] } "#;
+        assert_eq!(
+            r#"{"s":"","n":0,"b":true,"f":false,"l":null,"o":{},"a":[1,[]]}"#,
+            roundtrip(input)
+        );
+    }
+
+    #[test]
+    fn numbers_keep_source_text() {
+        let input = "[1.50, -0, 1E+2, 12345678901234567890]";
+        assert_eq!("[1.50,-0,1e+2,12345678901234567890]", roundtrip(input));
+    }
+
+    #[test]
+    fn json5_numbers_written_as_json() {
+        let options = ParseOptions {
+            dialect: Dialect::Json5,
+            ..ParseOptions::default()
+        };
+        let json = parse_with_options("[0x1F, +1, .5, 5., Infinity, -Infinity, NaN]", &options);
+        assert_eq!(
+            "[31,1,0.5,5,null,null,null]",
+            to_json_string(&json.unwrap().token)
+        );
+    }
+
+    #[test]
+    fn comments_written_back() {
+        let options = ParseOptions {
+            dialect: Dialect::Jsonc,
+            preserve_comments: true,
+            ..ParseOptions::default()
+        };
+        let input = "// tsconfig
+{
+  \"compilerOptions\": {
+    /* language */
+    \"target\": \"es2022\", // bump yearly
+    \"strict\": true
+  },
+  \"exclude\": [
+    // nothing yet
+  ]
+}
+";
+        let mut json = parse_with_options(input, &options).unwrap();
+        assert_eq!(input.trim_end(), to_json_pretty_with_comments(&json, 2));
+
+        if let ValueToken::ObjectToken { token, .. } = &mut *json.token {
+            token.remove("exclude");
+            token.insert(
+                "files".to_string(),
+                Box::new(ValueToken::NullToken {
+                    skip: 0,
+                    span: Span::default(),
+                }),
+            );
+        }
+        assert_eq!(
+            "// tsconfig\n{\n  \"compilerOptions\": {\n    /* language */\n    \"target\": \"es2022\", // bump yearly\n    \"strict\": true\n  },\n  \"files\": null\n}",
+            to_json_pretty_with_comments(&json, 2)
+        );
+    }
+
+    #[test]
+    fn comments_after_keys_and_root_written_back() {
+        let options = ParseOptions {
+            dialect: Dialect::Jsonc,
+            preserve_comments: true,
+            ..ParseOptions::default()
+        };
+        for input in [
+            "{\n  \"a\": /* one */ 1,\n  \"b\": // two\n  {\n    \"c\": 3\n  }\n} // end\n// after\n/* more */",
+            "[\n  1\n]\n// after",
+        ] {
+            let json = parse_with_options(input, &options).unwrap();
+            let output = to_json_pretty_with_comments(&json, 2);
+            assert_eq!(input, output);
+            assert_eq!(
+                json.comments
+                    .iter()
+                    .map(|comment| (&comment.path, comment.placement))
+                    .collect::<Vec<_>>(),
+                parse_with_options(&output, &options)
+                    .unwrap()
+                    .comments
+                    .iter()
+                    .map(|comment| (&comment.path, comment.placement))
+                    .collect::<Vec<_>>()
+            );
+        }
+    }
+
+    #[test]
+    fn strings_are_escaped() {
+        let token = ValueToken::StringToken {
+            skip: 0,
+            span: Span::default(),
+            token: "quote\" backslash\\ slash/ \u{8}\u{c}\n\r\t \u{0}\u{1f} é😀".to_string(),
+        };
+        assert_eq!(
+            r#""quote\" backslash\\ slash/ \b\f\n\r\t \u0000\u001f é😀""#,
+            to_json_string(&token)
+        );
+    }
+
+    #[test]
+    fn pretty_layout() {
+        let json = parse(r#"{"a":[1,{"b":null}],"c":{},"d":[]}"#).unwrap();
+        assert_eq!(
+            "{\n  \"a\": [\n    1,\n    {\n      \"b\": null\n    }\n  ],\n  \"c\": {},\n  \"d\": []\n}",
+            to_json_pretty(&json.token, 2)
+        );
+    }
+
+    #[test]
+    fn pretty_scalar() {
+        let json = parse("\"x\"").unwrap();
+        assert_eq!("\"x\"", to_json_pretty(&json.token, 4));
+    }
+
+    #[test]
+    fn pair_as_object() {
+        let token = ValueToken::PairToken {
+            skip: 0,
+            span: Span::default(),
+            token: Pair::new(
+                "k".to_string(),
+                Box::new(ValueToken::NullToken {
+                    skip: 0,
+                    span: Span::default(),
+                }),
+            ),
+        };
+        assert_eq!(r#"{"k":null}"#, to_json_string(&token));
+    }
+
+    fn number_strategy() -> impl Strategy<Value = String> {
+        prop_oneof![
+            any::<i64>().prop_map(|n| n.to_string()),
+            any::<f64>()
+                .prop_filter("finite", |n| n.is_finite())
+                .prop_map(|n| format!("{:e}", n)),
+        ]
+    }
+
+    fn token_strategy() -> impl Strategy<Value = ValueToken> {
+        let leaf = prop_oneof![
+            Just(ValueToken::NullToken {
+                skip: 0,
+                span: Span::default(),
+            }),
+            any::<bool>().prop_map(|b| if b {
+                ValueToken::TrueToken {
+                    skip: 0,
+                    span: Span::default(),
+                    token: b,
+                }
+            } else {
+                ValueToken::FalseToken {
+                    skip: 0,
+                    span: Span::default(),
+                    token: b,
+                }
+            }),
+            number_strategy().prop_map(|s| ValueToken::NumberToken {
+                skip: 0,
+                span: Span::default(),
+                token: Number {
+                    value: s.parse().unwrap(),
+                    value_as_string: s,
+                },
+            }),
+            any::<String>().prop_map(|s| ValueToken::StringToken {
+                skip: 0,
+                span: Span::default(),
+                token: s,
+            }),
+        ];
+        leaf.prop_recursive(4, 64, 8, |inner| {
+            prop_oneof![
+                prop::collection::vec(inner.clone(), 0..8).prop_map(|values| {
+                    ValueToken::ArrayToken {
+                        skip: 0,
+                        span: Span::default(),
+                        token: Array {
+                            values: values.into_iter().map(Box::new).collect(),
+                        },
+                    }
+                }),
+                prop::collection::vec((any::<String>(), inner), 0..8).prop_map(|members| {
+                    ValueToken::ObjectToken {
+                        skip: 0,
+                        span: Span::default(),
+                        token: members
+                            .into_iter()
+                            .map(|(key, value)| Pair::new(key, Box::new(value)))
+                            .collect::<Object>(),
+                    }
+                }),
+            ]
+        })
+    }
+
+    /// The tree with every span and skip reset, as a generated tree has them.
+    fn without_spans(token: &ValueToken) -> ValueToken {
+        let (skip, span) = (0, Span::default());
+        match token {
+            ValueToken::ArrayToken { token, .. } => ValueToken::ArrayToken {
+                skip,
+                span,
+                token: Array {
+                    values: token
+                        .values
+                        .iter()
+                        .map(|value| Box::new(without_spans(value)))
+                        .collect(),
+                },
+            },
+            ValueToken::ObjectToken { token, .. } => ValueToken::ObjectToken {
+                skip,
+                span,
+                token: token
+                    .iter()
+                    .map(|pair| Pair::new(pair.key.clone(), Box::new(without_spans(&pair.value))))
+                    .collect(),
+            },
+            ValueToken::PairToken { token, .. } => ValueToken::PairToken {
+                skip,
+                span,
+                token: Pair::new(token.key.clone(), Box::new(without_spans(&token.value))),
+            },
+            ValueToken::FalseToken { token, .. } => ValueToken::FalseToken {
+                skip,
+                span,
+                token: *token,
+            },
+            ValueToken::TrueToken { token, .. } => ValueToken::TrueToken {
+                skip,
+                span,
+                token: *token,
+            },
+            ValueToken::NullToken { .. } => ValueToken::NullToken { skip, span },
+            ValueToken::NumberToken { token, .. } => ValueToken::NumberToken {
+                skip,
+                span,
+                token: token.clone(),
+            },
+            ValueToken::StringToken { token, .. } => ValueToken::StringToken {
+                skip,
+                span,
+                token: token.clone(),
+            },
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn compact_roundtrip(token in token_strategy()) {
+            let text = to_json_string(&token);
+            let parsed = parse(&text).map_err(|e| TestCaseError::fail(e.to_string()))?;
+            prop_assert_eq!(&token, &without_spans(&parsed.token));
+            prop_assert_eq!(text, to_json_string(&parsed.token));
+        }
+
+        #[test]
+        fn pretty_roundtrip(token in token_strategy(), indent in 0usize..5) {
+            let pretty = to_json_pretty(&token, indent);
+            let parsed = parse(&pretty).map_err(|e| TestCaseError::fail(e.to_string()))?;
+            prop_assert_eq!(&token, &without_spans(&parsed.token));
+            prop_assert_eq!(pretty, to_json_pretty(&parsed.token, indent));
+        }
+    }
+}
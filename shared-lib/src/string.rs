@@ -1,5 +1,7 @@
-use crate::types::ValueToken;
-use regex::Regex;
+use crate::cursor::Cursor;
+use crate::error::{ErrorKind, ParseError};
+use crate::options::{Dialect, LoneSurrogates};
+use crate::types::{RawString, Span, ValueToken};
 
 #[derive(PartialEq)]
 enum Mode {
@@ -10,95 +12,108 @@ enum Mode {
     End,
 }
 
-lazy_static! {
-    static ref WHITESPACE: Regex = Regex::new(r"[ \n\r\t]").unwrap();
-}
-
-pub fn parse_string(string: &str) -> Result<ValueToken, &'static str> {
+pub fn parse_string(cursor: &mut Cursor) -> Result<ValueToken, ParseError> {
     let mut mode = Mode::Scanning;
-    let mut pos: usize = 0;
+    let start = cursor.chars();
+    let mut span_start = cursor.position();
     let mut token = String::new();
+    let mut quote = '"';
+    // Byte offsets in `token` of replacement characters standing for lone
+    // surrogates, with the surrogates themselves.
+    let mut surrogates: Vec<(usize, u32)> = Vec::new();
 
-    while let Some(ch) = string.chars().nth(pos) {
-        let char = &ch.to_string()[..];
-
+    while let Some(ch) = cursor.peek() {
         match mode {
             Mode::Scanning => {
-                if WHITESPACE.is_match(char) {
-                    pos += 1;
-                } else if ch == '"' {
-                    pos += 1;
+                if cursor.is_trivia(ch) {
+                    cursor.skip_trivia(ch)?;
+                } else if ch == '"' || (ch == '\'' && cursor.dialect() == Dialect::Json5) {
+                    span_start = cursor.position();
+                    quote = ch;
+                    cursor.advance(ch);
                     mode = Mode::Character;
                 } else {
-                    return Err("Expected '\"'");
+                    return Err(ParseError::at(ErrorKind::Expected("'\"'"), cursor.pos()));
                 }
             }
             Mode::Character => {
                 if ch == '\\' {
-                    pos += 1;
+                    cursor.advance(ch);
                     mode = Mode::EscapedCharacter;
-                } else if ch == '"' {
-                    pos += 1;
+                } else if ch == quote {
+                    cursor.advance(ch);
                     mode = Mode::End;
-                } else if ch != '\n' && ch != '\r' {
-                    pos += 1;
-                    token.push_str(char);
+                } else if ch > '\u{1f}'
+                    || cursor.options().allow_control_characters
+                    || (cursor.dialect() == Dialect::Json5 && ch != '\n' && ch != '\r')
+                {
+                    cursor.advance(ch);
+                    token.push(ch);
                 } else {
-                    return Err("Unexpected character");
+                    return Err(ParseError::at(ErrorKind::ControlCharacter, cursor.pos()));
                 }
             }
             Mode::EscapedCharacter => {
                 if ch == '"' || ch == '\\' || ch == '/' {
-                    pos += 1;
-                    token.push_str(char);
+                    cursor.advance(ch);
+                    token.push(ch);
                     mode = Mode::Character;
                 } else if ch == 'b' {
-                    pos += 1;
+                    cursor.advance(ch);
                     token.push('\u{8}'); // no \b in Rust
                     mode = Mode::Character;
                 } else if ch == 'f' {
-                    pos += 1;
+                    cursor.advance(ch);
                     token.push('\u{000c}'); // no \f in Rust
                     mode = Mode::Character;
                 } else if ch == 'n' {
-                    pos += 1;
+                    cursor.advance(ch);
                     token.push('\n');
                     mode = Mode::Character;
                 } else if ch == 'r' {
-                    pos += 1;
+                    cursor.advance(ch);
                     token.push('\r');
                     mode = Mode::Character;
                 } else if ch == 't' {
-                    pos += 1;
+                    cursor.advance(ch);
                     token.push('\t');
                     mode = Mode::Character;
                 } else if ch == 'u' {
-                    pos += 1;
+                    cursor.advance(ch);
                     mode = Mode::Unicode;
+                } else if cursor.dialect() == Dialect::Json5 {
+                    parse_json5_escape(cursor, ch, &mut token)?;
+                    mode = Mode::Character;
                 } else {
-                    return Err("Unexpected escape characxter");
+                    return Err(ParseError::at(
+                        ErrorKind::UnexpectedEscapeCharacter,
+                        cursor.pos(),
+                    ));
                 }
             }
             Mode::Unicode => {
-                // Ensure there are at least 4 hex digits
-                if string.len() < pos + 4 {
-                    return Err("Invalid unicode escape: too short");
+                let pos = cursor.pos();
+                let mut codepoint = parse_hex(cursor, 4)
+                    .ok_or_else(|| ParseError::at(ErrorKind::InvalidUnicodeEscape, pos))?;
+                if (0xD800..0xDC00).contains(&codepoint)
+                    && let Some(low) = parse_low_surrogate(cursor)
+                {
+                    codepoint = 0x10000 + ((codepoint - 0xD800) << 10) + (low - 0xDC00);
                 }
-                let slice: String = string.chars().skip(pos).take(4).collect();
-                if !slice.chars().all(|c| c.is_ascii_hexdigit()) {
-                    return Err("Invalid unicode escape: non-hex digit");
+                match char::from_u32(codepoint) {
+                    Some(unicode_char) => token.push(unicode_char),
+                    None => match cursor.options().lone_surrogates {
+                        LoneSurrogates::Error => {
+                            // Point at the backslash that starts the escape.
+                            return Err(ParseError::at(ErrorKind::LoneSurrogate, pos - 2));
+                        }
+                        LoneSurrogates::Replace => token.push(char::REPLACEMENT_CHARACTER),
+                        LoneSurrogates::Preserve => {
+                            surrogates.push((token.len(), codepoint));
+                            token.push(char::REPLACEMENT_CHARACTER);
+                        }
+                    },
                 }
-                // Convert hex to char
-                if let Ok(codepoint) = u32::from_str_radix(&slice, 16) {
-                    if let Some(unicode_char) = std::char::from_u32(codepoint) {
-                        token.push(unicode_char);
-                    } else {
-                        return Err("Invalid unicode codepoint");
-                    }
-                } else {
-                    return Err("Invalid unicode escape");
-                }
-                pos += 4;
                 mode = Mode::Character;
             }
             Mode::End => break,
@@ -106,16 +121,114 @@ pub fn parse_string(string: &str) -> Result<ValueToken, &'static str> {
     }
     // If we didn't reach Mode::End, string was unterminated
     if mode != Mode::End {
-        return Err("Unterminated string");
+        return Err(ParseError::at(ErrorKind::UnterminatedString, cursor.pos()));
+    }
+    let span = Span {
+        start: span_start,
+        end: cursor.position(),
+    };
+    if !surrogates.is_empty() {
+        cursor.push_raw_string(RawString {
+            span,
+            wtf8: wtf8(&token, &surrogates),
+        });
+    }
+    Ok(ValueToken::StringToken {
+        skip: cursor.chars() - start,
+        span,
+        token,
+    })
+}
+
+/// Re-encode `token` as WTF-8, putting each surrogate back in place of its
+/// replacement character. Both take three bytes.
+fn wtf8(token: &str, surrogates: &[(usize, u32)]) -> Vec<u8> {
+    let mut bytes = token.as_bytes().to_vec();
+    for &(offset, codepoint) in surrogates {
+        bytes[offset..offset + 3].copy_from_slice(&[
+            0xE0 | (codepoint >> 12) as u8,
+            0x80 | ((codepoint >> 6) & 0x3F) as u8,
+            0x80 | (codepoint & 0x3F) as u8,
+        ]);
     }
-    Ok(ValueToken::StringToken { skip: pos, token })
+    bytes
+}
+
+/// Escapes JSON5 adds to JSON's: `\'`, `\v`, `\0`, `\xHH`, line
+/// continuations, and any other non-digit character standing for itself.
+fn parse_json5_escape(cursor: &mut Cursor, ch: char, token: &mut String) -> Result<(), ParseError> {
+    let pos = cursor.pos();
+    cursor.advance(ch);
+    match ch {
+        'v' => token.push('\u{b}'),
+        '0' if !cursor.peek().is_some_and(|next| next.is_ascii_digit()) => token.push('\0'),
+        '0'..='9' => return Err(ParseError::at(ErrorKind::UnexpectedEscapeCharacter, pos)),
+        'x' => {
+            let digits = cursor.pos();
+            let codepoint = parse_hex(cursor, 2)
+                .ok_or_else(|| ParseError::at(ErrorKind::InvalidUnicodeEscape, digits))?;
+            // Two hex digits never reach the surrogate range.
+            token.extend(char::from_u32(codepoint));
+        }
+        '\r' => {
+            if cursor.peek() == Some('\n') {
+                cursor.advance('\n');
+            }
+        }
+        '\n' | '\u{2028}' | '\u{2029}' => {}
+        _ => token.push(ch),
+    }
+    Ok(())
+}
+
+/// Consume exactly `len` hex digits, or nothing if they are not all present.
+fn parse_hex(cursor: &mut Cursor, len: usize) -> Option<u32> {
+    let digits = cursor.input().get(cursor.pos()..cursor.pos() + len)?;
+    if !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    let codepoint = u32::from_str_radix(digits, 16).ok()?;
+    cursor.eat(digits);
+    Some(codepoint)
+}
+
+/// Consume a `\uXXXX` escape if it encodes a low surrogate, completing the
+/// pair started by the escape just read.
+fn parse_low_surrogate(cursor: &mut Cursor) -> Option<u32> {
+    let escape = cursor.input().get(cursor.pos()..cursor.pos() + 6)?;
+    let digits = escape.strip_prefix("\\u")?;
+    if !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    let low = u32::from_str_radix(digits, 16).ok()?;
+    if !(0xDC00..0xE000).contains(&low) {
+        return None;
+    }
+    cursor.eat(escape);
+    Some(low)
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::error::ErrorKind;
     use crate::json;
+    use crate::options::{Dialect, LoneSurrogates, ParseOptions};
     use crate::types::{Json, ValueToken};
 
+    fn decode(input: &str, lone_surrogates: LoneSurrogates) -> Result<String, ErrorKind> {
+        let options = ParseOptions {
+            lone_surrogates,
+            ..ParseOptions::default()
+        };
+        match json::parse_with_options(input, &options) {
+            Ok(Json { token, .. }) => match *token {
+                ValueToken::StringToken { token, .. } => Ok(token),
+                _ => panic!("Expected StringToken"),
+            },
+            Err(e) => Err(e.kind),
+        }
+    }
+
     #[test]
     fn test_hello_world() {
         let input = "\"Hello, world!\"";
@@ -123,12 +236,12 @@ mod tests {
         let expected_token_skip = 15;
         let expected_token = "Hello, world!";
         match json::parse(input) {
-            Ok(Json { skip, token }) => {
+            Ok(Json { skip, token, .. }) => {
                 assert_eq!(expected_json_skip, skip);
 
                 let unboxed = *token;
                 match unboxed {
-                    ValueToken::StringToken { skip, token } => {
+                    ValueToken::StringToken { skip, token, .. } => {
                         assert_eq!(expected_token_skip, skip);
                         assert_eq!(expected_token, token);
                     }
@@ -150,12 +263,12 @@ mod tests {
         let expected_token_skip = 4;
         let expected_token = "\"";
         match json::parse(input) {
-            Ok(Json { skip, token }) => {
+            Ok(Json { skip, token, .. }) => {
                 assert_eq!(expected_json_skip, skip);
 
                 let unboxed = *token;
                 match unboxed {
-                    ValueToken::StringToken { skip, token } => {
+                    ValueToken::StringToken { skip, token, .. } => {
                         assert_eq!(expected_token_skip, skip);
                         assert_eq!(expected_token, token);
                     }
@@ -177,12 +290,12 @@ mod tests {
         let expected_token_skip = 2;
         let expected_token = "";
         match json::parse(input) {
-            Ok(Json { skip, token }) => {
+            Ok(Json { skip, token, .. }) => {
                 assert_eq!(expected_json_skip, skip);
 
                 let unboxed = *token;
                 match unboxed {
-                    ValueToken::StringToken { skip, token } => {
+                    ValueToken::StringToken { skip, token, .. } => {
                         assert_eq!(expected_token_skip, skip);
                         assert_eq!(expected_token, token);
                     }
@@ -204,12 +317,12 @@ mod tests {
         let expected_token_skip = 2;
         let expected_token = "";
         match json::parse(input) {
-            Ok(Json { skip, token }) => {
+            Ok(Json { skip, token, .. }) => {
                 assert_eq!(expected_json_skip, skip);
 
                 let unboxed = *token;
                 match unboxed {
-                    ValueToken::StringToken { skip, token } => {
+                    ValueToken::StringToken { skip, token, .. } => {
                         assert_eq!(expected_token_skip, skip);
                         assert_eq!(expected_token, token);
                     }
@@ -265,4 +378,204 @@ mod tests {
             panic!("Should have failed");
         }
     }
+
+    #[test]
+    fn test_control_characters_rejected() {
+        for ch in ['\u{0}', '\t', '\n', '\r', '\u{b}', '\u{1b}', '\u{1f}'] {
+            let input = format!("\"a{}b\"", ch);
+            match json::parse(&input) {
+                Ok(_) => panic!("{:?} should not parse", input),
+                Err(e) => {
+                    assert_eq!(ErrorKind::ControlCharacter, e.kind);
+                    assert_eq!(2, e.offset);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_control_characters_allowed() {
+        let options = ParseOptions {
+            allow_control_characters: true,
+            ..ParseOptions::default()
+        };
+        match json::parse_with_options("\"a\tb\u{0}\nc\"", &options) {
+            Ok(Json { token, .. }) => match *token {
+                ValueToken::StringToken { token, .. } => assert_eq!("a\tb\u{0}\nc", token),
+                _ => panic!("Expected StringToken"),
+            },
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    #[test]
+    fn test_delete_and_escaped_controls_accepted() {
+        assert_eq!(
+            Ok("\u{7f}\t\u{1}".to_string()),
+            decode("\"\u{7f}\\t\\u0001\"", LoneSurrogates::Error)
+        );
+    }
+
+    #[test]
+    fn test_surrogate_pair_emoji() {
+        assert_eq!(
+            Ok("\u{1F600}".to_string()),
+            decode(r#""\uD83D\uDE00""#, LoneSurrogates::Error)
+        );
+        assert_eq!(
+            Ok("a\u{1F44D}b\u{1D11E}".to_string()),
+            decode(r#""a\ud83d\udc4db\uD834\uDD1E""#, LoneSurrogates::Error)
+        );
+    }
+
+    #[test]
+    fn test_raw_emoji_and_cjk() {
+        assert_eq!(
+            Ok("😀 中文 日本語".to_string()),
+            decode("\"😀 中文 日本語\"", LoneSurrogates::Error)
+        );
+    }
+
+    #[test]
+    fn test_escaped_cjk() {
+        assert_eq!(
+            Ok("中文".to_string()),
+            decode(r#""\u4E2D\u6587""#, LoneSurrogates::Error)
+        );
+    }
+
+    #[test]
+    fn test_lone_surrogates_error() {
+        for input in [
+            r#""\uD800""#,
+            r#""\uDE00""#,
+            r#""\uD83Dx""#,
+            r#""\uD83D\u0041""#,
+            r#""\uD800\uD800\uDC00""#,
+            r#""\uDE00\uD83D""#,
+        ] {
+            assert_eq!(
+                Err(ErrorKind::LoneSurrogate),
+                decode(input, LoneSurrogates::Error),
+                "{}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_lone_surrogate_position() {
+        match json::parse(r#"["ok", "ab\uD800"]"#) {
+            Ok(_) => panic!("Should have failed"),
+            Err(e) => {
+                assert_eq!(ErrorKind::LoneSurrogate, e.kind);
+                assert_eq!(10, e.offset);
+            }
+        }
+    }
+
+    #[test]
+    fn test_lone_surrogates_replace() {
+        assert_eq!(
+            Ok("\u{FFFD}x".to_string()),
+            decode(r#""\uD83Dx""#, LoneSurrogates::Replace)
+        );
+        assert_eq!(
+            Ok("\u{FFFD}A".to_string()),
+            decode(r#""\uD83D\u0041""#, LoneSurrogates::Replace)
+        );
+        assert_eq!(
+            Ok("\u{FFFD}\u{10000}".to_string()),
+            decode(r#""\uD800\uD800\uDC00""#, LoneSurrogates::Replace)
+        );
+        assert_eq!(
+            Ok("\u{FFFD}".to_string()),
+            decode(r#""\uDE00""#, LoneSurrogates::Replace)
+        );
+    }
+
+    #[test]
+    fn test_lone_surrogates_preserve() {
+        let options = ParseOptions {
+            lone_surrogates: LoneSurrogates::Preserve,
+            ..ParseOptions::default()
+        };
+        let input = r#"{"k\uDC00": ["a\uD800b\uD83D\uDE00", "plain"]}"#;
+        match json::parse_with_options(input, &options) {
+            Ok(json) => {
+                assert_eq!(2, json.raw_strings.len());
+                let ValueToken::ObjectToken { token, .. } = &*json.token else {
+                    panic!("Expected ObjectToken");
+                };
+                let pair = &token.members()[0];
+                assert_eq!("k\u{FFFD}", pair.key);
+                assert_eq!(Some(&b"k\xED\xB0\x80"[..]), json.raw_string(pair.key_span));
+                let ValueToken::ArrayToken { token, .. } = &*pair.value else {
+                    panic!("Expected ArrayToken");
+                };
+                match &*token.values[0] {
+                    ValueToken::StringToken { span, token, .. } => {
+                        assert_eq!("a\u{FFFD}b\u{1F600}", token);
+                        assert_eq!(
+                            Some(&b"a\xED\xA0\x80b\xF0\x9F\x98\x80"[..]),
+                            json.raw_string(*span)
+                        );
+                    }
+                    _ => panic!("Expected StringToken"),
+                }
+                assert_eq!(None, json.raw_string(token.values[1].span()));
+            }
+            Err(e) => panic!("{}", e),
+        }
+        match json::parse(r#""\uD800""#) {
+            Ok(_) => panic!("Should have failed"),
+            Err(e) => assert_eq!(ErrorKind::LoneSurrogate, e.kind),
+        }
+    }
+
+    #[test]
+    fn test_unicode_escape_multibyte_boundary() {
+        assert_eq!(
+            Err(ErrorKind::InvalidUnicodeEscape),
+            decode("\"\\u00\u{e9}\"", LoneSurrogates::Error)
+        );
+    }
+
+    fn decode_json5(input: &str) -> Result<String, ErrorKind> {
+        let options = ParseOptions {
+            dialect: Dialect::Json5,
+            ..ParseOptions::default()
+        };
+        match json::parse_with_options(input, &options) {
+            Ok(Json { token, .. }) => match *token {
+                ValueToken::StringToken { token, .. } => Ok(token),
+                _ => panic!("Expected StringToken"),
+            },
+            Err(e) => Err(e.kind),
+        }
+    }
+
+    #[test]
+    fn test_json5_single_quotes() {
+        assert_eq!(Ok("a\"b'c".to_string()), decode_json5(r#"'a"b\'c'"#));
+        assert_eq!(Ok("a'b".to_string()), decode_json5(r#""a'b""#));
+        assert_eq!(Err(ErrorKind::UnterminatedString), decode_json5(r#"'abc""#));
+    }
+
+    #[test]
+    fn test_json5_escapes() {
+        assert_eq!(
+            Ok("\u{b}\0A\u{e9}qz".to_string()),
+            decode_json5(r#""\v\0\x41\xE9\q\z""#)
+        );
+        assert_eq!(Ok("ab\tc".to_string()), decode_json5("'a\\\nb\\\r\n\tc'"));
+        for (input, kind) in [
+            (r#""\1""#, ErrorKind::UnexpectedEscapeCharacter),
+            (r#""\01""#, ErrorKind::UnexpectedEscapeCharacter),
+            (r#""\x4""#, ErrorKind::InvalidUnicodeEscape),
+            ("'a\nb'", ErrorKind::ControlCharacter),
+        ] {
+            assert_eq!(Err(kind), decode_json5(input), "{:?}", input);
+        }
+    }
 }
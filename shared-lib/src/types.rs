@@ -1,43 +1,238 @@
+use crate::error::PathSegment;
+use crate::ordered_map::{Member, OrderedMap};
+
 /// Box token to enable recursive enum types.
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Json {
     pub skip: usize,
     pub token: Box<ValueToken>,
+    /// Comments in source order. Empty unless
+    /// [`crate::ParseOptions::preserve_comments`] is set.
+    pub comments: Vec<Comment>,
+    /// Strings and keys containing lone surrogates, in source order. Empty
+    /// unless [`crate::ParseOptions::lone_surrogates`] is
+    /// [`crate::LoneSurrogates::Preserve`].
+    pub raw_strings: Vec<RawString>,
+}
+
+impl Json {
+    /// WTF-8 bytes of the string or key at `span`, if it contained a lone
+    /// surrogate kept under [`crate::LoneSurrogates::Preserve`].
+    pub fn raw_string(&self, span: Span) -> Option<&[u8]> {
+        self.raw_strings
+            .iter()
+            .find(|raw| raw.span == span)
+            .map(|raw| raw.wtf8.as_slice())
+    }
+}
+
+/// A decoded string as WTF-8: UTF-8 that may also encode lone UTF-16
+/// surrogates, as used for file names on Windows.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RawString {
+    /// Location of the quoted string, matching its token's or key's span.
+    pub span: Span,
+    pub wtf8: Vec<u8>,
 }
 
-#[derive(Debug)]
+/// `skip` counts the characters the parser consumed for the token, including
+/// leading whitespace; `span` locates the token itself in the source text.
+#[derive(Debug, Clone, PartialEq)]
 pub enum ValueToken {
-    ArrayToken { skip: usize, token: Array },
-    FalseToken { skip: usize, token: bool },
-    NullToken { skip: usize },
-    NumberToken { skip: usize, token: Number },
-    ObjectToken { skip: usize, token: Object },
-    PairToken { skip: usize, token: Pair },
-    StringToken { skip: usize, token: String },
-    TrueToken { skip: usize, token: bool },
+    ArrayToken {
+        skip: usize,
+        span: Span,
+        token: Array,
+    },
+    FalseToken {
+        skip: usize,
+        span: Span,
+        token: bool,
+    },
+    NullToken {
+        skip: usize,
+        span: Span,
+    },
+    NumberToken {
+        skip: usize,
+        span: Span,
+        token: Number,
+    },
+    ObjectToken {
+        skip: usize,
+        span: Span,
+        token: Object,
+    },
+    PairToken {
+        skip: usize,
+        span: Span,
+        token: Pair,
+    },
+    StringToken {
+        skip: usize,
+        span: Span,
+        token: String,
+    },
+    TrueToken {
+        skip: usize,
+        span: Span,
+        token: bool,
+    },
+}
+
+impl ValueToken {
+    pub fn span(&self) -> Span {
+        match self {
+            ValueToken::ArrayToken { span, .. }
+            | ValueToken::FalseToken { span, .. }
+            | ValueToken::NullToken { span, .. }
+            | ValueToken::NumberToken { span, .. }
+            | ValueToken::ObjectToken { span, .. }
+            | ValueToken::PairToken { span, .. }
+            | ValueToken::StringToken { span, .. }
+            | ValueToken::TrueToken { span, .. } => *span,
+        }
+    }
+}
+
+/// A comment kept from JSONC or JSON5 input, attached to the value nearest
+/// to it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comment {
+    pub kind: CommentKind,
+    /// Text between the delimiters, without `//`, `/*` or `*/`.
+    pub text: String,
+    pub span: Span,
+    /// Array element or object member the comment belongs to, or the
+    /// container itself for [`CommentPlacement::Inner`]. Empty for the root.
+    pub path: Vec<PathSegment>,
+    pub placement: CommentPlacement,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommentKind {
+    /// `// ...` up to the end of the line.
+    Line,
+    /// `/* ... */`.
+    Block,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommentPlacement {
+    /// On the lines before the value.
+    Leading,
+    /// After the value (and its comma) on the same line.
+    Trailing,
+    /// Inside an array or object after its last element, or inside an empty
+    /// one.
+    Inner,
+    /// Between an object member's key and its value.
+    AfterKey,
+    /// On the lines after the root value.
+    After,
+}
+
+/// A location in the source text. `offset` is in bytes; `line` and `column`
+/// are 1-based, with columns counted in characters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Position {
+    pub offset: usize,
+    pub line: usize,
+    pub column: usize,
 }
 
-#[derive(Debug)]
+impl Position {
+    /// The start of a text.
+    pub(crate) const START: Position = Position {
+        offset: 0,
+        line: 1,
+        column: 1,
+    };
+
+    /// Translate a position within a slice of a larger text into one within
+    /// the larger text, given where the slice begins.
+    pub(crate) fn offset_by(self, origin: Position) -> Position {
+        Position {
+            offset: origin.offset + self.offset,
+            line: origin.line + self.line - 1,
+            column: if self.line == 1 {
+                origin.column + self.column - 1
+            } else {
+                self.column
+            },
+        }
+    }
+}
+
+/// The source range of a token, from its first character up to but not
+/// including the character after it. Tokens built in code rather than parsed
+/// have an empty default span.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Span {
+    pub start: Position,
+    pub end: Position,
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct Array {
     /// Boxing is required because ValueToken is a recursive type.
     #[allow(clippy::vec_box)]
     pub values: Vec<Box<ValueToken>>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Number {
     pub value: f64,
     pub value_as_string: String,
 }
 
-#[derive(Debug)]
-pub struct Object {
-    pub members: Vec<Pair>,
-}
+/// Object members in source order, with a key index for O(1) lookup.
+pub type Object = OrderedMap<Pair>;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Pair {
     pub key: String,
+    /// Location of the quoted key.
+    pub key_span: Span,
     /// Box value to enable recursive enum types.
     pub value: Box<ValueToken>,
 }
+
+impl Pair {
+    pub fn new(key: String, value: Box<ValueToken>) -> Self {
+        Pair {
+            key,
+            key_span: Span::default(),
+            value,
+        }
+    }
+}
+
+impl Member for Pair {
+    type Value = ValueToken;
+    type Owned = Box<ValueToken>;
+
+    fn new(key: String, value: Box<ValueToken>) -> Self {
+        Pair::new(key, value)
+    }
+
+    fn key(&self) -> &str {
+        &self.key
+    }
+
+    fn value(&self) -> &ValueToken {
+        &self.value
+    }
+
+    fn value_mut(&mut self) -> &mut ValueToken {
+        &mut self.value
+    }
+
+    fn replace(&mut self, value: Box<ValueToken>) -> Box<ValueToken> {
+        std::mem::replace(&mut self.value, value)
+    }
+
+    fn into_value(self) -> Box<ValueToken> {
+        self.value
+    }
+}
@@ -1,9 +1,11 @@
 use crate::array::parse_array;
+use crate::cursor::Cursor;
+use crate::error::{ErrorKind, ParseError};
 use crate::number::parse_number;
 use crate::object::parse_object;
+use crate::options::Dialect;
 use crate::string::parse_string;
-use crate::types::ValueToken;
-use regex::Regex;
+use crate::types::{Span, ValueToken};
 
 enum Mode {
     Scanning,
@@ -17,146 +19,137 @@ enum Mode {
     End,
 }
 
-lazy_static! {
-    static ref DIGIT_OR_DASH: Regex = Regex::new(r"[-\d]").unwrap();
-    static ref WHITESPACE: Regex = Regex::new(r"[ \n\r\t]").unwrap();
-}
-
-pub fn parse_value(value: &str, delimiters: &str) -> Result<ValueToken, &'static str> {
+pub fn parse_value(cursor: &mut Cursor, delimiters: &[char]) -> Result<ValueToken, ParseError> {
     let mut mode = Mode::Scanning;
-    let mut pos: usize = 0;
-    let is_delimiter: &Regex = &Regex::new(delimiters).unwrap();
+    let start = cursor.chars();
+    let mut span_start = cursor.position();
 
-    while let Some(ch) = value.chars().nth(pos) {
+    while let Some(ch) = cursor.peek() {
         match mode {
             Mode::Scanning => {
-                let char = &ch.to_string()[..];
-
-                if WHITESPACE.is_match(char) {
-                    pos += 1;
+                span_start = cursor.position();
+                if cursor.is_trivia(ch) {
+                    cursor.skip_trivia(ch)?;
                 } else if ch == '[' {
                     mode = Mode::Array;
                 } else if ch == 'f' {
                     mode = Mode::False;
                 } else if ch == 'n' {
                     mode = Mode::Null;
-                } else if DIGIT_OR_DASH.is_match(char) {
+                } else if ch == '-'
+                    || ch.is_ascii_digit()
+                    || (cursor.dialect() == Dialect::Json5 && matches!(ch, '+' | '.' | 'I' | 'N'))
+                {
                     mode = Mode::Number;
                 } else if ch == '{' {
                     mode = Mode::Object;
-                } else if ch == '"' {
+                } else if ch == '"' || (ch == '\'' && cursor.dialect() == Dialect::Json5) {
                     mode = Mode::StringValue;
                 } else if ch == 't' {
                     mode = Mode::True;
-                } else if !delimiters.is_empty() && is_delimiter.is_match(char) {
+                } else if delimiters.contains(&ch) {
                     mode = Mode::End;
                 } else {
-                    return Err("Unexpected character");
+                    return Err(ParseError::at(ErrorKind::UnexpectedCharacter, cursor.pos()));
                 }
             }
-            Mode::Array => {
-                let slice: String = value.chars().skip(pos).collect();
-                match parse_array(&slice) {
-                    Ok(ValueToken::ArrayToken { skip, token }) => {
-                        return Ok(ValueToken::ArrayToken {
-                            skip: pos + skip,
-                            token,
-                        });
-                    }
-                    Ok(_) => {
-                        return Err("Expected array");
-                    }
-                    Err(e) => {
-                        return Err(e);
-                    }
+            Mode::Array => match parse_array(cursor)? {
+                ValueToken::ArrayToken { span, token, .. } => {
+                    return Ok(ValueToken::ArrayToken {
+                        skip: cursor.chars() - start,
+                        span,
+                        token,
+                    });
                 }
-            }
+                _ => {
+                    return Err(ParseError::at(ErrorKind::Expected("array"), cursor.pos()));
+                }
+            },
             Mode::False => {
-                let slice: String = value.chars().skip(pos).take(5).collect();
-                if &slice == "false" {
+                if cursor.eat("false") {
                     return Ok(ValueToken::FalseToken {
-                        skip: pos + 5,
+                        skip: cursor.chars() - start,
+                        span: Span {
+                            start: span_start,
+                            end: cursor.position(),
+                        },
                         token: false,
                     });
                 } else {
-                    return Err("Expected 'false'");
+                    return Err(ParseError::at(ErrorKind::Expected("'false'"), cursor.pos()));
                 }
             }
             Mode::Null => {
-                let slice: String = value.chars().skip(pos).take(4).collect();
-                if &slice == "null" {
-                    return Ok(ValueToken::NullToken { skip: pos + 4 });
+                if cursor.eat("null") {
+                    return Ok(ValueToken::NullToken {
+                        skip: cursor.chars() - start,
+                        span: Span {
+                            start: span_start,
+                            end: cursor.position(),
+                        },
+                    });
                 } else {
-                    return Err("Expected 'null'");
+                    return Err(ParseError::at(ErrorKind::Expected("'null'"), cursor.pos()));
                 }
             }
-            Mode::Number => {
-                let slice: String = value.chars().skip(pos).collect();
-                match parse_number(&slice, delimiters) {
-                    Ok(ValueToken::NumberToken { skip, token }) => {
-                        return Ok(ValueToken::NumberToken {
-                            skip: pos + skip,
-                            token,
-                        });
-                    }
-                    Ok(_) => {
-                        return Err("Expected number");
-                    }
-                    Err(e) => {
-                        return Err(e);
-                    }
+            Mode::Number => match parse_number(cursor)? {
+                ValueToken::NumberToken { span, token, .. } => {
+                    return Ok(ValueToken::NumberToken {
+                        skip: cursor.chars() - start,
+                        span,
+                        token,
+                    });
                 }
-            }
-            Mode::Object => {
-                let slice: String = value.chars().skip(pos).collect();
-                match parse_object(&slice) {
-                    Ok(ValueToken::ObjectToken { skip, token }) => {
-                        return Ok(ValueToken::ObjectToken {
-                            skip: pos + skip,
-                            token,
-                        });
-                    }
-                    Ok(_) => {
-                        return Err("Expected number");
-                    }
-                    Err(e) => {
-                        return Err(e);
-                    }
+                _ => {
+                    return Err(ParseError::at(ErrorKind::Expected("number"), cursor.pos()));
                 }
-            }
-            Mode::StringValue => {
-                let slice: String = value.chars().skip(pos).collect();
-                match parse_string(&slice) {
-                    Ok(ValueToken::StringToken { skip, token }) => {
-                        return Ok(ValueToken::StringToken {
-                            skip: pos + skip,
-                            token,
-                        });
-                    }
-                    Ok(_) => {
-                        return Err("Expected string");
-                    }
-                    Err(e) => {
-                        return Err(e);
-                    }
+            },
+            Mode::Object => match parse_object(cursor)? {
+                ValueToken::ObjectToken { span, token, .. } => {
+                    return Ok(ValueToken::ObjectToken {
+                        skip: cursor.chars() - start,
+                        span,
+                        token,
+                    });
                 }
-            }
+                _ => {
+                    return Err(ParseError::at(ErrorKind::Expected("object"), cursor.pos()));
+                }
+            },
+            Mode::StringValue => match parse_string(cursor)? {
+                ValueToken::StringToken { span, token, .. } => {
+                    return Ok(ValueToken::StringToken {
+                        skip: cursor.chars() - start,
+                        span,
+                        token,
+                    });
+                }
+                _ => {
+                    return Err(ParseError::at(ErrorKind::Expected("string"), cursor.pos()));
+                }
+            },
             Mode::True => {
-                let slice: String = value.chars().skip(pos).take(4).collect();
-                if &slice == "true" {
+                if cursor.eat("true") {
                     return Ok(ValueToken::TrueToken {
-                        skip: pos + 4,
+                        skip: cursor.chars() - start,
+                        span: Span {
+                            start: span_start,
+                            end: cursor.position(),
+                        },
                         token: true,
                     });
                 } else {
-                    return Err("Expected 'true'");
+                    return Err(ParseError::at(ErrorKind::Expected("'true'"), cursor.pos()));
                 }
             }
             Mode::End => break,
         }
     }
 
-    Err("Expected value token")
+    Err(ParseError::at(
+        ErrorKind::Expected("value token"),
+        cursor.pos(),
+    ))
 }
 
 #[cfg(test)]
@@ -167,12 +160,12 @@ mod tests {
     #[test]
     fn test_true() {
         match json::parse("true") {
-            Ok(Json { skip, token }) => {
+            Ok(Json { skip, token, .. }) => {
                 assert_eq!(4, skip);
 
                 let unboxed = *token;
                 match unboxed {
-                    ValueToken::TrueToken { skip, token } => {
+                    ValueToken::TrueToken { skip, token, .. } => {
                         assert_eq!(4, skip);
                         assert!(token);
                     }
@@ -190,12 +183,12 @@ mod tests {
     #[test]
     fn test_false() {
         match json::parse("false") {
-            Ok(Json { skip, token }) => {
+            Ok(Json { skip, token, .. }) => {
                 assert_eq!(5, skip);
 
                 let unboxed = *token;
                 match unboxed {
-                    ValueToken::FalseToken { skip, token } => {
+                    ValueToken::FalseToken { skip, token, .. } => {
                         assert_eq!(5, skip);
                         assert!(!token);
                     }
@@ -213,12 +206,12 @@ mod tests {
     #[test]
     fn test_null() {
         match json::parse("null") {
-            Ok(Json { skip, token }) => {
+            Ok(Json { skip, token, .. }) => {
                 assert_eq!(4, skip);
 
                 let unboxed = *token;
                 match unboxed {
-                    ValueToken::NullToken { skip } => {
+                    ValueToken::NullToken { skip, .. } => {
                         assert_eq!(4, skip);
                     }
                     _ => {